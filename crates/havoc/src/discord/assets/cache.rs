@@ -47,6 +47,12 @@ impl AssetCache {
         raw_content_inner(&mut self.raw_content, asset).await
     }
 
+    /// Stores the raw content of an asset that was obtained elsewhere, so that
+    /// it isn't fetched.
+    pub fn insert_raw_content(&mut self, asset: &FeAsset, content: AssetContent) {
+        self.raw_content.insert(asset.name.clone(), content);
+    }
+
     /// Returns the preprocessed content of an asset, fetching and caching both
     /// the raw and preprocessed work if necessary.
     pub async fn preprocessed_content(
@@ -1,11 +1,9 @@
 //! CSS class mapping dumping.
 
 use crate::{
-    artifact::Artifact,
-    discord::{AssetCache, AssetsExt, RootScript},
-    dump::{DumpError, DumpResult},
+    discord::RootScript,
+    dump::{AnalysisContext, DumpError, DumpResult},
     parse::{ModuleId, ParseError},
-    scrape::ScrapeError,
 };
 
 use super::Dump;
@@ -100,27 +98,10 @@ pub fn walk_classes_chunk(script: &ast::Script) -> Result<ClassModuleMap, ParseE
 
 pub struct CSSClasses;
 
-#[async_trait::async_trait]
 impl Dump for CSSClasses {
-    async fn dump(
-        &mut self,
-        artifact: &(dyn Artifact + Sync),
-        cache: &mut AssetCache,
-    ) -> Result<DumpResult, DumpError> {
-        let classes_asset = artifact
-            .assets()
-            .iter()
-            .find_root_script(RootScript::Classes)
-            .ok_or(ScrapeError::MissingBranchPageAssets(
-                "failed to locate root classes script; discord has updated their /channels/@me",
-            ))?;
-
-        let content = cache.raw_content(classes_asset).await?;
-        let classes_js = std::str::from_utf8(content)
-            .map_err(ScrapeError::Decoding)?
-            .to_owned();
-        let script = crate::parse::parse_script(classes_js)?;
-        let mapping = walk_classes_chunk(&script)?;
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let classes = cx.root_script(RootScript::Classes)?;
+        let mapping = walk_classes_chunk(&classes.script)?;
 
         Ok(DumpResult::from_serializable(&mapping, "classes")?)
     }
@@ -0,0 +1,493 @@
+//! React component dumping.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult, LocatedModule},
+    parse::{
+        util::{member_prop_name, object_props, prop_name_str, DisplayNameVisitor},
+        walk_module_interface, ChunkId, ModuleId, ModuleInterface,
+    },
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// How a component is defined.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentKind {
+    Function,
+    Class,
+
+    /// A component wrapped with `forwardRef`.
+    ForwardRef,
+
+    /// A component wrapped with `memo`.
+    Memo,
+}
+
+/// A React component.
+#[derive(Serialize, Debug)]
+pub struct Component {
+    /// The display name of the component, if one could be found.
+    pub display_name: Option<String>,
+    pub kind: ComponentKind,
+
+    /// The module that the component is defined in.
+    pub module_id: ModuleId,
+
+    /// The ID of the chunk containing the component's module, if it was found
+    /// in a deep chunk.
+    pub chunk_id: Option<ChunkId>,
+
+    /// The names that the component's module exports it as.
+    pub exports: BTreeSet<String>,
+
+    /// The prop keys passed to the component where it's rendered.
+    pub props: BTreeSet<String>,
+
+    /// The modules that render the component.
+    pub rendered_by: BTreeSet<ModuleId>,
+}
+
+/// What a call to `jsx` or `createElement` renders.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RenderTarget {
+    /// A local variable, e.g. `o` in `jsx(o, {})`.
+    Local(String),
+
+    /// An export of another module, e.g. `a.Z` in `jsx(a.Z, {})` where `a`
+    /// is bound to `n(123)`.
+    Export { module_id: ModuleId, name: String },
+}
+
+/// The components defined within a module and the components that it renders.
+#[derive(Debug, Default)]
+pub struct ModuleComponents {
+    /// Components keyed by the local variable that they're bound to.
+    defined: HashMap<String, (ComponentKind, Option<String>)>,
+
+    /// Render call sites, alongside the prop keys passed at each of them.
+    rendered: Vec<(RenderTarget, BTreeSet<String>)>,
+
+    interface: ModuleInterface,
+}
+
+fn is_render_function(name: &str) -> bool {
+    matches!(name, "jsx" | "jsxs" | "jsxDEV" | "createElement")
+}
+
+/// Returns the name of the function being called, looking through the
+/// `(0, r.jsx)(...)` calls emitted by bundlers.
+fn called_function_name(call: &ast::CallExpr) -> Option<&str> {
+    let ast::Callee::Expr(callee) = &call.callee else {
+        return None;
+    };
+
+    let mut callee = &**callee;
+    loop {
+        callee = match callee {
+            ast::Expr::Paren(ast::ParenExpr { expr, .. }) => expr,
+            ast::Expr::Seq(ast::SeqExpr { exprs, .. }) => exprs.last()?,
+            ast::Expr::Ident(ident) => return Some(&ident.sym),
+            ast::Expr::Member(member) => return member_prop_name(member),
+            _ => return None,
+        };
+    }
+}
+
+/// Returns the kind of component that a wrapper call creates, e.g.
+/// `forwardRef(...)` or `memo(...)`.
+fn wrapper_kind(expr: &ast::Expr) -> Option<ComponentKind> {
+    let ast::Expr::Call(call) = expr else {
+        return None;
+    };
+
+    match called_function_name(call)? {
+        "forwardRef" => Some(ComponentKind::ForwardRef),
+        "memo" => Some(ComponentKind::Memo),
+        _ => None,
+    }
+}
+
+/// Returns whether a class has a `render` method.
+fn has_render_method(class: &ast::Class) -> bool {
+    class.super_class.is_some()
+        && class.body.iter().any(|member| match member {
+            ast::ClassMember::Method(method) => prop_name_str(&method.key) == Some("render"),
+            _ => false,
+        })
+}
+
+/// What a render call site refers to, before resolving imports.
+enum RenderedRef {
+    /// e.g. `o` in `jsx(o, {})`
+    Ident(String),
+
+    /// e.g. `a.Z` in `jsx(a.Z, {})`
+    Member { object: String, property: String },
+}
+
+fn rendered_ref(expr: &ast::Expr) -> Option<RenderedRef> {
+    match expr {
+        ast::Expr::Ident(ident) => Some(RenderedRef::Ident(ident.sym.to_string())),
+        ast::Expr::Member(member) => match &*member.obj {
+            ast::Expr::Ident(object) => Some(RenderedRef::Member {
+                object: object.sym.to_string(),
+                property: member_prop_name(member)?.to_owned(),
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct ComponentVisitor {
+    /// Local variables bound to functions, which are only considered to be
+    /// components once they're rendered or given a display name.
+    functions: BTreeSet<String>,
+    defined: HashMap<String, ComponentKind>,
+    rendered: Vec<(RenderedRef, BTreeSet<String>)>,
+}
+
+impl Visit for ComponentVisitor {
+    fn visit_fn_decl(&mut self, n: &ast::FnDecl) {
+        self.functions.insert(n.ident.sym.to_string());
+        n.visit_children_with(self);
+    }
+
+    fn visit_class_decl(&mut self, n: &ast::ClassDecl) {
+        if has_render_method(&n.class) {
+            self.defined
+                .insert(n.ident.sym.to_string(), ComponentKind::Class);
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, n: &ast::VarDeclarator) {
+        if_chain::if_chain! {
+            if let ast::Pat::Ident(binding) = &n.name;
+            if let Some(init) = n.init.as_deref();
+            then {
+                let name = binding.id.sym.to_string();
+
+                match init {
+                    ast::Expr::Fn(_) | ast::Expr::Arrow(_) => {
+                        self.functions.insert(name);
+                    }
+                    ast::Expr::Class(ast::ClassExpr { class, .. }) if has_render_method(class) => {
+                        self.defined.insert(name, ComponentKind::Class);
+                    }
+                    _ => {
+                        if let Some(kind) = wrapper_kind(init) {
+                            self.defined.insert(name, kind);
+                        }
+                    }
+                }
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        // jsx(Component, { prop: ... }) or createElement(Component, { ... })
+        if_chain::if_chain! {
+            if called_function_name(n).is_some_and(is_render_function);
+            if let Some(target) = n.args.first();
+            if let Some(target) = rendered_ref(&target.expr);
+            then {
+                let props = match n.args.get(1).map(|arg| &*arg.expr) {
+                    Some(ast::Expr::Object(props)) => object_props(props)
+                        .map(|(key, _)| key.to_owned())
+                        .collect(),
+                    _ => BTreeSet::new(),
+                };
+
+                self.rendered.push((target, props));
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the components defined within a module and the components that it
+/// renders.
+pub fn walk_module_components(module: &LocatedModule<'_>) -> ModuleComponents {
+    let mut visitor = ComponentVisitor::default();
+    module.module.func.visit_with(&mut visitor);
+
+    let mut display_name_visitor = DisplayNameVisitor::default();
+    module.module.func.visit_with(&mut display_name_visitor);
+
+    let interface = walk_module_interface(&module.module);
+
+    let mut defined: HashMap<String, (ComponentKind, Option<String>)> = visitor
+        .defined
+        .into_iter()
+        .map(|(local, kind)| (local, (kind, None)))
+        .collect();
+
+    for display_name in display_name_visitor.display_names {
+        // Display names are given to plenty of things besides components,
+        // such as Flux stores.
+        let Some(target) = display_name
+            .target
+            .filter(|target| defined.contains_key(target) || visitor.functions.contains(target))
+        else {
+            continue;
+        };
+
+        let kind = defined
+            .get(&target)
+            .map_or(ComponentKind::Function, |(kind, _)| *kind);
+        defined.insert(target, (kind, Some(display_name.name)));
+    }
+
+    let rendered = visitor
+        .rendered
+        .into_iter()
+        .filter_map(|(target, props)| {
+            let target = match target {
+                RenderedRef::Ident(local) => {
+                    if !defined.contains_key(&local) && !visitor.functions.contains(&local) {
+                        return None;
+                    }
+
+                    // Functions are only components if they're rendered.
+                    defined
+                        .entry(local.clone())
+                        .or_insert((ComponentKind::Function, None));
+                    RenderTarget::Local(local)
+                }
+                RenderedRef::Member { object, property } => RenderTarget::Export {
+                    module_id: *interface.imports.get(&object)?,
+                    name: property,
+                },
+            };
+
+            Some((target, props))
+        })
+        .collect();
+
+    ModuleComponents {
+        defined,
+        rendered,
+        interface,
+    }
+}
+
+/// Finds the components within a set of modules, attributing the props passed
+/// at render call sites across modules to the components being rendered.
+pub fn collect_components(modules: &[LocatedModule<'_>]) -> Vec<Component> {
+    let walked: BTreeMap<ModuleId, (ModuleComponents, &LocatedModule<'_>)> = modules
+        .iter()
+        .map(|module| (module.id(), (walk_module_components(module), module)))
+        .collect();
+
+    let mut components: BTreeMap<(ModuleId, String), Component> = BTreeMap::new();
+
+    for (module_id, (module_components, module)) in &walked {
+        let exports_by_local = module_components.interface.exports.iter().fold(
+            HashMap::<&str, BTreeSet<String>>::new(),
+            |mut exports, (name, local)| {
+                exports
+                    .entry(local.as_str())
+                    .or_default()
+                    .insert(name.clone());
+                exports
+            },
+        );
+
+        for (local, (kind, display_name)) in &module_components.defined {
+            components.insert(
+                (*module_id, local.clone()),
+                Component {
+                    display_name: display_name.clone(),
+                    kind: *kind,
+                    module_id: *module_id,
+                    chunk_id: module.chunk_id,
+                    exports: exports_by_local
+                        .get(local.as_str())
+                        .cloned()
+                        .unwrap_or_default(),
+                    props: BTreeSet::new(),
+                    rendered_by: BTreeSet::new(),
+                },
+            );
+        }
+    }
+
+    for (rendering_module_id, (module_components, _)) in &walked {
+        for (target, props) in &module_components.rendered {
+            let key = match target {
+                RenderTarget::Local(local) => (*rendering_module_id, local.clone()),
+                RenderTarget::Export { module_id, name } => {
+                    let Some(local) = walked
+                        .get(module_id)
+                        .and_then(|(exporter, _)| exporter.interface.exports.get(name))
+                    else {
+                        continue;
+                    };
+                    (*module_id, local.clone())
+                }
+            };
+
+            if let Some(component) = components.get_mut(&key) {
+                component.props.extend(props.iter().cloned());
+                component.rendered_by.insert(*rendering_module_id);
+            }
+        }
+    }
+
+    components.into_values().collect()
+}
+
+pub struct Components;
+
+impl Dump for Components {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let modules = cx.modules();
+        let components = collect_components(&modules);
+
+        tracing::info!(
+            "found {} component(s) ({} with display names) across {} module(s)",
+            components.len(),
+            components
+                .iter()
+                .filter(|component| component.display_name.is_some())
+                .count(),
+            modules.len()
+        );
+
+        Ok(DumpResult::from_serializable(&components, "components")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::ParsedScript;
+
+    fn chunk(modules: &str) -> ParsedScript {
+        ParsedScript::test_chunk(&format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ {} }}]);",
+            modules
+        ))
+    }
+
+    fn find<'c>(components: &'c [Component], display_name: &str) -> &'c Component {
+        components
+            .iter()
+            .find(|component| component.display_name.as_deref() == Some(display_name))
+            .unwrap_or_else(|| panic!("no component named {}", display_name))
+    }
+
+    fn set<T: Ord + Clone>(items: &[T]) -> BTreeSet<T> {
+        items.iter().cloned().collect()
+    }
+
+    #[test]
+    fn detects_component_kinds() {
+        let script = chunk(
+            r#"10: (e, t, n) => {
+                var r = n(1);
+                let i = r.forwardRef((e, t) => null);
+                i.displayName = "Input";
+                let o = (0, r.memo)(function () { return null; });
+                o.displayName = "Avatar";
+                class a extends r.PureComponent {
+                    render() { return null; }
+                }
+                a.displayName = "Modal";
+                class s extends r.Store {}
+                s.displayName = "ModalStore";
+                function l() { return null; }
+                l.displayName = "Spacer";
+                function u() { return null; }
+            }"#,
+        );
+        let components = collect_components(&script.test_modules());
+
+        let kinds = components
+            .iter()
+            .map(|component| (component.display_name.as_deref(), component.kind))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            kinds,
+            set(&[
+                (Some("Avatar"), ComponentKind::Memo),
+                (Some("Input"), ComponentKind::ForwardRef),
+                (Some("Modal"), ComponentKind::Class),
+                (Some("Spacer"), ComponentKind::Function),
+            ])
+        );
+    }
+
+    #[test]
+    fn attributes_props_across_modules() {
+        let script = chunk(
+            r#"10: (e, t, n) => {
+                n.d(t, { Z: () => o });
+                var r = n(1);
+                function o(e) { return null; }
+                o.displayName = "Button";
+            },
+            11: (e, t, n) => {
+                var r = n(1), a = n(10);
+                function s() {
+                    return (0, r.jsx)(a.Z, { size: 1, onClick: s });
+                }
+                s.displayName = "Toolbar";
+            },
+            12: (e, t, n) => {
+                var r = n(1), a = n(10);
+                t.Z = () => (0, r.jsxs)(a.Z, { look: "filled", children: [] });
+            }"#,
+        );
+        let components = collect_components(&script.test_modules());
+
+        let button = find(&components, "Button");
+        assert_eq!(button.module_id, 10);
+        assert_eq!(button.exports, set(&["Z".to_owned()]));
+        assert_eq!(
+            button.props,
+            set(&[
+                "children".to_owned(),
+                "look".to_owned(),
+                "onClick".to_owned(),
+                "size".to_owned(),
+            ])
+        );
+        assert_eq!(button.rendered_by, set(&[11, 12]));
+
+        let toolbar = find(&components, "Toolbar");
+        assert!(toolbar.exports.is_empty());
+        assert!(toolbar.rendered_by.is_empty());
+    }
+
+    #[test]
+    fn treats_rendered_functions_as_components() {
+        let script = chunk(
+            r#"10: (e, t, n) => {
+                var r = n(1);
+                let o = (e) => null;
+                function i() { return null; }
+                t.Z = () => (0, r.createElement)(o, { id: 1 });
+            }"#,
+        );
+        let components = collect_components(&script.test_modules());
+
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].display_name, None);
+        assert_eq!(components[0].kind, ComponentKind::Function);
+        assert_eq!(components[0].props, set(&["id".to_owned()]));
+        assert_eq!(components[0].rendered_by, set(&[10]));
+    }
+}
@@ -0,0 +1,366 @@
+//! Fetched and parsed assets shared between dumpers.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+use crate::{
+    artifact::Artifact,
+    discord::{AssetCache, AssetsExt, FeAsset, RootScript},
+    dump::{registry::RequiredAsset, DumpError},
+    parse::{parse_scripts, ChunkId, ModuleId, ParseError, WebpackChunk, WebpackModule},
+    scrape::{extract_assets_from_chunk_loader, ScrapeError},
+};
+
+extern crate swc_ecma_ast as ast;
+
+/// A script asset alongside its source and AST.
+pub struct ParsedScript {
+    pub asset: FeAsset,
+    pub source: String,
+    pub script: ast::Script,
+}
+
+impl ParsedScript {
+    /// Parses the source of a script asset.
+    pub fn parse(asset: FeAsset, source: String) -> Result<Self, ParseError> {
+        let script = crate::parse::parse_script(&source)?;
+
+        Ok(Self {
+            asset,
+            source,
+            script,
+        })
+    }
+
+    /// Walks this script as a Webpack chunk.
+    pub fn webpack_chunk(&self) -> Result<WebpackChunk<'_>, ParseError> {
+        crate::parse::walk_webpack_chunk(&self.script)
+    }
+
+    /// Returns the source code of a module contained within this script.
+    pub fn module_source(&self, module: &WebpackModule<'_>) -> &str {
+        let span = module.func.span();
+
+        // swc's spans seem to start at one.
+        let module_beginning = span.lo.0 as usize - 1;
+        let module_end = span.hi.0 as usize - 1;
+        &self.source[module_beginning..module_end]
+    }
+}
+
+/// The assets of an artifact that dumpers are interested in, fetched and parsed
+/// ahead of time.
+///
+/// Each asset is parsed at most once, no matter how many dumpers require it.
+/// Because the context is immutable once prepared, it can be shared between
+/// dumpers running on different threads.
+pub struct AnalysisContext<'a> {
+    pub artifact: &'a (dyn Artifact + Sync),
+    scripts: HashMap<String, ParsedScript>,
+    root_scripts: HashMap<RootScript, String>,
+    deep_chunks: Option<Vec<(ChunkId, String)>>,
+}
+
+/// The assets of an artifact that dumpers are interested in, fetched but not
+/// yet parsed.
+///
+/// Parsing is CPU-bound and blocks, so it's kept separate from fetching to
+/// let callers decide where it happens (see [`FetchedAssets::parse`]).
+pub struct FetchedAssets<'a> {
+    cx: AnalysisContext<'a>,
+    assets: Vec<FeAsset>,
+    sources: Vec<(String, String)>,
+}
+
+impl<'a> FetchedAssets<'a> {
+    /// Parses the fetched assets in parallel (see [`parse_scripts`]).
+    ///
+    /// This blocks the current thread until every script has been parsed. On
+    /// a multi-threaded Tokio runtime, call it within
+    /// `tokio::task::block_in_place`.
+    pub fn parse(self) -> Result<AnalysisContext<'a>, DumpError> {
+        let FetchedAssets {
+            mut cx,
+            assets,
+            sources,
+        } = self;
+
+        let labelled = sources
+            .iter()
+            .map(|(filename, source)| (filename, source.as_str()))
+            .collect::<Vec<_>>();
+        let parsed = parse_scripts(&labelled);
+
+        for ((asset, (_, source)), script) in assets.into_iter().zip(sources).zip(parsed) {
+            cx.scripts.insert(
+                asset.name.clone(),
+                ParsedScript {
+                    asset,
+                    source,
+                    script: script?,
+                },
+            );
+        }
+
+        Ok(cx)
+    }
+}
+
+impl<'a> AnalysisContext<'a> {
+    /// Fetches and parses the assets of an artifact needed to satisfy some
+    /// requirements.
+    ///
+    /// Assets are fetched one by one, then parsed on the current thread, which
+    /// blocks until parsing is done. Callers on a multi-threaded runtime should
+    /// use [`AnalysisContext::fetch`] and parse the result within
+    /// `tokio::task::block_in_place` instead.
+    pub async fn prepare(
+        artifact: &'a (dyn Artifact + Sync),
+        cache: &mut AssetCache,
+        requirements: &[RequiredAsset],
+    ) -> Result<AnalysisContext<'a>, DumpError> {
+        Self::fetch(artifact, cache, requirements).await?.parse()
+    }
+
+    /// Fetches the assets of an artifact needed to satisfy some requirements,
+    /// one by one, without parsing them.
+    pub async fn fetch(
+        artifact: &'a (dyn Artifact + Sync),
+        cache: &mut AssetCache,
+        requirements: &[RequiredAsset],
+    ) -> Result<FetchedAssets<'a>, DumpError> {
+        let mut cx = AnalysisContext {
+            artifact,
+            scripts: HashMap::new(),
+            root_scripts: HashMap::new(),
+            deep_chunks: None,
+        };
+
+        let mut fetching: Vec<FeAsset> = vec![];
+
+        // The classes script has always been dumped from its raw content, so
+        // it's kept away from any preprocessors.
+        let mut unpreprocessed: HashSet<String> = HashSet::new();
+
+        for requirement in requirements {
+            let root_script = match requirement {
+                RequiredAsset::Entrypoint => RootScript::Entrypoint,
+                RequiredAsset::Classes => RootScript::Classes,
+                RequiredAsset::DeepChunks => {
+                    if cx.deep_chunks.is_none() {
+                        let chunks =
+                            extract_assets_from_chunk_loader(artifact.assets(), cache).await?;
+                        let mut deep_chunks = chunks
+                            .iter()
+                            .map(|(chunk_id, asset)| (*chunk_id, asset.name.clone()))
+                            .collect::<Vec<_>>();
+                        deep_chunks.sort();
+                        cx.deep_chunks = Some(deep_chunks);
+                        fetching.extend(chunks.into_iter().map(|(_, asset)| asset));
+                    }
+                    continue;
+                }
+            };
+
+            let asset = artifact
+                .assets()
+                .iter()
+                .find_root_script(root_script)
+                .ok_or(ScrapeError::MissingBranchPageAssets(
+                    "failed to locate root script; discord has updated their HTML",
+                ))?;
+            cx.root_scripts.insert(root_script, asset.name.clone());
+            if root_script == RootScript::Classes {
+                unpreprocessed.insert(asset.name.clone());
+            }
+            fetching.push(asset.clone());
+        }
+
+        let mut fetched: HashSet<String> = HashSet::new();
+        let mut sources: Vec<(String, String)> = vec![];
+        let mut assets: Vec<FeAsset> = vec![];
+
+        for asset in fetching {
+            if !fetched.insert(asset.name.clone()) {
+                continue;
+            }
+
+            let content = if unpreprocessed.contains(&asset.name) {
+                cache.raw_content(&asset).await?
+            } else {
+                cache
+                    .preprocessed_content(&asset)
+                    .await?
+                    .map_err(DumpError::Preprocessing)?
+            };
+            let source = std::str::from_utf8(content)
+                .map_err(ScrapeError::Decoding)?
+                .to_owned();
+
+            sources.push((asset.filename(), source));
+            assets.push(asset);
+        }
+
+        Ok(FetchedAssets {
+            cx,
+            assets,
+            sources,
+        })
+    }
+
+    /// Returns a parsed root script.
+    ///
+    /// The root script must have been required when preparing the context.
+    pub fn root_script(&self, root_script: RootScript) -> Result<&ParsedScript, DumpError> {
+        self.root_scripts
+            .get(&root_script)
+            .map(|name| &self.scripts[name])
+            .ok_or_else(|| DumpError::Unprepared(root_script.to_string()))
+    }
+
+    /// Returns the parsed script chunks referenced by the chunk loader.
+    ///
+    /// Deep chunks must have been required when preparing the context.
+    pub fn deep_chunks(
+        &self,
+    ) -> Result<impl Iterator<Item = (ChunkId, &ParsedScript)> + '_, DumpError> {
+        let chunks = self
+            .deep_chunks
+            .as_ref()
+            .ok_or_else(|| DumpError::Unprepared(RequiredAsset::DeepChunks.to_string()))?;
+
+        Ok(chunks
+            .iter()
+            .map(|(chunk_id, name)| (*chunk_id, &self.scripts[name])))
+    }
+
+    /// Returns every prepared script in a stable order: the entrypoint, then
+    /// the other root scripts, then deep chunks by chunk ID.
+    ///
+    /// Modules that appear in multiple scripts are attributed to the first of
+    /// them in this order, so it mustn't depend on hashing.
+    fn ordered_scripts(&self) -> Vec<&ParsedScript> {
+        let mut root_scripts = self.root_scripts.iter().collect::<Vec<_>>();
+        root_scripts.sort_by_key(|(root_script, _)| match root_script {
+            RootScript::Entrypoint => 0,
+            other => other.assumed_index() + 1,
+        });
+
+        let names = root_scripts
+            .into_iter()
+            .map(|(_, name)| name)
+            .chain(self.deep_chunks.iter().flatten().map(|(_, name)| name));
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        names
+            .filter(|name| seen.insert(name.as_str()))
+            .map(|name| &self.scripts[name])
+            .collect()
+    }
+
+    /// Walks every prepared script as a Webpack chunk, in the order described
+    /// by [`AnalysisContext::ordered_scripts`].
+    ///
+    /// Scripts that can't be walked are skipped.
+    pub fn webpack_chunks(&self) -> Vec<(&ParsedScript, WebpackChunk<'_>)> {
+        self.ordered_scripts()
+            .into_iter()
+            .filter_map(|script| match script.webpack_chunk() {
+                Ok(chunk) => Some((script, chunk)),
+                Err(err) => {
+                    tracing::warn!(asset = %script.asset.filename(), "failed to walk script: {}", err);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Walks every prepared script as a Webpack chunk, returning all of the
+    /// modules within them ordered by module ID.
+    ///
+    /// Modules that appear in multiple scripts are only returned once, located
+    /// in the first script that contains them (see
+    /// [`AnalysisContext::webpack_chunks`]). Scripts that can't be walked are
+    /// skipped.
+    pub fn modules(&self) -> Vec<LocatedModule<'_>> {
+        let chunk_ids: HashMap<&str, ChunkId> = self
+            .deep_chunks
+            .iter()
+            .flatten()
+            .map(|(chunk_id, name)| (name.as_str(), *chunk_id))
+            .collect();
+
+        let mut modules: BTreeMap<ModuleId, LocatedModule<'_>> = BTreeMap::new();
+
+        for (script, chunk) in self.webpack_chunks() {
+            for (module_id, module) in chunk.modules {
+                modules.entry(module_id).or_insert(LocatedModule {
+                    script,
+                    chunk_id: chunk_ids.get(script.asset.name.as_str()).copied(),
+                    module,
+                });
+            }
+        }
+
+        modules.into_values().collect()
+    }
+}
+
+/// A Webpack module alongside the script that contains it.
+pub struct LocatedModule<'cx> {
+    pub script: &'cx ParsedScript,
+
+    /// The ID of the chunk containing this module, if it was found in a deep
+    /// chunk.
+    pub chunk_id: Option<ChunkId>,
+
+    pub module: WebpackModule<'cx>,
+}
+
+impl LocatedModule<'_> {
+    /// Returns the module's ID.
+    pub fn id(&self) -> ModuleId {
+        self.module.id
+    }
+
+    /// Returns the source code of the module.
+    pub fn source(&self) -> &str {
+        self.script.module_source(&self.module)
+    }
+
+    /// Converts the span of a node within the module into a byte range within
+    /// the module's source.
+    pub fn source_range(&self, span: swc_common::Span) -> Range<usize> {
+        let module_start = self.module.func.span().lo.0;
+        (span.lo.0 - module_start) as usize..(span.hi.0 - module_start) as usize
+    }
+}
+
+#[cfg(test)]
+impl ParsedScript {
+    /// Parses the source of a Webpack chunk, for testing dumpers.
+    pub(crate) fn test_chunk(source: &str) -> Self {
+        let asset = FeAsset {
+            name: "chunk".to_owned(),
+            typ: crate::discord::FeAssetType::Js,
+        };
+        Self::parse(asset, source.to_owned()).unwrap()
+    }
+
+    /// Returns the modules of this script as a Webpack chunk, ordered by ID.
+    pub(crate) fn test_modules(&self) -> Vec<LocatedModule<'_>> {
+        let mut modules = self
+            .webpack_chunk()
+            .unwrap()
+            .modules
+            .into_values()
+            .map(|module| LocatedModule {
+                script: self,
+                chunk_id: None,
+                module,
+            })
+            .collect::<Vec<_>>();
+        modules.sort_by_key(LocatedModule::id);
+        modules
+    }
+}
@@ -0,0 +1,313 @@
+//! REST endpoint table dumping.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult, LocatedModule},
+    parse::{
+        util::{callee_prop_name, num_lit, object_props, returned_expr, str_lit, PropValue},
+        ChunkId, ModuleId,
+    },
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// The minimum amount of routes that an object literal must contain for it to
+/// be considered an endpoint table.
+const MINIMUM_TABLE_SIZE: usize = 10;
+
+/// An endpoint, reconstructed into a route template.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    /// The route template, with parameters substituted with their (zero-based)
+    /// position in curly braces, e.g. `/guilds/{0}/members`.
+    pub template: String,
+    pub parameters: usize,
+}
+
+/// A table of endpoints found within a module.
+#[derive(Serialize, Debug)]
+pub struct EndpointTable {
+    pub module_id: ModuleId,
+
+    /// The ID of the chunk containing the table's module, if it was found in a
+    /// deep chunk.
+    pub chunk_id: Option<ChunkId>,
+
+    /// Endpoints keyed by name.
+    pub endpoints: BTreeMap<String, Endpoint>,
+}
+
+/// Evaluates an expression that builds a route out of string literals and
+/// parameters, e.g.:
+///
+/// ```js
+/// "/guilds/".concat(e, "/members")
+/// `/guilds/${e}/members`
+/// "/guilds/" + e + "/members"
+/// ```
+///
+/// Parameters are substituted with their position in `parameters`. Returns
+/// `None` if the expression is too complicated to evaluate.
+pub fn evaluate_route(expr: &ast::Expr, parameters: &[&str]) -> Option<String> {
+    let evaluate = |expr: &ast::Expr| evaluate_route(expr, parameters);
+
+    match expr {
+        ast::Expr::Lit(ast::Lit::Str(_)) => str_lit(expr).map(ToOwned::to_owned),
+        ast::Expr::Lit(ast::Lit::Num(_)) => num_lit(expr).map(|value| value.to_string()),
+        ast::Expr::Ident(ident) => {
+            let position = parameters
+                .iter()
+                .position(|parameter| *parameter == &*ident.sym)?;
+            Some(format!("{{{}}}", position))
+        }
+        ast::Expr::Paren(ast::ParenExpr { expr, .. }) => evaluate(expr),
+        ast::Expr::Bin(ast::BinExpr {
+            op: ast::BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => Some(evaluate(left)? + &evaluate(right)?),
+        ast::Expr::Tpl(ast::Tpl { exprs, quasis, .. }) => {
+            let mut route = String::new();
+
+            for (index, quasi) in quasis.iter().enumerate() {
+                route.push_str(quasi.cooked.as_deref()?);
+                if let Some(expr) = exprs.get(index) {
+                    route.push_str(&evaluate(expr)?);
+                }
+            }
+
+            Some(route)
+        }
+        ast::Expr::Call(call) if callee_prop_name(call) == Some("concat") => {
+            let ast::Callee::Expr(callee) = &call.callee else {
+                return None;
+            };
+            let ast::Expr::Member(ast::MemberExpr { obj, .. }) = &**callee else {
+                return None;
+            };
+
+            let mut route = evaluate(obj)?;
+            for arg in &call.args {
+                route.push_str(&evaluate(&arg.expr)?);
+            }
+
+            Some(route)
+        }
+        // Parameters are sometimes passed through a function first, such as
+        // `encodeURIComponent(e)`.
+        ast::Expr::Call(ast::CallExpr { args, .. }) => match args.as_slice() {
+            [arg] => evaluate(&arg.expr).filter(|route| {
+                route.starts_with('{') && route.ends_with('}') && !route[1..].contains('{')
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn pat_ident(pat: &ast::Pat) -> Option<&str> {
+    match pat {
+        ast::Pat::Ident(binding) => Some(&binding.id.sym),
+        _ => None,
+    }
+}
+
+/// Returns the parameter names of a function alongside the expression that it
+/// returns.
+fn evaluate_function(function: &ast::Function) -> Option<(Vec<&str>, &ast::Expr)> {
+    let parameters = function
+        .params
+        .iter()
+        .map(|param| pat_ident(&param.pat))
+        .collect::<Option<_>>()?;
+    Some((parameters, returned_expr(function.body.as_ref()?)?))
+}
+
+/// Reconstructs an endpoint from the value of a property in an endpoint table.
+pub fn evaluate_endpoint(value: PropValue<'_>) -> Option<Endpoint> {
+    let (parameters, returned): (Vec<&str>, &ast::Expr) = match value {
+        PropValue::Expr(ast::Expr::Arrow(arrow)) => {
+            let parameters = arrow.params.iter().map(pat_ident).collect::<Option<_>>()?;
+            let returned = match &arrow.body {
+                ast::BlockStmtOrExpr::Expr(expr) => &**expr,
+                ast::BlockStmtOrExpr::BlockStmt(body) => returned_expr(body)?,
+            };
+            (parameters, returned)
+        }
+        PropValue::Expr(ast::Expr::Fn(ast::FnExpr { function, .. })) => {
+            evaluate_function(function)?
+        }
+        PropValue::Method(function) => evaluate_function(function)?,
+        PropValue::Expr(expr) => (vec![], expr),
+    };
+
+    let template = evaluate_route(returned, &parameters)?;
+
+    // Not every string is a route.
+    if !template.starts_with('/') {
+        return None;
+    }
+
+    Some(Endpoint {
+        template,
+        parameters: parameters.len(),
+    })
+}
+
+struct EndpointTableVisitor<'m> {
+    module: &'m LocatedModule<'m>,
+    tables: Vec<EndpointTable>,
+}
+
+impl Visit for EndpointTableVisitor<'_> {
+    fn visit_object_lit(&mut self, n: &ast::ObjectLit) {
+        let endpoints: BTreeMap<String, Endpoint> = object_props(n)
+            .filter_map(|(name, value)| Some((name.to_owned(), evaluate_endpoint(value)?)))
+            .collect();
+
+        // Tolerate some endpoints that couldn't be evaluated, but make sure
+        // that most of the object consists of them.
+        if endpoints.len() >= MINIMUM_TABLE_SIZE && endpoints.len() * 4 >= n.props.len() * 3 {
+            tracing::debug!(
+                module_id = self.module.id(),
+                "found endpoint table with {} endpoint(s), {} unevaluated",
+                endpoints.len(),
+                n.props.len() - endpoints.len()
+            );
+
+            self.tables.push(EndpointTable {
+                module_id: self.module.id(),
+                chunk_id: self.module.chunk_id,
+                endpoints,
+            });
+
+            return;
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the endpoint tables within a module.
+pub fn walk_module_endpoint_tables<'m>(module: &'m LocatedModule<'m>) -> Vec<EndpointTable> {
+    let mut visitor = EndpointTableVisitor {
+        module,
+        tables: vec![],
+    };
+    module.module.func.visit_with(&mut visitor);
+    visitor.tables
+}
+
+pub struct Endpoints;
+
+impl Dump for Endpoints {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let modules = cx.modules();
+        let tables = modules
+            .iter()
+            .flat_map(walk_module_endpoint_tables)
+            .collect::<Vec<_>>();
+
+        tracing::info!(
+            "found {} endpoint table(s) across {} module(s)",
+            tables.len(),
+            modules.len()
+        );
+
+        Ok(DumpResult::from_serializable(&tables, "endpoints")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_script;
+
+    /// Evaluates the value of the `route` property of an object literal.
+    fn endpoint(value: &str) -> Option<Endpoint> {
+        let script = parse_script(&format!("({{ route: {} }})", value)).unwrap();
+        let [ast::Stmt::Expr(ast::ExprStmt { expr, .. })] = script.body.as_slice() else {
+            panic!("expected an expression statement");
+        };
+        let ast::Expr::Paren(ast::ParenExpr { expr, .. }) = &**expr else {
+            panic!("expected a parenthesized expression");
+        };
+        let ast::Expr::Object(object) = &**expr else {
+            panic!("expected an object literal");
+        };
+
+        let (_, value) = object_props(object).next().unwrap();
+        evaluate_endpoint(value)
+    }
+
+    fn template(value: &str) -> Option<String> {
+        endpoint(value).map(|endpoint| endpoint.template)
+    }
+
+    #[test]
+    fn evaluates_constant_routes() {
+        assert_eq!(
+            endpoint(r#""/users/@me""#),
+            Some(Endpoint {
+                template: "/users/@me".to_owned(),
+                parameters: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn evaluates_concatenation() {
+        assert_eq!(
+            endpoint(r#"e => "/guilds/".concat(e, "/members")"#),
+            Some(Endpoint {
+                template: "/guilds/{0}/members".to_owned(),
+                parameters: 1,
+            })
+        );
+        assert_eq!(
+            template(r#"(e, t) => "/channels/" + e + "/messages/" + t"#).as_deref(),
+            Some("/channels/{0}/messages/{1}")
+        );
+    }
+
+    #[test]
+    fn evaluates_template_literals() {
+        assert_eq!(
+            template("(e, t) => `/guilds/${e}/roles/${t}`").as_deref(),
+            Some("/guilds/{0}/roles/{1}")
+        );
+    }
+
+    #[test]
+    fn evaluates_function_bodies() {
+        assert_eq!(
+            template(r#"function (e) { return "/users/" + e; }"#).as_deref(),
+            Some("/users/{0}")
+        );
+        assert_eq!(
+            template(r#"e => { return "/users/".concat(e); }"#).as_deref(),
+            Some("/users/{0}")
+        );
+    }
+
+    #[test]
+    fn evaluates_wrapped_parameters() {
+        assert_eq!(
+            template(r#"e => "/emojis/".concat(encodeURIComponent(e))"#).as_deref(),
+            Some("/emojis/{0}")
+        );
+    }
+
+    #[test]
+    fn rejects_non_routes() {
+        assert_eq!(endpoint(r#""hello""#), None);
+        assert_eq!(endpoint(r#"e => "/users/" + t"#), None);
+        assert_eq!(endpoint(r#"e => { foo(); return "/users/" + e; }"#), None);
+        assert_eq!(endpoint(r#"e => "/users/".concat(e.id)"#), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Client experiment dumping.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult, LocatedModule},
+    parse::{
+        util::{array_elems, num_lit, object_prop, str_lit},
+        ChunkId, ModuleId,
+    },
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// A treatment (also known as a bucket) of an experiment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Treatment {
+    pub id: i64,
+    pub label: Option<String>,
+}
+
+/// A client experiment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Experiment {
+    /// The ID of the experiment, e.g. `2023-01_feature`.
+    pub id: String,
+
+    /// What the experiment is rolled out to, e.g. `user` or `guild`.
+    pub kind: Option<String>,
+    pub label: Option<String>,
+    pub treatments: Vec<Treatment>,
+
+    /// The module that the experiment is defined in.
+    pub module_id: ModuleId,
+
+    /// The ID of the chunk containing the experiment's module, if it was found
+    /// in a deep chunk.
+    pub chunk_id: Option<ChunkId>,
+}
+
+/// Experiments keyed by experiment ID.
+pub type ExperimentMap = BTreeMap<String, Experiment>;
+
+/// Extracts the treatments of an experiment definition.
+///
+/// Treatments are either specified as an array of objects, or as a pair of
+/// parallel `buckets` and `description` arrays:
+///
+/// ```js
+/// { treatments: [{ id: 1, label: "Treatment 1" }] }
+/// { buckets: [0, 1], description: ["Control", "Treatment 1"] }
+/// ```
+fn walk_treatments(definition: &ast::ObjectLit) -> Vec<Treatment> {
+    if let Some(treatments) = object_prop(definition, "treatments") {
+        return array_elems(treatments)
+            .filter_map(|treatment| {
+                let ast::Expr::Object(treatment) = treatment else {
+                    return None;
+                };
+
+                Some(Treatment {
+                    id: num_lit(object_prop(treatment, "id")?)? as i64,
+                    label: object_prop(treatment, "label")
+                        .and_then(str_lit)
+                        .map(ToOwned::to_owned),
+                })
+            })
+            .collect();
+    }
+
+    let descriptions = object_prop(definition, "description")
+        .into_iter()
+        .flat_map(array_elems)
+        .map(|description| str_lit(description).map(ToOwned::to_owned));
+
+    object_prop(definition, "buckets")
+        .into_iter()
+        .flat_map(array_elems)
+        .zip(descriptions.map(Some).chain(std::iter::repeat(None)))
+        .filter_map(|(bucket, label)| {
+            Some(Treatment {
+                id: num_lit(bucket)? as i64,
+                label: label.flatten(),
+            })
+        })
+        .collect()
+}
+
+struct ExperimentVisitor<'m> {
+    module: &'m LocatedModule<'m>,
+    experiments: Vec<Experiment>,
+}
+
+impl Visit for ExperimentVisitor<'_> {
+    fn visit_object_lit(&mut self, n: &ast::ObjectLit) {
+        // An experiment definition is an object with an ID, a kind, and some
+        // kind of treatment listing.
+        if_chain::if_chain! {
+            if let Some(id) = object_prop(n, "id").and_then(str_lit);
+            if let Some(kind) = object_prop(n, "kind").and_then(str_lit);
+            if object_prop(n, "treatments").is_some() || object_prop(n, "buckets").is_some();
+            then {
+                self.experiments.push(Experiment {
+                    id: id.to_owned(),
+                    kind: Some(kind.to_owned()),
+                    label: object_prop(n, "label").and_then(str_lit).map(ToOwned::to_owned),
+                    treatments: walk_treatments(n),
+                    module_id: self.module.id(),
+                    chunk_id: self.module.chunk_id,
+                });
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the experiments defined within a module.
+pub fn walk_module_experiments<'m>(module: &'m LocatedModule<'m>) -> Vec<Experiment> {
+    let mut visitor = ExperimentVisitor {
+        module,
+        experiments: vec![],
+    };
+    module.module.func.visit_with(&mut visitor);
+    visitor.experiments
+}
+
+/// The experiments that were added or removed between two sets of experiments.
+#[derive(Serialize, Debug, Default)]
+pub struct ExperimentChanges<'a> {
+    pub added: Vec<&'a Experiment>,
+    pub removed: Vec<&'a Experiment>,
+}
+
+/// Compares two sets of experiments, e.g. from two consecutive builds.
+pub fn diff_experiments<'a>(
+    old: &'a ExperimentMap,
+    new: &'a ExperimentMap,
+) -> ExperimentChanges<'a> {
+    ExperimentChanges {
+        added: new
+            .values()
+            .filter(|experiment| !old.contains_key(&experiment.id))
+            .collect(),
+        removed: old
+            .values()
+            .filter(|experiment| !new.contains_key(&experiment.id))
+            .collect(),
+    }
+}
+
+pub struct Experiments;
+
+impl Dump for Experiments {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let modules = cx.modules();
+        let experiments: ExperimentMap = modules
+            .iter()
+            .flat_map(walk_module_experiments)
+            .map(|experiment| (experiment.id.clone(), experiment))
+            .collect();
+
+        tracing::info!(
+            "found {} experiment(s) across {} module(s)",
+            experiments.len(),
+            modules.len()
+        );
+
+        Ok(DumpResult::from_serializable(&experiments, "experiments")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::ParsedScript;
+
+    fn experiments(modules: &str) -> Vec<Experiment> {
+        let script = ParsedScript::test_chunk(&format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ {} }}]);",
+            modules
+        ));
+        script
+            .test_modules()
+            .iter()
+            .flat_map(walk_module_experiments)
+            .collect()
+    }
+
+    fn experiment(id: &str, treatments: Vec<Treatment>) -> Experiment {
+        Experiment {
+            id: id.to_owned(),
+            kind: Some("user".to_owned()),
+            label: None,
+            treatments,
+            module_id: 10,
+            chunk_id: None,
+        }
+    }
+
+    fn treatment(id: i64, label: Option<&str>) -> Treatment {
+        Treatment {
+            id,
+            label: label.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn walks_treatment_objects() {
+        let found = experiments(
+            r#"10: (e, t, n) => {
+                t.Z = (0, n(1).B)({
+                    kind: "user",
+                    id: "2023-01_feature",
+                    label: "Feature",
+                    defaultConfig: { enabled: false },
+                    treatments: [
+                        { id: 1, label: "Enabled", config: { enabled: true } },
+                        { id: 2, label: "Enabled, differently" },
+                    ],
+                });
+            }"#,
+        );
+
+        assert_eq!(
+            found,
+            vec![Experiment {
+                label: Some("Feature".to_owned()),
+                ..experiment(
+                    "2023-01_feature",
+                    vec![
+                        treatment(1, Some("Enabled")),
+                        treatment(2, Some("Enabled, differently")),
+                    ],
+                )
+            }]
+        );
+    }
+
+    #[test]
+    fn walks_parallel_bucket_arrays() {
+        let found = experiments(
+            r#"10: (e, t, n) => {
+                t.Z = {
+                    id: "2021-06_legacy",
+                    kind: "guild",
+                    buckets: [0, 1, 2],
+                    description: ["Control", "Treatment 1"],
+                };
+            }"#,
+        );
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind.as_deref(), Some("guild"));
+        assert_eq!(
+            found[0].treatments,
+            vec![
+                treatment(0, Some("Control")),
+                treatment(1, Some("Treatment 1")),
+                treatment(2, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_unrelated_objects() {
+        let found = experiments(
+            r#"10: (e, t, n) => {
+                t.a = { id: "settings", kind: "user" };
+                t.b = { id: 123, kind: "guild", buckets: [0, 1] };
+                t.c = { id: "channel", type: 0, treatments: [] };
+                t.d = { kind: "user", buckets: [0, 1] };
+            }"#,
+        );
+
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn diffs_experiments() {
+        let map = |ids: &[&str]| -> ExperimentMap {
+            ids.iter()
+                .map(|id| ((*id).to_owned(), experiment(id, vec![])))
+                .collect()
+        };
+        let old = map(&["kept", "removed"]);
+        let new = map(&["added", "kept"]);
+
+        let ids = |experiments: Vec<&Experiment>| {
+            experiments
+                .into_iter()
+                .map(|experiment| experiment.id.clone())
+                .collect::<Vec<_>>()
+        };
+        let changes = diff_experiments(&old, &new);
+        assert_eq!(ids(changes.added), vec!["added".to_owned()]);
+        assert_eq!(ids(changes.removed), vec!["removed".to_owned()]);
+
+        let changes = diff_experiments(&old, &old);
+        assert!(changes.added.is_empty() && changes.removed.is_empty());
+    }
+}
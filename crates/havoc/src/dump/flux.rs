@@ -0,0 +1,341 @@
+//! Flux action dumping.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult, LocatedModule},
+    parse::{
+        util::{
+            callee_prop_name, object_prop, object_props, str_lit, DisplayNameVisitor, PropValue,
+        },
+        ModuleId,
+    },
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Returns whether a string looks like a Flux action type, e.g.
+/// `MESSAGE_CREATE`.
+pub fn is_action_type(s: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref ACTION_TYPE_RE: Regex = Regex::new(r#"^[A-Z][A-Z0-9]*(?:_[A-Z0-9]+)*$"#).unwrap();
+    }
+
+    ACTION_TYPE_RE.is_match(s)
+}
+
+/// Returns the action types handled by an object literal, assuming that it's
+/// a store's action handler map.
+///
+/// Action handler maps are objects where every key is an action type and
+/// every value is a function (or a reference to one):
+///
+/// ```js
+/// { MESSAGE_CREATE: function (e) { ... }, CONNECTION_OPEN: u }
+/// ```
+pub fn action_handler_types(object: &ast::ObjectLit) -> Option<Vec<&str>> {
+    let handled = object_props(object)
+        .filter(|(key, value)| {
+            is_action_type(key)
+                && (value.is_function_like()
+                    || matches!(value, PropValue::Expr(ast::Expr::Ident(_))))
+        })
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+
+    if handled.is_empty() || handled.len() != object.props.len() {
+        return None;
+    }
+
+    Some(handled)
+}
+
+/// The Flux action types that a module dispatches and handles.
+#[derive(Default, Debug)]
+pub struct ModuleFluxActions {
+    pub dispatched: BTreeSet<String>,
+    pub handled: BTreeSet<String>,
+}
+
+struct FluxActionVisitor {
+    actions: ModuleFluxActions,
+}
+
+impl Visit for FluxActionVisitor {
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        // d.dispatch({ type: "MESSAGE_CREATE", ... })
+        if_chain::if_chain! {
+            if callee_prop_name(n) == Some("dispatch");
+            if let Some(ast::ExprOrSpread { spread: None, expr }) = n.args.first();
+            if let ast::Expr::Object(object) = &**expr;
+            if let Some(action_type) = object_prop(object, "type").and_then(str_lit);
+            if is_action_type(action_type);
+            then {
+                self.actions.dispatched.insert(action_type.to_owned());
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_object_lit(&mut self, n: &ast::ObjectLit) {
+        if let Some(handled) = action_handler_types(n) {
+            self.actions
+                .handled
+                .extend(handled.into_iter().map(ToOwned::to_owned));
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the Flux action types that a module dispatches and handles.
+pub fn walk_module_flux_actions(module: &LocatedModule<'_>) -> ModuleFluxActions {
+    let mut visitor = FluxActionVisitor {
+        actions: ModuleFluxActions::default(),
+    };
+    module.module.func.visit_with(&mut visitor);
+    visitor.actions
+}
+
+/// Guesses the display name of the store defined within a module.
+///
+/// Display names ending in `Store` are preferred, since a module could define
+/// components or other classes alongside a store.
+pub fn store_display_name(module: &LocatedModule<'_>) -> Option<String> {
+    let mut visitor = DisplayNameVisitor::default();
+    module.module.func.visit_with(&mut visitor);
+
+    let mut display_names = visitor
+        .display_names
+        .into_iter()
+        .map(|display_name| display_name.name);
+    let first = display_names.next()?;
+
+    if first.ends_with("Store") {
+        return Some(first);
+    }
+
+    Some(
+        display_names
+            .find(|name| name.ends_with("Store"))
+            .unwrap_or(first),
+    )
+}
+
+/// A store module that handles a Flux action.
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FluxActionHandler {
+    pub module_id: ModuleId,
+
+    /// The display name of the store, if one could be found.
+    pub store: Option<String>,
+}
+
+/// Where a Flux action is dispatched and handled.
+#[derive(Serialize, Default, Debug)]
+pub struct FluxAction {
+    pub dispatched_by: BTreeSet<ModuleId>,
+    pub handled_by: BTreeSet<FluxActionHandler>,
+}
+
+/// Flux actions keyed by action type.
+pub type FluxActionMap = BTreeMap<String, FluxAction>;
+
+/// Finds Flux actions across a set of modules.
+pub fn collect_flux_actions(modules: &[LocatedModule<'_>]) -> FluxActionMap {
+    let mut actions = FluxActionMap::new();
+
+    for module in modules {
+        let ModuleFluxActions {
+            dispatched,
+            handled,
+        } = walk_module_flux_actions(module);
+
+        for action_type in dispatched {
+            actions
+                .entry(action_type)
+                .or_default()
+                .dispatched_by
+                .insert(module.id());
+        }
+
+        if handled.is_empty() {
+            continue;
+        }
+
+        let store = store_display_name(module);
+        for action_type in handled {
+            actions
+                .entry(action_type)
+                .or_default()
+                .handled_by
+                .insert(FluxActionHandler {
+                    module_id: module.id(),
+                    store: store.clone(),
+                });
+        }
+    }
+
+    tracing::info!(
+        "found {} flux action type(s) across {} module(s)",
+        actions.len(),
+        modules.len()
+    );
+
+    actions
+}
+
+pub struct FluxActions;
+
+impl Dump for FluxActions {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let actions = collect_flux_actions(&cx.modules());
+        Ok(DumpResult::from_serializable(&actions, "flux_actions")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::ParsedScript;
+    use crate::parse::parse_script;
+
+    /// Parses a chunk containing some modules.
+    fn chunk(modules: &str) -> ParsedScript {
+        ParsedScript::test_chunk(&format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ {} }}]);",
+            modules
+        ))
+    }
+
+    /// Returns the action types handled by an object literal.
+    fn handled(object: &str) -> Option<Vec<String>> {
+        let script = parse_script(&format!("({})", object)).unwrap();
+        let [ast::Stmt::Expr(ast::ExprStmt { expr, .. })] = script.body.as_slice() else {
+            panic!("expected an expression statement");
+        };
+        let ast::Expr::Paren(ast::ParenExpr { expr, .. }) = &**expr else {
+            panic!("expected a parenthesized expression");
+        };
+        let ast::Expr::Object(object) = &**expr else {
+            panic!("expected an object literal");
+        };
+
+        action_handler_types(object).map(|types| types.into_iter().map(ToOwned::to_owned).collect())
+    }
+
+    #[test]
+    fn detects_action_types() {
+        assert!(is_action_type("MESSAGE_CREATE"));
+        assert!(is_action_type("CONNECTION_OPEN_2"));
+        assert!(!is_action_type("messageCreate"));
+        assert!(!is_action_type("MESSAGE__CREATE"));
+        assert!(!is_action_type("_MESSAGE"));
+    }
+
+    #[test]
+    fn detects_action_handler_maps() {
+        assert_eq!(
+            handled("{ MESSAGE_CREATE: function (e) {}, MESSAGE_DELETE: (e) => {}, CONNECTION_OPEN: u, LOGOUT() {} }"),
+            Some(vec![
+                "MESSAGE_CREATE".to_owned(),
+                "MESSAGE_DELETE".to_owned(),
+                "CONNECTION_OPEN".to_owned(),
+                "LOGOUT".to_owned(),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_mixed_objects() {
+        // Not every key is an action type.
+        assert_eq!(handled("{ MESSAGE_CREATE: u, initialize: u }"), None);
+        // Not every value is a function.
+        assert_eq!(
+            handled(r#"{ MESSAGE_CREATE: u, MESSAGE_DELETE: "x" }"#),
+            None
+        );
+        assert_eq!(handled("{ MESSAGE_CREATE: u, ...rest }"), None);
+        assert_eq!(handled("{}"), None);
+    }
+
+    #[test]
+    fn finds_dispatched_and_handled_actions() {
+        let script = chunk(
+            r#"10: (e, t, n) => {
+                n(1).Z.dispatch({ type: "MESSAGE_CREATE", message: e });
+                n(1).Z.dispatch({ type: "notAnAction" });
+                n(1).Z.dispatch(e);
+            },
+            11: (e, t, n) => {
+                class s extends n(2).ZP.Store {}
+                s.displayName = "MessageStore";
+                t.Z = new s(n(1).Z, {
+                    MESSAGE_CREATE: function (e) {},
+                    CONNECTION_OPEN: l,
+                });
+            }"#,
+        );
+        let modules = script.test_modules();
+
+        let dispatcher = walk_module_flux_actions(&modules[0]);
+        assert_eq!(
+            dispatcher.dispatched.into_iter().collect::<Vec<_>>(),
+            vec!["MESSAGE_CREATE".to_owned()]
+        );
+        assert!(dispatcher.handled.is_empty());
+
+        let actions = collect_flux_actions(&modules);
+        assert_eq!(
+            actions.keys().collect::<Vec<_>>(),
+            vec!["CONNECTION_OPEN", "MESSAGE_CREATE"]
+        );
+        let message_create = &actions["MESSAGE_CREATE"];
+        assert_eq!(
+            message_create.dispatched_by.iter().collect::<Vec<_>>(),
+            vec![&10]
+        );
+        assert_eq!(
+            message_create.handled_by.iter().collect::<Vec<_>>(),
+            vec![&FluxActionHandler {
+                module_id: 11,
+                store: Some("MessageStore".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn prefers_store_display_names() {
+        let script = chunk(
+            r#"10: (e, t, n) => {
+                function a() {}
+                a.displayName = "MessageList";
+                class s {}
+                s.displayName = "MessageStore";
+            },
+            11: (e, t, n) => {
+                function a() {}
+                a.displayName = "MessageList";
+                function b() {}
+                b.displayName = "MessageItem";
+            },
+            12: (e, t, n) => {}"#,
+        );
+        let modules = script.test_modules();
+
+        assert_eq!(
+            store_display_name(&modules[0]).as_deref(),
+            Some("MessageStore")
+        );
+        assert_eq!(
+            store_display_name(&modules[1]).as_deref(),
+            Some("MessageList")
+        );
+        assert_eq!(store_display_name(&modules[2]), None);
+    }
+}
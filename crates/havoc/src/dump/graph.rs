@@ -0,0 +1,23 @@
+//! Module graph dumping.
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult},
+    parse::graph::ModuleGraph,
+};
+
+pub struct WebpackModuleGraph;
+
+impl Dump for WebpackModuleGraph {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let chunks = cx.webpack_chunks();
+        let graph: ModuleGraph = chunks.iter().map(|(_, chunk)| chunk).collect();
+
+        tracing::info!(
+            "mapped {} module(s) to chunks, {} of which make lazy imports",
+            graph.module_chunks.len(),
+            graph.lazy_imports.len()
+        );
+
+        Ok(DumpResult::from_serializable(&graph, "module_graph")?)
+    }
+}
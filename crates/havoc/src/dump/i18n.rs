@@ -0,0 +1,399 @@
+//! Localization message table dumping.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult, LocatedModule},
+    parse::{
+        util::{callee_prop_name, num_lit, object_props, PropValue},
+        ModuleId,
+    },
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// The locale that message tables are assumed to be in when they're found in
+/// the entrypoint without being referenced by the locale loader.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// The minimum amount of messages that an object literal must contain for it
+/// to be considered a message table.
+const MINIMUM_TABLE_SIZE: usize = 50;
+
+/// Messages keyed by message key.
+pub type MessageMap = BTreeMap<String, String>;
+
+/// The messages of a single locale.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct LocaleMessages {
+    /// The modules containing the locale's message tables.
+    pub module_ids: BTreeSet<ModuleId>,
+    pub messages: MessageMap,
+}
+
+/// Locale messages keyed by locale, e.g. `en-US`.
+pub type LocaleMessagesMap = BTreeMap<String, LocaleMessages>;
+
+fn is_locale(s: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref LOCALE_RE: Regex = Regex::new(r#"^[a-z]{2,3}(?:-[A-Z]{2})?$"#).unwrap();
+    }
+
+    LOCALE_RE.is_match(s)
+}
+
+fn is_message_key(s: &str) -> bool {
+    lazy_static::lazy_static! {
+        static ref MESSAGE_KEY_RE: Regex = Regex::new(r#"^[A-Z][A-Z0-9_]*$"#).unwrap();
+    }
+
+    MESSAGE_KEY_RE.is_match(s)
+}
+
+/// Returns the value of a string literal or a template literal without any
+/// expressions.
+fn message_text(expr: &ast::Expr) -> Option<&str> {
+    match expr {
+        ast::Expr::Lit(ast::Lit::Str(ast::Str { value: atom, .. })) => Some(atom),
+        ast::Expr::Tpl(ast::Tpl { exprs, quasis, .. }) if exprs.is_empty() => {
+            quasis.first()?.cooked.as_deref()
+        }
+        _ => None,
+    }
+}
+
+/// Returns the messages within an object literal, assuming that it's a message
+/// table.
+pub fn message_table(object: &ast::ObjectLit) -> Option<MessageMap> {
+    let messages: MessageMap = object_props(object)
+        .filter(|(key, _)| is_message_key(key))
+        .filter_map(|(key, value)| match value {
+            PropValue::Expr(expr) => Some((key.to_owned(), message_text(expr)?.to_owned())),
+            PropValue::Method(_) => None,
+        })
+        .collect();
+
+    // Some messages might be computed, but the vast majority of the object
+    // should consist of plain messages.
+    if messages.len() < MINIMUM_TABLE_SIZE || messages.len() * 10 < object.props.len() * 9 {
+        return None;
+    }
+
+    // Constant tables (e.g. `{ FOO: "FOO", BAR: "bar" }`) look just like
+    // message tables, except that their values mirror their keys.
+    let mirrored = messages
+        .iter()
+        .filter(|(key, text)| key.eq_ignore_ascii_case(text))
+        .count();
+    if mirrored * 2 > messages.len() {
+        return None;
+    }
+
+    Some(messages)
+}
+
+/// Finds the module ID that a lazy import resolves to, e.g. `456` in
+/// `n.e(123).then(n.t.bind(n, 456, 23))`.
+#[derive(Default)]
+struct LazyModuleVisitor {
+    module_id: Option<ModuleId>,
+}
+
+impl Visit for LazyModuleVisitor {
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        if_chain::if_chain! {
+            if self.module_id.is_none();
+            if callee_prop_name(n) == Some("bind");
+            if let Some(module_id) = n.args.get(1).and_then(|arg| num_lit(&arg.expr));
+            then {
+                self.module_id = Some(module_id as ModuleId);
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Collects the locales of modules imported by the locale loader, which maps
+/// locales to functions that lazily import their message tables:
+///
+/// ```js
+/// { fr: () => n.e(123).then(n.t.bind(n, 456, 23)), ... }
+/// ```
+#[derive(Default)]
+struct LocaleLoaderVisitor {
+    locales: HashMap<ModuleId, String>,
+}
+
+impl Visit for LocaleLoaderVisitor {
+    fn visit_object_lit(&mut self, n: &ast::ObjectLit) {
+        let loaders = object_props(n)
+            .filter(|(key, value)| is_locale(key) && value.is_function_like())
+            .collect::<Vec<_>>();
+
+        if loaders.len() < 5 || loaders.len() != n.props.len() {
+            n.visit_children_with(self);
+            return;
+        }
+
+        for (locale, loader) in loaders {
+            let mut visitor = LazyModuleVisitor::default();
+            match loader {
+                PropValue::Expr(expr) => expr.visit_with(&mut visitor),
+                PropValue::Method(function) => function.visit_with(&mut visitor),
+            }
+
+            if let Some(module_id) = visitor.module_id {
+                self.locales.insert(module_id, locale.to_owned());
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct MessageTableVisitor {
+    tables: Vec<MessageMap>,
+}
+
+impl Visit for MessageTableVisitor {
+    fn visit_object_lit(&mut self, n: &ast::ObjectLit) {
+        match message_table(n) {
+            Some(messages) => self.tables.push(messages),
+            None => n.visit_children_with(self),
+        }
+    }
+}
+
+/// Finds the message tables in a set of modules, grouping them by locale.
+///
+/// Tables whose locale can't be determined are skipped.
+pub fn collect_locale_messages(modules: &[LocatedModule<'_>]) -> LocaleMessagesMap {
+    let mut loader_visitor = LocaleLoaderVisitor::default();
+    for module in modules {
+        module.module.func.visit_with(&mut loader_visitor);
+    }
+    let locales = loader_visitor.locales;
+
+    let mut messages = LocaleMessagesMap::new();
+
+    for module in modules {
+        let mut visitor = MessageTableVisitor::default();
+        module.module.func.visit_with(&mut visitor);
+
+        if visitor.tables.is_empty() {
+            continue;
+        }
+
+        let locale = match locales.get(&module.id()) {
+            Some(locale) => locale.as_str(),
+            None if module.chunk_id.is_none() => DEFAULT_LOCALE,
+            None => {
+                tracing::debug!(
+                    module_id = module.id(),
+                    "skipping message table(s) with an unknown locale"
+                );
+                continue;
+            }
+        };
+
+        let locale_messages = messages.entry(locale.to_owned()).or_default();
+        locale_messages.module_ids.insert(module.id());
+        for table in visitor.tables {
+            locale_messages.messages.extend(table);
+        }
+    }
+
+    messages
+}
+
+/// The messages that changed between two message maps.
+#[derive(Serialize, Debug, Default)]
+pub struct MessageChanges<'a> {
+    pub added: BTreeMap<&'a str, &'a str>,
+    pub removed: BTreeMap<&'a str, &'a str>,
+
+    /// Messages whose text changed, as pairs of old and new text.
+    pub changed: BTreeMap<&'a str, (&'a str, &'a str)>,
+}
+
+impl MessageChanges<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares two message maps, e.g. of the same locale from two consecutive
+/// builds.
+pub fn diff_messages<'a>(old: &'a MessageMap, new: &'a MessageMap) -> MessageChanges<'a> {
+    let mut changes = MessageChanges::default();
+
+    for (key, new_text) in new {
+        match old.get(key) {
+            None => {
+                changes.added.insert(key, new_text);
+            }
+            Some(old_text) if old_text != new_text => {
+                changes.changed.insert(key, (old_text, new_text));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (key, old_text) in old {
+        if !new.contains_key(key) {
+            changes.removed.insert(key, old_text);
+        }
+    }
+
+    changes
+}
+
+pub struct I18nMessages {
+    /// Whether to dump the messages of every locale instead of only the
+    /// default locale.
+    pub all_locales: bool,
+}
+
+impl Dump for I18nMessages {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let mut messages = collect_locale_messages(&cx.modules());
+
+        if !self.all_locales {
+            messages.retain(|locale, _| locale == DEFAULT_LOCALE);
+        }
+
+        tracing::info!(
+            "found messages for {} locale(s): {}",
+            messages.len(),
+            messages
+                .iter()
+                .map(|(locale, messages)| format!("{} ({})", locale, messages.messages.len()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        Ok(DumpResult::from_serializable(&messages, "i18n_messages")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_script;
+
+    /// Finds the message tables in a script.
+    fn tables(js: &str) -> Vec<MessageMap> {
+        let mut visitor = MessageTableVisitor::default();
+        parse_script(js).unwrap().visit_with(&mut visitor);
+        visitor.tables
+    }
+
+    /// Builds an object literal with `count` properties, whose values are
+    /// produced by `value`.
+    fn object(count: usize, value: impl Fn(usize) -> String) -> String {
+        let props = (0..count)
+            .map(|index| format!("KEY_{}: {}", index, value(index)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("var e = {{ {} }};", props)
+    }
+
+    fn messages(pairs: &[(&str, &str)]) -> MessageMap {
+        pairs
+            .iter()
+            .map(|(key, text)| ((*key).to_owned(), (*text).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn detects_message_tables() {
+        let tables = tables(&object(MINIMUM_TABLE_SIZE, |index| {
+            if index % 2 == 0 {
+                format!("\"Message {}\"", index)
+            } else {
+                format!("`Message {}`", index)
+            }
+        }));
+
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].len(), MINIMUM_TABLE_SIZE);
+        assert_eq!(tables[0]["KEY_0"], "Message 0");
+        assert_eq!(tables[0]["KEY_1"], "Message 1");
+    }
+
+    #[test]
+    fn detects_nested_message_tables() {
+        let table = object(MINIMUM_TABLE_SIZE, |index| format!("\"Message {}\"", index));
+        let js = format!(
+            "(function (e, t, n) {{ {} e.exports = {{ default: 1 }}; }});",
+            table
+        );
+
+        assert_eq!(tables(&js).len(), 1);
+    }
+
+    #[test]
+    fn rejects_small_tables() {
+        let js = object(MINIMUM_TABLE_SIZE - 1, |index| {
+            format!("\"Message {}\"", index)
+        });
+        assert!(tables(&js).is_empty());
+    }
+
+    #[test]
+    fn rejects_mostly_computed_tables() {
+        let js = object(MINIMUM_TABLE_SIZE * 2, |index| {
+            if index % 4 == 0 {
+                "n(123)".to_owned()
+            } else {
+                format!("\"Message {}\"", index)
+            }
+        });
+        assert!(tables(&js).is_empty());
+    }
+
+    #[test]
+    fn rejects_constant_tables() {
+        let js = object(MINIMUM_TABLE_SIZE, |index| format!("\"KEY_{}\"", index));
+        assert!(tables(&js).is_empty());
+
+        let js = object(MINIMUM_TABLE_SIZE, |index| format!("\"key_{}\"", index));
+        assert!(tables(&js).is_empty());
+
+        // A handful of mirrored values doesn't disqualify a message table.
+        let js = object(MINIMUM_TABLE_SIZE, |index| {
+            if index < 5 {
+                format!("\"KEY_{}\"", index)
+            } else {
+                format!("\"Message {}\"", index)
+            }
+        });
+        assert_eq!(tables(&js).len(), 1);
+    }
+
+    #[test]
+    fn diffs_messages() {
+        let old = messages(&[("KEPT", "Kept"), ("CHANGED", "Before"), ("REMOVED", "Gone")]);
+        let new = messages(&[("KEPT", "Kept"), ("CHANGED", "After"), ("ADDED", "New")]);
+        let changes = diff_messages(&old, &new);
+
+        assert_eq!(changes.added, BTreeMap::from([("ADDED", "New")]));
+        assert_eq!(changes.removed, BTreeMap::from([("REMOVED", "Gone")]));
+        assert_eq!(
+            changes.changed,
+            BTreeMap::from([("CHANGED", ("Before", "After"))])
+        );
+        assert!(!changes.is_empty());
+    }
+
+    #[test]
+    fn diffs_identical_messages() {
+        let old = messages(&[("A", "a"), ("B", "b")]);
+        assert!(diff_messages(&old, &old.clone()).is_empty());
+        assert!(diff_messages(&MessageMap::new(), &MessageMap::new()).is_empty());
+    }
+}
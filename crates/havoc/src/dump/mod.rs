@@ -3,16 +3,45 @@
 use std::{borrow::Cow, path::Path};
 
 use crate::{
-    artifact::Artifact,
-    discord::assets::{AnyError, AssetCache},
+    discord::assets::AnyError,
     scrape::{NetworkError, ScrapeError},
 };
 
+pub mod context;
+pub use context::{AnalysisContext, FetchedAssets, LocatedModule, ParsedScript};
+
 pub mod modules;
 pub use modules::WebpackModules;
 
 pub mod classes;
 pub use classes::CSSClasses;
+
+pub mod components;
+pub use components::Components;
+
+pub mod endpoints;
+pub use endpoints::Endpoints;
+
+pub mod experiments;
+pub use experiments::Experiments;
+
+pub mod flux;
+pub use flux::FluxActions;
+
+pub mod graph;
+pub use graph::WebpackModuleGraph;
+
+pub mod i18n;
+pub use i18n::I18nMessages;
+
+pub mod patches;
+pub use patches::PatchChecks;
+
+pub mod stores;
+pub use stores::Stores;
+
+pub mod registry;
+pub use registry::{dumpers, find_dumper, DumperDescriptor, DumperInvocation, RequiredAsset};
 use thiserror::Error;
 
 /// A dump result, returned by [`Dump::dump`](Dump::dump).
@@ -44,7 +73,7 @@ impl DumpResult {
         }
     }
 
-    pub fn writable_content(&self) -> Result<Cow<str>, DumpWriteError> {
+    pub fn writable_content(&self) -> Result<Cow<'_, str>, DumpWriteError> {
         Ok(match self.content {
             DumpContent::Json(ref value) => Cow::Owned(serde_json::to_string(value)?),
             DumpContent::Text { ref content, .. } => Cow::Borrowed(content),
@@ -90,13 +119,59 @@ pub enum DumpError {
 
     #[error("failed to parse/traverse JS")]
     JSParseError(#[from] crate::parse::ParseError),
+
+    #[error("asset wasn't prepared in the analysis context: {0}")]
+    Unprepared(String),
+
+    #[error("failed to configure dumper")]
+    Options(#[from] registry::DumpOptionError),
+
+    #[error("dumper panicked: {0}")]
+    Panicked(String),
+}
+
+/// Something that extracts useful data from the assets of an artifact.
+///
+/// Dumpers don't fetch or parse anything themselves; instead, they consume an
+/// [`AnalysisContext`] that was prepared with the assets they require. This
+/// lets many dumpers share the same parsed scripts and run concurrently.
+pub trait Dump: Send + Sync {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError>;
 }
 
-#[async_trait::async_trait]
-pub trait Dump {
-    async fn dump(
-        &mut self,
-        artifact: &(dyn Artifact + Sync),
-        cache: &mut AssetCache,
-    ) -> Result<DumpResult, DumpError>;
+/// Invokes multiple dumpers against the same analysis context, each on its own
+/// thread.
+///
+/// The results are returned in the same order as the invocations. A dumper
+/// that panics only fails its own invocation.
+pub fn dump_concurrently(
+    cx: &AnalysisContext<'_>,
+    invocations: &[DumperInvocation],
+) -> Vec<Result<DumpResult, DumpError>> {
+    std::thread::scope(|scope| {
+        let handles = invocations
+            .iter()
+            .map(|invocation| {
+                let span = tracing::info_span!("dumping", dumper = %invocation);
+                scope.spawn(move || {
+                    let _entered = span.enter();
+                    invocation.dumper()?.dump(cx)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().unwrap_or_else(|payload| {
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| "unknown panic".to_owned());
+                    Err(DumpError::Panicked(message))
+                })
+            })
+            .collect()
+    })
 }
@@ -0,0 +1,19 @@
+//! Patch definition checking, as a dumper.
+
+use crate::{
+    dump::{AnalysisContext, Dump, DumpError, DumpResult},
+    patches::{check_patches, Patch},
+};
+
+pub struct PatchChecks {
+    pub patches: Vec<Patch>,
+}
+
+impl Dump for PatchChecks {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let modules = cx.modules();
+        let reports = check_patches(&modules, &self.patches);
+
+        Ok(DumpResult::from_serializable(&reports, "patches")?)
+    }
+}
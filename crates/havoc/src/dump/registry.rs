@@ -0,0 +1,466 @@
+//! The set of dumpers that havoc knows about, alongside metadata describing
+//! them.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::{
+    CSSClasses, Components, Dump, Endpoints, Experiments, FluxActions, I18nMessages, PatchChecks,
+    Stores, WebpackModuleGraph, WebpackModules,
+};
+
+/// The kind of output that a dumper produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpOutputKind {
+    Json,
+    Text { extension: &'static str },
+}
+
+impl Display for DumpOutputKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DumpOutputKind::Json => write!(f, "json"),
+            DumpOutputKind::Text { extension } => write!(f, "text (.{})", extension),
+        }
+    }
+}
+
+/// An asset that a dumper needs in order to do its work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequiredAsset {
+    /// The entrypoint root script.
+    Entrypoint,
+
+    /// The root script containing CSS class mappings.
+    Classes,
+
+    /// The script chunks referenced by the chunk loader.
+    DeepChunks,
+}
+
+impl Display for RequiredAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use RequiredAsset::*;
+
+        match self {
+            Entrypoint => write!(f, "entrypoint"),
+            Classes => write!(f, "classes"),
+            DeepChunks => write!(f, "deep chunks"),
+        }
+    }
+}
+
+/// An option accepted by a dumper.
+#[derive(Debug)]
+pub struct DumperOption {
+    pub name: &'static str,
+    pub description: &'static str,
+
+    /// The value used when the option isn't specified, or `None` if the
+    /// option must always be specified.
+    pub default: Option<&'static str>,
+}
+
+type DumperConstructor = fn(&DumpOptions) -> Result<Box<dyn Dump>, DumpOptionError>;
+
+/// Describes a dumper and how to construct it.
+pub struct DumperDescriptor {
+    /// The name of the dumper, as used on the command line.
+    pub name: &'static str,
+    pub description: &'static str,
+    pub output: DumpOutputKind,
+    pub requires: &'static [RequiredAsset],
+    pub options: &'static [DumperOption],
+    construct: DumperConstructor,
+}
+
+impl std::fmt::Debug for DumperDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DumperDescriptor")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DumperDescriptor {
+    /// Constructs the dumper, configured with some options.
+    ///
+    /// Options that the dumper doesn't declare are rejected, and unspecified
+    /// options take on their default values. Options without a default must
+    /// be specified.
+    pub fn construct(&self, options: &DumpOptions) -> Result<Box<dyn Dump>, DumpOptionError> {
+        (self.construct)(&self.resolve_options(options)?)
+    }
+
+    /// Validates some options against the options that the dumper declares,
+    /// filling in default values.
+    fn resolve_options(&self, options: &DumpOptions) -> Result<DumpOptions, DumpOptionError> {
+        let mut resolved = DumpOptions::default();
+
+        for option in self.options {
+            match option.default {
+                Some(default) => {
+                    resolved
+                        .0
+                        .insert(option.name.to_owned(), default.to_owned());
+                }
+                None if !options.0.contains_key(option.name) => {
+                    return Err(DumpOptionError::Missing(option.name));
+                }
+                None => {}
+            }
+        }
+
+        for (name, value) in &options.0 {
+            if !self.options.iter().any(|option| option.name == name) {
+                return Err(DumpOptionError::UnknownOption {
+                    dumper: self.name,
+                    option: name.clone(),
+                });
+            }
+
+            resolved.0.insert(name.clone(), value.clone());
+        }
+
+        Ok(resolved)
+    }
+}
+
+static DUMPERS: &[DumperDescriptor] = &[
+    DumperDescriptor {
+        name: "classes",
+        description: "CSS class mappings from the classes root script, keyed by module ID",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Classes],
+        options: &[],
+        construct: |_| Ok(Box::new(CSSClasses)),
+    },
+    DumperDescriptor {
+        name: "modules",
+        description: "the source code of each Webpack module in the entrypoint, keyed by module ID",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint],
+        options: &[DumperOption {
+            name: "beautify",
+            description: "reformat the source code of each module",
+            default: Some("false"),
+        }],
+        construct: |options| {
+            Ok(Box::new(WebpackModules {
+                beautify: options.flag("beautify")?,
+            }))
+        },
+    },
+    DumperDescriptor {
+        name: "module_graph",
+        description: "the chunks containing each Webpack module, and the modules that each module lazily imports",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(WebpackModuleGraph)),
+    },
+    DumperDescriptor {
+        name: "flux_actions",
+        description: "Flux action types, alongside the modules that dispatch them and the stores that handle them",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(FluxActions)),
+    },
+    DumperDescriptor {
+        name: "stores",
+        description: "Flux stores, alongside their display names, handled action types, and module IDs",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(Stores)),
+    },
+    DumperDescriptor {
+        name: "components",
+        description: "React components, alongside their display names, module IDs, and the props passed to them",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(Components)),
+    },
+    DumperDescriptor {
+        name: "experiments",
+        description: "client experiments, alongside their kinds, labels, treatments, and module IDs",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(Experiments)),
+    },
+    DumperDescriptor {
+        name: "endpoints",
+        description: "REST endpoint tables, reconstructed into route templates",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[],
+        construct: |_| Ok(Box::new(Endpoints)),
+    },
+    DumperDescriptor {
+        name: "i18n",
+        description: "localization message tables, keyed by locale and message key",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[DumperOption {
+            name: "all",
+            description: "dump every locale instead of only en-US",
+            default: Some("false"),
+        }],
+        construct: |options| {
+            Ok(Box::new(I18nMessages {
+                all_locales: options.flag("all")?,
+            }))
+        },
+    },
+    DumperDescriptor {
+        name: "patches",
+        description: "whether client mod patch definitions still apply, loaded from a JSON file",
+        output: DumpOutputKind::Json,
+        requires: &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+        options: &[DumperOption {
+            name: "file",
+            description: "the path to a JSON file of patch definitions",
+            default: None,
+        }],
+        construct: |options| {
+            let path = options.get("file").unwrap_or_default();
+            if path.is_empty() {
+                return Err(DumpOptionError::Missing("file"));
+            }
+
+            let invalid = |message: String| DumpOptionError::Invalid {
+                option: "file".to_owned(),
+                message,
+            };
+            let json = std::fs::read_to_string(path)
+                .map_err(|err| invalid(format!("failed to read `{}`: {}", path, err)))?;
+            let patches = crate::patches::parse_patches(&json)
+                .map_err(|err| invalid(format!("failed to load `{}`: {}", path, err)))?;
+
+            Ok(Box::new(PatchChecks { patches }))
+        },
+    },
+];
+
+/// Returns all known dumpers.
+pub fn dumpers() -> &'static [DumperDescriptor] {
+    DUMPERS
+}
+
+/// Finds a dumper by name.
+pub fn find_dumper(name: &str) -> Option<&'static DumperDescriptor> {
+    DUMPERS.iter().find(|descriptor| descriptor.name == name)
+}
+
+/// Options passed to a dumper, keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions(HashMap<String, String>);
+
+impl DumpOptions {
+    /// Returns the raw value of an option.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Interprets an option as a boolean flag. Missing options are `false`.
+    pub fn flag(&self, name: &str) -> Result<bool, DumpOptionError> {
+        match self.get(name) {
+            None | Some("false") => Ok(false),
+            Some("true") => Ok(true),
+            Some(value) => Err(DumpOptionError::InvalidValue {
+                option: name.to_owned(),
+                value: value.to_owned(),
+                expected: "`true` or `false`",
+            }),
+        }
+    }
+}
+
+/// A dumper to invoke alongside its options, parsed from strings such as
+/// `modules` or `modules:beautify=true`.
+///
+/// Multiple options are separated by commas.
+#[derive(Debug, Clone)]
+pub struct DumperInvocation {
+    pub descriptor: &'static DumperDescriptor,
+    pub options: DumpOptions,
+}
+
+impl DumperInvocation {
+    /// Constructs the invoked dumper.
+    pub fn dumper(&self) -> Result<Box<dyn Dump>, DumpOptionError> {
+        self.descriptor.construct(&self.options)
+    }
+}
+
+impl Display for DumperInvocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.descriptor.name)
+    }
+}
+
+impl FromStr for DumperInvocation {
+    type Err = DumpOptionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, raw_options) = s.split_once(':').unwrap_or((s, ""));
+
+        let descriptor =
+            find_dumper(name).ok_or_else(|| DumpOptionError::UnknownDumper(name.to_owned()))?;
+
+        let mut options = DumpOptions::default();
+        for pair in raw_options.split(',').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| DumpOptionError::Malformed(pair.to_owned()))?;
+            options.0.insert(key.to_owned(), value.to_owned());
+        }
+
+        let invocation = DumperInvocation {
+            descriptor,
+            options,
+        };
+
+        // Construct the dumper once in order to validate the options up front.
+        invocation.dumper()?;
+
+        Ok(invocation)
+    }
+}
+
+/// Errors that can occur while resolving and configuring a dumper.
+#[derive(Error, Debug)]
+pub enum DumpOptionError {
+    #[error("`{0}` is an unknown dumper")]
+    UnknownDumper(String),
+
+    #[error("dumper `{dumper}` has no option named `{option}`")]
+    UnknownOption {
+        dumper: &'static str,
+        option: String,
+    },
+
+    #[error("malformed dumper option `{0}` (expected `key=value`)")]
+    Malformed(String),
+
+    #[error("option `{option}` expected {expected}, but got `{value}`")]
+    InvalidValue {
+        option: String,
+        value: String,
+        expected: &'static str,
+    },
+
+    #[error("option `{0}` must be specified")]
+    Missing(&'static str),
+
+    #[error("option `{option}` is invalid: {message}")]
+    Invalid { option: String, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static OPTIONS: &[DumperOption] = &[
+        DumperOption {
+            name: "pretty",
+            description: "",
+            default: Some("false"),
+        },
+        DumperOption {
+            name: "path",
+            description: "",
+            default: None,
+        },
+    ];
+
+    fn descriptor() -> DumperDescriptor {
+        DumperDescriptor {
+            name: "test",
+            description: "",
+            output: DumpOutputKind::Json,
+            requires: &[],
+            options: OPTIONS,
+            construct: |_| Ok(Box::new(CSSClasses)),
+        }
+    }
+
+    fn options(pairs: &[(&str, &str)]) -> DumpOptions {
+        DumpOptions(
+            pairs
+                .iter()
+                .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let resolved = descriptor()
+            .resolve_options(&options(&[("path", "a.json")]))
+            .unwrap();
+        assert_eq!(resolved.get("pretty"), Some("false"));
+        assert_eq!(resolved.get("path"), Some("a.json"));
+
+        let resolved = descriptor()
+            .resolve_options(&options(&[("path", "a.json"), ("pretty", "true")]))
+            .unwrap();
+        assert_eq!(resolved.get("pretty"), Some("true"));
+    }
+
+    #[test]
+    fn requires_options_without_defaults() {
+        assert!(matches!(
+            descriptor().resolve_options(&options(&[("pretty", "true")])),
+            Err(DumpOptionError::Missing("path"))
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(matches!(
+            descriptor().resolve_options(&options(&[("path", "a.json"), ("ugly", "true")])),
+            Err(DumpOptionError::UnknownOption { dumper: "test", option }) if option == "ugly"
+        ));
+    }
+
+    #[test]
+    fn parses_invocations() {
+        let invocation = "modules".parse::<DumperInvocation>().unwrap();
+        assert_eq!(invocation.descriptor.name, "modules");
+        assert_eq!(invocation.options.get("beautify"), None);
+
+        let invocation = "modules:beautify=true".parse::<DumperInvocation>().unwrap();
+        assert_eq!(invocation.options.get("beautify"), Some("true"));
+        assert!(invocation.options.flag("beautify").unwrap());
+
+        let invocation = "i18n:".parse::<DumperInvocation>().unwrap();
+        assert_eq!(invocation.descriptor.name, "i18n");
+    }
+
+    #[test]
+    fn rejects_invalid_invocations() {
+        let error = |s: &str| s.parse::<DumperInvocation>().unwrap_err();
+
+        assert!(matches!(error("nope"), DumpOptionError::UnknownDumper(name) if name == "nope"));
+        assert!(matches!(
+            error("modules:pretty=true"),
+            DumpOptionError::UnknownOption { dumper: "modules", option } if option == "pretty"
+        ));
+        assert!(matches!(
+            error("modules:beautify"),
+            DumpOptionError::Malformed(pair) if pair == "beautify"
+        ));
+        assert!(matches!(
+            error("modules:beautify=yes"),
+            DumpOptionError::InvalidValue { value, .. } if value == "yes"
+        ));
+        assert!(matches!(error("patches"), DumpOptionError::Missing("file")));
+    }
+}
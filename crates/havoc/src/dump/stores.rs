@@ -0,0 +1,255 @@
+//! Flux store dumping.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::{
+    dump::{
+        flux::walk_module_flux_actions, AnalysisContext, Dump, DumpError, DumpResult, LocatedModule,
+    },
+    parse::{util::DisplayNameVisitor, ChunkId, ModuleId},
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// A Flux store.
+#[derive(Serialize, Debug)]
+pub struct Store {
+    /// The display name of the store, if one could be found.
+    pub display_name: Option<String>,
+
+    /// The module that the store is defined in.
+    pub module_id: ModuleId,
+
+    /// The ID of the chunk containing the store's module, if it was found in a
+    /// deep chunk.
+    pub chunk_id: Option<ChunkId>,
+
+    /// The Flux action types handled by the store's module.
+    pub handled_actions: BTreeSet<String>,
+}
+
+/// Collects the identifiers of classes that extend another class.
+///
+/// Anonymous classes are collected as `None`, unless they're immediately bound
+/// to a variable.
+#[derive(Default)]
+struct SubclassVisitor {
+    subclasses: Vec<Option<String>>,
+}
+
+impl Visit for SubclassVisitor {
+    fn visit_class_decl(&mut self, n: &ast::ClassDecl) {
+        if n.class.super_class.is_some() {
+            self.subclasses.push(Some(n.ident.sym.to_string()));
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, n: &ast::VarDeclarator) {
+        // let c = class extends ... {}
+        if_chain::if_chain! {
+            if let ast::Pat::Ident(binding) = &n.name;
+            if let Some(ast::Expr::Class(ast::ClassExpr { ident: None, class })) = n.init.as_deref();
+            if class.super_class.is_some();
+            then {
+                self.subclasses.push(Some(binding.id.sym.to_string()));
+                class.visit_children_with(self);
+                return;
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_class_expr(&mut self, n: &ast::ClassExpr) {
+        if n.class.super_class.is_some() {
+            self.subclasses
+                .push(n.ident.as_ref().map(|ident| ident.sym.to_string()));
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the stores defined within a module.
+///
+/// A store is a subclass that either has a display name (or identifier) ending
+/// in `Store`, or is the only subclass within a module that handles Flux
+/// actions.
+pub fn walk_module_stores(module: &LocatedModule<'_>) -> Vec<Store> {
+    let mut subclass_visitor = SubclassVisitor::default();
+    module.module.func.visit_with(&mut subclass_visitor);
+
+    if subclass_visitor.subclasses.is_empty() {
+        return vec![];
+    }
+
+    let mut display_name_visitor = DisplayNameVisitor::default();
+    module.module.func.visit_with(&mut display_name_visitor);
+    let display_names = display_name_visitor.display_names;
+
+    let handled_actions = walk_module_flux_actions(module).handled;
+    let lone_subclass = subclass_visitor.subclasses.len() == 1;
+
+    subclass_visitor
+        .subclasses
+        .into_iter()
+        .filter_map(|ident| {
+            let display_name = display_names
+                .iter()
+                .find(|display_name| ident.is_some() && display_name.target == ident)
+                .map(|display_name| display_name.name.clone())
+                // Fall back to the class name, which is only meaningful when
+                // it survived minification.
+                .or_else(|| ident.filter(|ident| ident.ends_with("Store")))
+                .or_else(|| {
+                    // An anonymous store, but there's only one subclass to
+                    // attribute a stray display name to.
+                    display_names
+                        .iter()
+                        .find(|display_name| lone_subclass && display_name.name.ends_with("Store"))
+                        .map(|display_name| display_name.name.clone())
+                });
+
+            let is_store = display_name
+                .as_deref()
+                .is_some_and(|name| name.ends_with("Store"))
+                || (lone_subclass && !handled_actions.is_empty());
+
+            is_store.then(|| Store {
+                display_name,
+                module_id: module.id(),
+                chunk_id: module.chunk_id,
+                handled_actions: handled_actions.clone(),
+            })
+        })
+        .collect()
+}
+
+pub struct Stores;
+
+impl Dump for Stores {
+    fn dump(&self, cx: &AnalysisContext<'_>) -> Result<DumpResult, DumpError> {
+        let modules = cx.modules();
+        let stores = modules
+            .iter()
+            .flat_map(walk_module_stores)
+            .collect::<Vec<_>>();
+
+        tracing::info!(
+            "found {} store(s) across {} module(s)",
+            stores.len(),
+            modules.len()
+        );
+
+        Ok(DumpResult::from_serializable(&stores, "stores")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::ParsedScript;
+
+    /// Finds the stores within a single module, described by their display
+    /// names and handled actions.
+    fn stores(module: &str) -> Vec<(Option<String>, Vec<String>)> {
+        let script = ParsedScript::test_chunk(&format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ 10: (e, t, n) => {{ {} }} }}]);",
+            module
+        ));
+        let modules = script.test_modules();
+
+        walk_module_stores(&modules[0])
+            .into_iter()
+            .map(|store| {
+                (
+                    store.display_name,
+                    store.handled_actions.into_iter().collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn names_stores_by_identifier() {
+        assert_eq!(
+            stores(
+                r#"class s extends n(1).ZP.Store {}
+                class a extends n(2).Component {}
+                a.displayName = "GuildList";
+                s.displayName = "GuildStore";"#
+            ),
+            vec![(Some("GuildStore".to_owned()), vec![])]
+        );
+    }
+
+    #[test]
+    fn names_stores_by_class_name() {
+        assert_eq!(
+            stores(
+                r#"class ChannelStore extends n(1).ZP.Store {}
+                class a extends n(2).Component {}"#
+            ),
+            vec![(Some("ChannelStore".to_owned()), vec![])]
+        );
+    }
+
+    #[test]
+    fn names_lone_anonymous_stores() {
+        assert_eq!(
+            stores(
+                r#"let i = n(1);
+                i.displayName = "UserStore";
+                t.Z = new (class extends n(2).ZP.Store {})();"#
+            ),
+            vec![(Some("UserStore".to_owned()), vec![])]
+        );
+
+        // The display name can't be attributed when there are other
+        // subclasses.
+        assert_eq!(
+            stores(
+                r#"let i = n(1);
+                i.displayName = "UserStore";
+                t.a = new (class extends n(2).ZP.Store {})();
+                t.b = class extends n(3).Component {};"#
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn detects_stores_by_handled_actions() {
+        let handlers = "{ CONNECTION_OPEN: function () {}, LOGOUT: l }";
+
+        assert_eq!(
+            stores(&format!(
+                "class s extends n(1).ZP.Store {{}} t.Z = new s(n(2).Z, {});",
+                handlers
+            )),
+            vec![(
+                None,
+                vec!["CONNECTION_OPEN".to_owned(), "LOGOUT".to_owned()]
+            )]
+        );
+
+        // Without a display name, a store can't be told apart from the other
+        // subclasses.
+        assert_eq!(
+            stores(&format!(
+                "class s extends n(1).ZP.Store {{}} class a extends n(2).Component {{}} t.Z = new s(n(3).Z, {});",
+                handlers
+            )),
+            vec![]
+        );
+
+        // Subclasses that neither look like stores nor handle actions aren't
+        // stores.
+        assert_eq!(stores("class a extends n(1).Component {}"), vec![]);
+    }
+}
@@ -2,4 +2,6 @@ pub mod artifact;
 pub mod discord;
 pub mod dump;
 pub mod parse;
+pub mod patches;
 pub mod scrape;
+pub mod search;
@@ -1,14 +1,19 @@
+use std::collections::HashMap;
 use std::io::Write;
+use std::path::PathBuf;
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use clap::{ArgAction, ArgMatches, Command};
+use regex::Regex;
 use termcolor::{Color, ColorChoice, ColorSpec, WriteColor};
-use tracing::Instrument;
 
 use havoc::artifact::Artifact;
 use havoc::discord::{AssetCache, AssetsExt, FeAsset, FeAssetType, FeBuild, RootScript};
-use havoc::dump::Dump;
+use havoc::dump::{dump_concurrently, AnalysisContext, DumperInvocation, RequiredAsset};
+use havoc::parse::{query::Pattern, ModuleId};
+use havoc::patches::{parse_patches, Patch, PatchStatus};
 use havoc::scrape::{self, extract_assets_from_chunk_loader};
+use havoc::search::{search_source, snippet, Occurrence, StringIndex, StringKind};
 
 fn app() -> clap::Command {
     clap::command!()
@@ -31,6 +36,16 @@ fn app() -> clap::Command {
                 .global(true),
         )
         .arg(clap::arg!(-V --version "print version").action(ArgAction::Version))
+        .arg(
+            clap::arg!(-j --threads <THREADS> "how many threads to parse scripts with")
+                .default_value("0")
+                .value_parser(clap::value_parser!(usize))
+                .long_help(
+                    "how many threads to parse scripts with when dumping; 0 uses
+one thread per logical core",
+                )
+                .global(true),
+        )
         .subcommand(
             Command::new("scrape")
                 .about("scrape a target")
@@ -42,9 +57,13 @@ scraped artifact can also be done.",
                 .arg(
                     clap::arg!(-d --dump "what to dump from the target")
                         .required(false)
+                        .value_parser(clap::value_parser!(DumperInvocation))
                         .long_help(
-                            r#"the names of dumpers to invoke on the target
-e.g. "modules", "classes""#,
+                            r#"the names of dumpers to invoke on the target, optionally
+followed by a colon and comma-separated options
+e.g. "classes", "modules:beautify=true"
+
+invoke `havoc dumpers` to list all dumpers"#,
                         )
                         .action(ArgAction::Append),
                 )
@@ -71,6 +90,97 @@ e.g. "fe:canary" to target latest canary"#,
                 .after_help("invoke with --help for more information")
                 .after_long_help(""),
         )
+        .subcommand(
+            Command::new("search")
+                .about("search the modules of a target")
+                .long_about(
+                    "This subcommand scrapes from a target and searches the string
+literals, identifiers, or raw source code of every module within the entrypoint
+and script chunks for a regular expression.",
+                )
+                .arg(
+                    clap::arg!(-k --kind <KIND> "what to search")
+                        .value_parser(["literal", "identifier", "source"])
+                        .default_value("literal")
+                        .long_help(
+                            r#"what to search: "literal" for string literals,
+"identifier" for identifiers and property names, or "source" for raw source code"#,
+                        ),
+                )
+                .arg(
+                    clap::arg!(-C --context <BYTES> "how much surrounding source code to show")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("40"),
+                )
+                .arg(
+                    clap::arg!(target: <TARGET> "what to search")
+                        .value_parser(clap::value_parser!(scrape::Target)),
+                )
+                .arg(
+                    clap::arg!(pattern: <PATTERN> "the regular expression to search for")
+                        .value_parser(clap::value_parser!(Regex)),
+                ),
+        )
+        .subcommand(
+            Command::new("query")
+                .about("query the modules of a target for AST shapes")
+                .long_about(
+                    "This subcommand scrapes from a target and finds every expression
+within the modules of the entrypoint and script chunks that matches a
+structural pattern.",
+                )
+                .arg(
+                    clap::arg!(-C --context <BYTES> "how much surrounding source code to show")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("40"),
+                )
+                .arg(
+                    clap::arg!(target: <TARGET> "what to query")
+                        .value_parser(clap::value_parser!(scrape::Target)),
+                )
+                .arg(
+                    clap::arg!(pattern: <PATTERN> "the pattern to match")
+                        .value_parser(clap::value_parser!(Pattern))
+                        .long_help(
+                            r#"the pattern to match, e.g. "object(actionHandler, displayName)"
+or "call(member(_, d), .., object(useState), ..)""#,
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("patches")
+                .about("work with client mod patch definitions")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("check")
+                        .about("check patch definitions against a target")
+                        .long_about(
+                            r#"This subcommand scrapes from a target and checks whether each patch
+definition still applies: exactly one module must contain the patch's find
+string, and the patch's match regex must match within that module.
+
+Patch definitions are read from a JSON file containing an array of objects with
+"find" and "match" keys, and optionally a "name"."#,
+                        )
+                        .arg(clap::arg!(--json "output the results as JSON"))
+                        .arg(
+                            clap::arg!(target: <TARGET> "what to check against")
+                                .value_parser(clap::value_parser!(scrape::Target)),
+                        )
+                        .arg(
+                            clap::arg!(patches: <PATCHES> "the patch definition file")
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("dumpers")
+                .about("list available dumpers")
+                .long_about(
+                    "This subcommand lists every dumper that can be invoked with
+`scrape --dump`, alongside the assets they require and the options they accept.",
+                ),
+        )
 }
 
 fn create_stdout(matches: &ArgMatches) -> (ColorChoice, termcolor::StandardStream) {
@@ -98,33 +208,90 @@ async fn main() -> Result<()> {
     let matches = app.get_matches();
     let (_color_choice, mut stdout) = create_stdout(&matches);
 
+    let threads = *matches.get_one::<usize>("threads").unwrap_or(&0);
+    havoc::parse::set_parsing_threads(threads).context("failed to set up parsing threads")?;
+
     if let Some(matches) = matches.subcommand_matches("scrape") {
         let target = matches
             .get_one::<scrape::Target>("target")
             .expect("no scrape target specified");
 
-        let scrape::Target::Frontend(branch) = target;
-
-        let manifest = scrape::scrape_fe_manifest(*branch)
-            .await
-            .context("failed to scrape frontend manifest")?;
-
         let mut cache = AssetCache::new();
-        let mut build = crate::scrape::scrape_fe_build(manifest, &mut cache)
-            .await
-            .context("failed to scrape frontend build")?;
+        let build = scrape_target(target, &mut cache).await?;
 
         print_build(&build, &mut cache, matches, &mut stdout).await?;
 
-        if let Some(dump_values) = matches.get_many("dump") {
-            let dumping = dump_values.copied().collect::<Vec<_>>();
-            dump_items(&dumping, &mut build, &mut cache).await?;
+        if let Some(dump_values) = matches.get_many::<DumperInvocation>("dump") {
+            let dumping = dump_values.cloned().collect::<Vec<_>>();
+            dump_items(&dumping, &build, &mut cache).await?;
         }
     }
 
+    if let Some(matches) = matches.subcommand_matches("search") {
+        let target = matches
+            .get_one::<scrape::Target>("target")
+            .expect("no search target specified");
+        let pattern = matches
+            .get_one::<Regex>("pattern")
+            .expect("no search pattern specified");
+
+        let mut cache = AssetCache::new();
+        let build = scrape_target(target, &mut cache).await?;
+        search(&build, &mut cache, pattern, matches, &mut stdout).await?;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("query") {
+        let target = matches
+            .get_one::<scrape::Target>("target")
+            .expect("no query target specified");
+        let pattern = matches
+            .get_one::<Pattern>("pattern")
+            .expect("no query pattern specified");
+
+        let mut cache = AssetCache::new();
+        let build = scrape_target(target, &mut cache).await?;
+        query(&build, &mut cache, pattern, matches, &mut stdout).await?;
+    }
+
+    if let Some(matches) = matches
+        .subcommand_matches("patches")
+        .and_then(|matches| matches.subcommand_matches("check"))
+    {
+        let target = matches
+            .get_one::<scrape::Target>("target")
+            .expect("no patch check target specified");
+        let path = matches
+            .get_one::<PathBuf>("patches")
+            .expect("no patch definition file specified");
+
+        let patches = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read patch definitions from {}", path.display()))?;
+        let patches = parse_patches(&patches).context("failed to parse patch definitions")?;
+
+        let mut cache = AssetCache::new();
+        let build = scrape_target(target, &mut cache).await?;
+        check_patches(&build, &mut cache, &patches, matches, &mut stdout).await?;
+    }
+
+    if matches.subcommand_matches("dumpers").is_some() {
+        print_dumpers(&mut stdout)?;
+    }
+
     Ok(())
 }
 
+async fn scrape_target(target: &scrape::Target, cache: &mut AssetCache) -> Result<FeBuild> {
+    let scrape::Target::Frontend(branch) = target;
+
+    let manifest = scrape::scrape_fe_manifest(*branch)
+        .await
+        .context("failed to scrape frontend manifest")?;
+
+    scrape::scrape_fe_build(manifest, cache)
+        .await
+        .context("failed to scrape frontend build")
+}
+
 async fn print_build(
     build: &FeBuild,
     cache: &mut AssetCache,
@@ -182,14 +349,15 @@ async fn print_build(
     for (asset, root_script_type) in assets
         .iter()
         .filter_by_type(FeAssetType::Js)
-        .zip(RootScript::assumed_ordering().into_iter())
+        .zip(RootScript::assumed_ordering())
     {
         match root_script_type {
             RootScript::ChunkLoader if matches.get_flag("deep") => {
                 if matches.get_flag("deep") {
-                    let script_chunks = extract_assets_from_chunk_loader(&build.manifest, cache)
-                        .await
-                        .context("failed to extract assets from chunk loader")?;
+                    let script_chunks =
+                        extract_assets_from_chunk_loader(&build.manifest.assets, cache)
+                            .await
+                            .context("failed to extract assets from chunk loader")?;
                     write_asset_plain(
                         asset,
                         Some(format!(
@@ -216,50 +384,278 @@ async fn print_build(
     Ok(())
 }
 
-fn resolve_dumper(name: &str) -> Option<Box<dyn Dump>> {
-    match name {
-        "classes" => Some(Box::new(havoc::dump::CSSClasses)),
-        "modules" => Some(Box::new(havoc::dump::WebpackModules)),
-        _ => None,
+async fn search(
+    build: &FeBuild,
+    cache: &mut AssetCache,
+    pattern: &Regex,
+    matches: &ArgMatches,
+    output: &mut termcolor::StandardStream,
+) -> Result<()> {
+    let context = *matches.get_one::<usize>("context").unwrap_or(&40);
+    let kind = matches
+        .get_one::<String>("kind")
+        .map_or("literal", String::as_str);
+
+    let cx = AnalysisContext::prepare(
+        build,
+        cache,
+        &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+    )
+    .await
+    .context("failed to prepare assets for searching")?;
+    let modules = cx.modules();
+    let sources: HashMap<ModuleId, &str> = modules
+        .iter()
+        .map(|module| (module.id(), module.source()))
+        .collect();
+
+    let occurrences: Vec<Occurrence> = match kind {
+        "source" => search_source(&modules, pattern).collect(),
+        _ => {
+            let kind = if kind == "identifier" {
+                StringKind::Identifier
+            } else {
+                StringKind::Literal
+            };
+
+            let index = StringIndex::build(&modules);
+            index
+                .search(kind, pattern)
+                .flat_map(|(_, occurrences)| occurrences.iter().cloned())
+                .collect()
+        }
+    };
+
+    print_occurrences(&occurrences, &sources, context, output)
+}
+
+async fn query(
+    build: &FeBuild,
+    cache: &mut AssetCache,
+    pattern: &Pattern,
+    matches: &ArgMatches,
+    output: &mut termcolor::StandardStream,
+) -> Result<()> {
+    let context = *matches.get_one::<usize>("context").unwrap_or(&40);
+
+    let cx = AnalysisContext::prepare(
+        build,
+        cache,
+        &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+    )
+    .await
+    .context("failed to prepare assets for querying")?;
+    let modules = cx.modules();
+    let sources: HashMap<ModuleId, &str> = modules
+        .iter()
+        .map(|module| (module.id(), module.source()))
+        .collect();
+
+    let occurrences: Vec<Occurrence> = modules
+        .iter()
+        .flat_map(|module| {
+            pattern
+                .find_in_module(&module.module)
+                .into_iter()
+                .map(|span| Occurrence {
+                    module_id: module.id(),
+                    chunk_id: module.chunk_id,
+                    range: module.source_range(span),
+                })
+        })
+        .collect();
+
+    print_occurrences(&occurrences, &sources, context, output)
+}
+
+/// The maximum amount of bytes of a match to print before truncating it.
+const MAX_MATCH_LENGTH: usize = 200;
+
+fn print_occurrences(
+    occurrences: &[Occurrence],
+    sources: &HashMap<ModuleId, &str>,
+    context: usize,
+    output: &mut termcolor::StandardStream,
+) -> Result<()> {
+    for occurrence in occurrences {
+        output.set_color(ColorSpec::new().set_bold(true))?;
+        write!(output, "module {}", occurrence.module_id)?;
+        output.reset()?;
+        match occurrence.chunk_id {
+            Some(chunk_id) => write!(output, " (chunk {}): ", chunk_id)?,
+            None => write!(output, " (entrypoint): ")?,
+        }
+
+        let snippet = snippet(
+            sources[&occurrence.module_id],
+            occurrence.range.clone(),
+            context,
+        );
+        write!(output, "{}", snippet.before)?;
+        output.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+        match snippet.highlighted.char_indices().nth(MAX_MATCH_LENGTH) {
+            Some((end, _)) => write!(output, "{}…", &snippet.highlighted[..end])?,
+            None => write!(output, "{}", snippet.highlighted)?,
+        }
+        output.reset()?;
+        writeln!(output, "{}", snippet.after)?;
     }
+
+    writeln!(output, "\n{} match(es)", occurrences.len())?;
+
+    Ok(())
+}
+
+async fn check_patches(
+    build: &FeBuild,
+    cache: &mut AssetCache,
+    patches: &[Patch],
+    matches: &ArgMatches,
+    output: &mut termcolor::StandardStream,
+) -> Result<()> {
+    let cx = AnalysisContext::prepare(
+        build,
+        cache,
+        &[RequiredAsset::Entrypoint, RequiredAsset::DeepChunks],
+    )
+    .await
+    .context("failed to prepare assets for checking patches")?;
+    let reports = havoc::patches::check_patches(&cx.modules(), patches);
+
+    if matches.get_flag("json") {
+        writeln!(output, "{}", serde_json::to_string_pretty(&reports)?)?;
+    } else {
+        for report in &reports {
+            let (color, status) = match report.status {
+                PatchStatus::Ok => (Color::Green, "ok"),
+                PatchStatus::FindNotFound => (Color::Red, "no module contains the find string"),
+                PatchStatus::FindAmbiguous => {
+                    (Color::Yellow, "multiple modules contain the find string")
+                }
+                PatchStatus::MatchNotFound => (Color::Red, "match doesn't match within the module"),
+            };
+
+            output.set_color(ColorSpec::new().set_bold(true))?;
+            write!(output, "{}", report.name.as_deref().unwrap_or(&report.find))?;
+            output.reset()?;
+            write!(output, ": ")?;
+            output.set_color(ColorSpec::new().set_fg(Some(color)))?;
+            writeln!(output, "{}", status)?;
+            output.reset()?;
+
+            if report.is_ok() {
+                continue;
+            }
+
+            let list = |module_ids: &[ModuleId]| {
+                module_ids
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+
+            if !report.found_in.is_empty() {
+                writeln!(output, "\tfound in: {}", list(&report.found_in))?;
+            }
+            if !report.matched_in.is_empty() {
+                writeln!(output, "\tmatch matches in: {}", list(&report.matched_in))?;
+            }
+            if let Some(nearest) = &report.nearest {
+                writeln!(
+                    output,
+                    "\tnearest module: {} ({:.0}% of words)",
+                    nearest.module_id,
+                    nearest.similarity * 100.0
+                )?;
+            }
+        }
+    }
+
+    let failed = reports.iter().filter(|report| !report.is_ok()).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} patch(es) failed", failed, reports.len());
+    }
+
+    Ok(())
+}
+
+fn print_dumpers(output: &mut termcolor::StandardStream) -> Result<()> {
+    for descriptor in havoc::dump::dumpers() {
+        output.set_color(ColorSpec::new().set_bold(true))?;
+        write!(output, "{}", descriptor.name)?;
+        output.reset()?;
+        writeln!(output, ": {}", descriptor.description)?;
+
+        let requires = descriptor
+            .requires
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(output, "\toutputs: {}", descriptor.output)?;
+        writeln!(output, "\trequires: {}", requires)?;
+
+        for option in descriptor.options {
+            match option.default {
+                Some(default) => writeln!(
+                    output,
+                    "\toption `{}` (default: {}): {}",
+                    option.name, default, option.description
+                )?,
+                None => writeln!(
+                    output,
+                    "\toption `{}` (required): {}",
+                    option.name, option.description
+                )?,
+            }
+        }
+    }
+
+    Ok(())
 }
 
 async fn dump_items(
-    dumping: &[&str],
-    artifact: &mut (dyn Artifact + Sync),
+    dumping: &[DumperInvocation],
+    artifact: &(dyn Artifact + Sync),
     assets: &mut AssetCache,
 ) -> Result<()> {
     let cwd = std::env::current_dir().context("failed to obtain current working dir")?;
 
-    for item in dumping {
-        let mut dumper: Box<dyn Dump> =
-            resolve_dumper(item).ok_or_else(|| anyhow!("`{}` is an unknown dumper", item))?;
+    let requirements = dumping
+        .iter()
+        .flat_map(|item| item.descriptor.requires)
+        .copied()
+        .collect::<Vec<_>>();
 
-        print!("dumping item \"{}\" ...", item);
+    let cx = AnalysisContext::prepare(artifact, assets, &requirements)
+        .await
+        .context("failed to prepare assets for dumping")?;
 
-        async {
-            let result = dumper
-                .dump(artifact, assets)
-                .await
-                .with_context(|| format!("failed to dump using dumper `{}`", item))?;
+    println!(
+        "dumping {} ...",
+        dumping
+            .iter()
+            .map(|item| format!("\"{}\"", item))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
 
-            let filename = result.filename();
+    for (item, result) in dumping.iter().zip(dump_concurrently(&cx, dumping)) {
+        let result = result.with_context(|| format!("failed to dump using dumper `{}`", item))?;
 
-            let full_filename = format!("havoc_{}_{}", artifact.dump_prefix(), filename);
-            let dest = cwd.join(full_filename.clone());
+        let filename = result.filename();
 
-            print!("\twriting \"{}\" to {} ...", result.name, full_filename);
+        let full_filename = format!("havoc_{}_{}", artifact.dump_prefix(), filename);
+        let dest = cwd.join(full_filename.clone());
 
-            result
-                .write(&dest)
-                .with_context(|| format!("failed to write dump result to disk at {:?}", dest))?;
+        print!("\twriting \"{}\" to {} ...", result.name, full_filename);
 
-            println!(" done");
+        result
+            .write(&dest)
+            .with_context(|| format!("failed to write dump result to disk at {:?}", dest))?;
 
-            anyhow::Ok(())
-        }
-        .instrument(tracing::info_span!("dumping", dumper = ?item))
-        .await?
+        println!(" done");
     }
 
     Ok(())
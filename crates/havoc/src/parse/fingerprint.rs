@@ -0,0 +1,130 @@
+//! Recognizing modules across builds.
+//!
+//! Module IDs are fairly stable, but the code of a module isn't directly
+//! comparable between builds: minification renames local variables, and the
+//! IDs of imported modules can shift around.
+
+use sha2::{Digest, Sha256};
+
+use super::WebpackModule;
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::Visit;
+
+struct FingerprintVisitor {
+    hasher: Sha256,
+}
+
+impl FingerprintVisitor {
+    fn feed(&mut self, kind: u8, value: &str) {
+        // Prefixing the kind and suffixing a terminator keeps adjacent values
+        // from running into each other.
+        self.hasher.update([kind]);
+        self.hasher.update(value.as_bytes());
+        self.hasher.update([0]);
+    }
+}
+
+impl Visit for FingerprintVisitor {
+    fn visit_str(&mut self, n: &ast::Str) {
+        self.feed(b's', &n.value);
+    }
+
+    fn visit_tpl_element(&mut self, n: &ast::TplElement) {
+        if let Some(cooked) = &n.cooked {
+            self.feed(b's', cooked);
+        }
+    }
+
+    fn visit_member_prop(&mut self, n: &ast::MemberProp) {
+        match n {
+            ast::MemberProp::Ident(ident) => self.feed(b'p', &ident.sym),
+            ast::MemberProp::PrivateName(name) => self.feed(b'p', &name.id.sym),
+            ast::MemberProp::Computed(computed) => self.visit_computed_prop_name(computed),
+        }
+    }
+
+    fn visit_prop_name(&mut self, n: &ast::PropName) {
+        match n {
+            ast::PropName::Ident(ident) => self.feed(b'k', &ident.sym),
+            ast::PropName::Str(str) => self.feed(b'k', &str.value),
+            ast::PropName::Computed(computed) => self.visit_computed_prop_name(computed),
+            ast::PropName::Num(_) | ast::PropName::BigInt(_) => {}
+        }
+    }
+}
+
+/// Computes a hexadecimal fingerprint of a module from what survives
+/// minification: its string literals and the names of the properties it
+/// accesses and defines, in order of appearance.
+///
+/// Two modules with the same fingerprint are very likely the same code, even
+/// across builds. Numbers are ignored, as they are mostly module IDs.
+pub fn fingerprint_module(module: &WebpackModule<'_>) -> String {
+    let mut visitor = FingerprintVisitor {
+        hasher: Sha256::new(),
+    };
+    module.func.visit_with(&mut visitor);
+
+    hex::encode(visitor.hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_script, walk_webpack_chunk};
+
+    /// Fingerprints the only module within a chunk.
+    fn fingerprint(module: &str) -> String {
+        let script = parse_script(&format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ {} }}]);",
+            module
+        ))
+        .unwrap();
+        let chunk = walk_webpack_chunk(&script).unwrap();
+        let [module] = chunk.modules.values().collect::<Vec<_>>()[..] else {
+            panic!("expected a single module");
+        };
+        fingerprint_module(module)
+    }
+
+    const MODULE: &str = r#"10: (e, t, n) => {
+        var r = n(123), i = n(456);
+        function o(e) { return r.Z.get(e.id, "messages"); }
+        t.Z = { getMessages: o, load: () => i.a(`channel ${o}`) };
+    }"#;
+
+    #[test]
+    fn ignores_minification() {
+        let renamed = r#"10: (a, b, c) => {
+            var x = c(123), y = c(456);
+            function z(a) { return x.Z.get(a.id, "messages"); }
+            b.Z = { getMessages: z, load: () => y.a(`channel ${z}`) };
+        }"#;
+        let shifted = r#"20: (e, t, n) => {
+            var r = n(789), i = n(12);
+            function o(e) { return r.Z.get(e.id, "messages"); }
+            t.Z = { getMessages: o, load: () => i.a(`channel ${o}`) };
+        }"#;
+
+        assert_eq!(fingerprint(MODULE), fingerprint(renamed));
+        assert_eq!(fingerprint(MODULE), fingerprint(shifted));
+    }
+
+    #[test]
+    fn changes_with_strings_and_properties() {
+        let fingerprints = [
+            MODULE.replace(r#""messages""#, r#""threads""#),
+            MODULE.replace("`channel ", "`thread "),
+            MODULE.replace("r.Z.get", "r.Z.fetch"),
+            MODULE.replace("getMessages:", "getThreads:"),
+        ]
+        .iter()
+        .map(|module| fingerprint(module))
+        .collect::<Vec<_>>();
+
+        for changed in &fingerprints {
+            assert_ne!(&fingerprint(MODULE), changed);
+        }
+    }
+}
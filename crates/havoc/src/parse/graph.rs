@@ -0,0 +1,223 @@
+//! The relationships between the modules and chunks of a build.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::{
+    util::{callee_prop_name, member_prop_name, num_lit},
+    ChunkId, ModuleId, WebpackChunk, WebpackModule,
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// A lazy import of a module, e.g. `n.e(123).then(n.bind(n, 456))`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LazyImport {
+    /// The chunks that are loaded before the module can be imported.
+    pub chunks: Vec<ChunkId>,
+
+    /// The imported module.
+    pub module_id: ModuleId,
+}
+
+/// Collects the chunks loaded with `n.e(...)` within an expression.
+struct ChunkLoadVisitor<'r> {
+    require: &'r str,
+    chunks: Vec<ChunkId>,
+}
+
+impl Visit for ChunkLoadVisitor<'_> {
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        if_chain::if_chain! {
+            if let ast::Callee::Expr(callee) = &n.callee;
+            if let ast::Expr::Member(member) = &**callee;
+            if matches!(&*member.obj, ast::Expr::Ident(ident) if &*ident.sym == self.require);
+            if member_prop_name(member) == Some("e");
+            if let [arg] = n.args.as_slice();
+            if let Some(chunk_id) = num_lit(&arg.expr);
+            then {
+                self.chunks.push(chunk_id as ChunkId);
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+struct LazyImportVisitor<'r> {
+    require: &'r str,
+    imports: Vec<LazyImport>,
+}
+
+impl Visit for LazyImportVisitor<'_> {
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        // n.e(123).then(n.bind(n, 456))
+        // Promise.all([n.e(123), n.e(456)]).then(n.t.bind(n, 789, 23))
+        if_chain::if_chain! {
+            if callee_prop_name(n) == Some("then");
+            if let ast::Callee::Expr(callee) = &n.callee;
+            if let ast::Expr::Member(then) = &**callee;
+            if let Some(ast::Expr::Call(bind)) = n.args.first().map(|arg| &*arg.expr);
+            if callee_prop_name(bind) == Some("bind");
+            if let Some(module_id) = bind.args.get(1).and_then(|arg| num_lit(&arg.expr));
+            then {
+                let mut visitor = ChunkLoadVisitor {
+                    require: self.require,
+                    chunks: vec![],
+                };
+                then.obj.visit_with(&mut visitor);
+
+                self.imports.push(LazyImport {
+                    chunks: visitor.chunks,
+                    module_id: module_id as ModuleId,
+                });
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the lazy imports made by a module.
+pub fn walk_module_lazy_imports(module: &WebpackModule<'_>) -> Vec<LazyImport> {
+    let Some(require) = module.require_ident() else {
+        return vec![];
+    };
+
+    let mut visitor = LazyImportVisitor {
+        require,
+        imports: vec![],
+    };
+    module.func.visit_with(&mut visitor);
+    visitor.imports
+}
+
+/// The chunks that contain each module, and the modules that each module
+/// lazily imports.
+#[derive(Serialize, Debug, Default)]
+pub struct ModuleGraph {
+    /// The chunks containing each module, keyed by module ID.
+    pub module_chunks: BTreeMap<ModuleId, BTreeSet<ChunkId>>,
+
+    /// The lazy imports made by each module, keyed by module ID.
+    pub lazy_imports: BTreeMap<ModuleId, Vec<LazyImport>>,
+}
+
+impl ModuleGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the modules of a chunk to the graph.
+    pub fn add_chunk(&mut self, chunk: &WebpackChunk<'_>) {
+        for (module_id, module) in &chunk.modules {
+            self.module_chunks
+                .entry(*module_id)
+                .or_default()
+                .extend(chunk.chunks.iter().copied());
+
+            if self.lazy_imports.contains_key(module_id) {
+                continue;
+            }
+
+            let imports = walk_module_lazy_imports(module);
+            if !imports.is_empty() {
+                self.lazy_imports.insert(*module_id, imports);
+            }
+        }
+    }
+
+    /// Returns the chunks that contain a module. Loading any of them makes
+    /// the module available.
+    pub fn chunks_containing(&self, module_id: ModuleId) -> Option<&BTreeSet<ChunkId>> {
+        self.module_chunks.get(&module_id)
+    }
+
+    /// Returns the lazy imports made by a module.
+    pub fn lazy_imports_of(&self, module_id: ModuleId) -> &[LazyImport] {
+        self.lazy_imports.get(&module_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates over the modules that lazily import a module.
+    pub fn lazy_importers_of(&self, module_id: ModuleId) -> impl Iterator<Item = ModuleId> + '_ {
+        self.lazy_imports
+            .iter()
+            .filter(move |(_, imports)| imports.iter().any(|import| import.module_id == module_id))
+            .map(|(importer, _)| *importer)
+    }
+}
+
+impl<'a, 'c: 'a> FromIterator<&'a WebpackChunk<'c>> for ModuleGraph {
+    fn from_iter<I: IntoIterator<Item = &'a WebpackChunk<'c>>>(chunks: I) -> Self {
+        let mut graph = ModuleGraph::new();
+        for chunk in chunks {
+            graph.add_chunk(chunk);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_script, walk_webpack_chunk};
+
+    fn chunk_source(chunk_ids: &str, modules: &str) -> String {
+        format!(
+            "(self.webpackChunk = self.webpackChunk || []).push([[{}], {{ {} }}]);",
+            chunk_ids, modules
+        )
+    }
+
+    fn lazy_import(chunks: &[ChunkId], module_id: ModuleId) -> LazyImport {
+        LazyImport {
+            chunks: chunks.to_vec(),
+            module_id,
+        }
+    }
+
+    #[test]
+    fn builds_module_graphs() {
+        let scripts = [
+            chunk_source(
+                "1",
+                r#"10: (e, t, n) => {
+                    t.a = () => n.e(2).then(n.bind(n, 20));
+                    t.b = () => Promise.all([n.e(2), n.e(3)]).then(n.t.bind(n, 30, 23));
+                    t.c = () => n.e(2).then(console.log.bind(console, "loaded"));
+                },
+                11: (e, t, n) => {
+                    t.a = () => n.e(2).then(n.bind(n, 20));
+                }"#,
+            ),
+            chunk_source("2", "20: (e, t, n) => {}, 11: (e, t, n) => {}"),
+            chunk_source("3, 4", "30: (e, t, n) => {}"),
+        ]
+        .map(|source| parse_script(&source).unwrap());
+        let chunks = scripts
+            .iter()
+            .map(|script| walk_webpack_chunk(script).unwrap())
+            .collect::<Vec<_>>();
+        let graph = chunks.iter().collect::<ModuleGraph>();
+
+        assert_eq!(
+            graph.lazy_imports_of(10),
+            [lazy_import(&[2], 20), lazy_import(&[2, 3], 30)]
+        );
+        assert_eq!(graph.lazy_imports_of(11), [lazy_import(&[2], 20)]);
+        assert!(graph.lazy_imports_of(20).is_empty());
+
+        assert_eq!(graph.chunks_containing(11), Some(&BTreeSet::from([1, 2])));
+        assert_eq!(graph.chunks_containing(30), Some(&BTreeSet::from([3, 4])));
+        assert_eq!(graph.chunks_containing(40), None);
+
+        assert_eq!(
+            graph.lazy_importers_of(20).collect::<Vec<_>>(),
+            vec![10, 11]
+        );
+        assert_eq!(graph.lazy_importers_of(30).collect::<Vec<_>>(), vec![10]);
+        assert_eq!(graph.lazy_importers_of(10).count(), 0);
+    }
+}
@@ -1,21 +1,35 @@
+pub mod fingerprint;
+pub mod graph;
+pub mod print;
+pub mod query;
+pub mod runtime;
+pub mod util;
 pub mod webpack;
+pub use print::Printer;
 pub use webpack::*;
 
-use swc_common::{sync::Lrc, FileName, SourceMap};
+use std::fmt::Display;
+use std::time::Instant;
+
+use rayon::prelude::*;
+use swc_common::BytePos;
 use swc_ecma_parser::{error::Error as SwcError, lexer::Lexer, Parser, StringInput, Syntax};
 extern crate swc_ecma_ast as ast;
 use thiserror::Error;
 
 /// Parses a script.
-pub fn parse_script(js: String) -> Result<ast::Script, ParseError> {
-    let cm: Lrc<SourceMap> = Default::default();
-    let fm = cm.new_source_file(FileName::Custom("script.js".into()), js);
+pub fn parse_script(js: &str) -> Result<ast::Script, ParseError> {
+    // Spans start at one, like they would if the script were the first file
+    // added to a `SourceMap`. Lexing directly from the string avoids copying
+    // it into one.
+    let start = BytePos(1);
+    let end = start + BytePos(js.len() as u32);
 
     let lexer = Lexer::new(
         Syntax::Es(Default::default()),
         // JscTarget = es5
         Default::default(),
-        StringInput::from(&*fm),
+        StringInput::new(js, start, end),
         None,
     );
 
@@ -23,6 +37,51 @@ pub fn parse_script(js: String) -> Result<ast::Script, ParseError> {
     Ok(parser.parse_script()?)
 }
 
+/// Parses many scripts in parallel on the parsing thread pool.
+///
+/// Each script is labelled for the purposes of logging. The results are
+/// returned in the same order as the scripts.
+pub fn parse_scripts<L: Display + Sync>(
+    scripts: &[(L, &str)],
+) -> Vec<Result<ast::Script, ParseError>> {
+    let started = Instant::now();
+
+    let results = scripts
+        .par_iter()
+        .map(|(label, js)| {
+            let started = Instant::now();
+            let result = parse_script(js);
+            tracing::info!(
+                script = %label,
+                bytes = js.len(),
+                elapsed = ?started.elapsed(),
+                "parsed script"
+            );
+            result
+        })
+        .collect();
+
+    tracing::info!(
+        scripts = scripts.len(),
+        elapsed = ?started.elapsed(),
+        "parsed batch of scripts"
+    );
+
+    results
+}
+
+/// Sets the number of threads used by [`parse_scripts`], where zero means one
+/// thread per logical core.
+///
+/// This must be called before any scripts are parsed in parallel, and may only
+/// be called once.
+pub fn set_parsing_threads(threads: usize) -> Result<(), rayon::ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("havoc-parse-{}", index))
+        .build_global()
+}
+
 #[derive(Error, Debug)]
 pub enum ParseError {
     #[error("missing ast node: {0}")]
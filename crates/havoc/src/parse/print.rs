@@ -0,0 +1,61 @@
+//! Turning AST nodes back into source code.
+
+use swc_common::{sync::Lrc, FileName, SourceMap};
+use swc_ecma_codegen::{text_writer::JsWriter, Emitter, Node};
+extern crate swc_ecma_ast as ast;
+
+use super::FunctionLike;
+
+/// Emits AST nodes as formatted (non-minified) source code.
+///
+/// The code generator occasionally peeks at the original source text through
+/// spans, so a printer must be created from the same source that the nodes were
+/// parsed from.
+pub struct Printer {
+    cm: Lrc<SourceMap>,
+}
+
+impl Printer {
+    /// Creates a printer for nodes parsed from `js` with
+    /// [`parse_script`](super::parse_script).
+    pub fn new(js: String) -> Self {
+        let cm: Lrc<SourceMap> = Default::default();
+
+        // This mirrors `parse_script`, which keeps the spans of both source
+        // maps in agreement.
+        cm.new_source_file(FileName::Custom("script.js".into()), js);
+
+        Self { cm }
+    }
+
+    /// Prints a function-like AST node.
+    pub fn print_function_like(&self, func: &FunctionLike<'_>) -> String {
+        let expr = match func {
+            FunctionLike::Function(function) => ast::Expr::Fn(ast::FnExpr {
+                ident: None,
+                function: Box::new((*function).clone()),
+            }),
+            FunctionLike::Arrow(arrow_expr) => ast::Expr::Arrow((*arrow_expr).clone()),
+        };
+
+        self.print(&expr)
+    }
+
+    fn print<N: Node>(&self, node: &N) -> String {
+        let mut buf = vec![];
+
+        {
+            let mut emitter = Emitter {
+                cfg: Default::default(),
+                cm: self.cm.clone(),
+                comments: None,
+                wr: JsWriter::new(self.cm.clone(), "\n", &mut buf, None),
+            };
+
+            node.emit_with(&mut emitter)
+                .expect("failed to emit node into an in-memory buffer");
+        }
+
+        String::from_utf8(buf).expect("code generator emitted malformed utf-8")
+    }
+}
@@ -0,0 +1,840 @@
+//! Structural queries over the AST.
+//!
+//! Patterns describe the shape of an expression and can either be built in
+//! Rust or parsed from a small query language:
+//!
+//! ```text
+//! _                        any expression
+//! "text"                   a string literal with exactly this value
+//! /regex/                  a string literal matching a regex
+//! 123                      a number literal (also `0x7b`, `1_000`, etc.)
+//! fn                       a function or arrow function
+//! ident                    any identifier
+//! ident(name)              an identifier with a certain name
+//! member(object, name)     a member expression, e.g. `member(_, d)` for `a.d`
+//! call(callee, args...)    a call expression
+//! new(callee, args...)     a `new` expression
+//! array(elems...)          an array literal
+//! object(key, key: value)  an object literal with at least these keys
+//! has(pattern)             an expression containing a match somewhere within
+//! any(pattern, ...)        any of several patterns
+//! ```
+//!
+//! Names (in `ident`, `member`, and object keys) are bare words, quoted
+//! strings, regexes, or `_`. Within the arguments of `call`, `new`, and
+//! `array`, `..` matches any amount of elements. For example, this matches
+//! calls to a method named `d` that are passed an object with a `useState` key
+//! as any argument:
+//!
+//! ```text
+//! call(member(_, d), .., object(useState), ..)
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+use thiserror::Error;
+
+use super::util::{member_prop_name, num_lit, object_props, str_lit, PropValue};
+use super::WebpackModule;
+
+extern crate swc_ecma_ast as ast;
+use swc_common::{Span, Spanned};
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// A pattern matching a name, such as that of an identifier or a property.
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// Matches any name.
+    Any,
+    Exact(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Any => true,
+            NamePattern::Exact(exact) => exact == name,
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl From<&str> for NamePattern {
+    fn from(name: &str) -> Self {
+        NamePattern::Exact(name.to_owned())
+    }
+}
+
+impl From<Regex> for NamePattern {
+    fn from(regex: Regex) -> Self {
+        NamePattern::Regex(regex)
+    }
+}
+
+/// A pattern matching the shape of an expression.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// Matches any expression.
+    Any,
+
+    /// Matches any amount of elements within the arguments of a call or the
+    /// elements of an array. Elsewhere, it behaves like [`Pattern::Any`].
+    Rest,
+
+    Str(NamePattern),
+    Num(f64),
+
+    /// Matches functions and arrow functions.
+    Function,
+
+    Ident(NamePattern),
+    Member {
+        object: Box<Pattern>,
+        property: NamePattern,
+    },
+    Call {
+        callee: Box<Pattern>,
+        args: Vec<Pattern>,
+    },
+    New {
+        callee: Box<Pattern>,
+        args: Vec<Pattern>,
+    },
+    Array(Vec<Pattern>),
+
+    /// Matches object literals that have at least the given keys, whose values
+    /// match the accompanying patterns.
+    Object(Vec<(NamePattern, Pattern)>),
+
+    /// Matches expressions containing a match of the pattern within them.
+    Has(Box<Pattern>),
+
+    /// Matches if any of the patterns match.
+    Either(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn str(value: impl Into<NamePattern>) -> Self {
+        Pattern::Str(value.into())
+    }
+
+    pub fn ident(name: impl Into<NamePattern>) -> Self {
+        Pattern::Ident(name.into())
+    }
+
+    pub fn member(object: Pattern, property: impl Into<NamePattern>) -> Self {
+        Pattern::Member {
+            object: Box::new(object),
+            property: property.into(),
+        }
+    }
+
+    pub fn call(callee: Pattern, args: impl IntoIterator<Item = Pattern>) -> Self {
+        Pattern::Call {
+            callee: Box::new(callee),
+            args: args.into_iter().collect(),
+        }
+    }
+
+    pub fn object<N: Into<NamePattern>>(keys: impl IntoIterator<Item = (N, Pattern)>) -> Self {
+        Pattern::Object(
+            keys.into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    pub fn has(pattern: Pattern) -> Self {
+        Pattern::Has(Box::new(pattern))
+    }
+
+    /// Returns whether an expression matches this pattern.
+    pub fn matches(&self, expr: &ast::Expr) -> bool {
+        // Parentheses don't affect the shape of an expression.
+        if let ast::Expr::Paren(ast::ParenExpr { expr, .. }) = expr {
+            return self.matches(expr);
+        }
+
+        match self {
+            Pattern::Any | Pattern::Rest => true,
+            Pattern::Str(value) => str_lit(expr).is_some_and(|s| value.matches(s)),
+            Pattern::Num(value) => num_lit(expr) == Some(*value),
+            Pattern::Function => matches!(expr, ast::Expr::Fn(_) | ast::Expr::Arrow(_)),
+            Pattern::Ident(name) => {
+                matches!(expr, ast::Expr::Ident(ident) if name.matches(&ident.sym))
+            }
+            Pattern::Member { object, property } => match expr {
+                ast::Expr::Member(member) => {
+                    object.matches(&member.obj)
+                        && member_prop_name(member).is_some_and(|name| property.matches(name))
+                }
+                _ => false,
+            },
+            Pattern::Call { callee, args } => match expr {
+                ast::Expr::Call(ast::CallExpr {
+                    callee: ast::Callee::Expr(callee_expr),
+                    args: call_args,
+                    ..
+                }) => callee.matches(callee_expr) && matches_args(args, call_args),
+                _ => false,
+            },
+            Pattern::New { callee, args } => match expr {
+                ast::Expr::New(new) => {
+                    callee.matches(&new.callee)
+                        && matches_args(args, new.args.as_deref().unwrap_or_default())
+                }
+                _ => false,
+            },
+            Pattern::Array(elems) => match expr {
+                ast::Expr::Array(array) => {
+                    let array_elems = array
+                        .elems
+                        .iter()
+                        .flatten()
+                        .map(|elem| &*elem.expr)
+                        .collect::<Vec<_>>();
+                    matches_sequence(elems, &array_elems)
+                }
+                _ => false,
+            },
+            Pattern::Object(keys) => match expr {
+                ast::Expr::Object(object) => keys.iter().all(|(key, value)| {
+                    object_props(object).any(|(name, prop_value)| {
+                        key.matches(name) && value.matches_prop_value(prop_value)
+                    })
+                }),
+                _ => false,
+            },
+            Pattern::Has(pattern) => {
+                let mut visitor = HasVisitor {
+                    pattern,
+                    found: false,
+                };
+                expr.visit_children_with(&mut visitor);
+                visitor.found
+            }
+            Pattern::Either(patterns) => patterns.iter().any(|pattern| pattern.matches(expr)),
+        }
+    }
+
+    fn matches_prop_value(&self, value: PropValue<'_>) -> bool {
+        match value {
+            PropValue::Expr(expr) => self.matches(expr),
+            PropValue::Method(function) => match self {
+                Pattern::Any | Pattern::Rest | Pattern::Function => true,
+                Pattern::Has(pattern) => {
+                    let mut visitor = HasVisitor {
+                        pattern,
+                        found: false,
+                    };
+                    function.visit_with(&mut visitor);
+                    visitor.found
+                }
+                Pattern::Either(patterns) => patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_prop_value(value)),
+                _ => false,
+            },
+        }
+    }
+
+    /// Finds the spans of all expressions within a module that match this
+    /// pattern.
+    ///
+    /// Matches may be nested within each other.
+    pub fn find_in_module(&self, module: &WebpackModule<'_>) -> Vec<Span> {
+        let mut visitor = FindVisitor {
+            pattern: self,
+            spans: vec![],
+        };
+        module.func.visit_with(&mut visitor);
+        visitor.spans
+    }
+}
+
+fn matches_args(patterns: &[Pattern], args: &[ast::ExprOrSpread]) -> bool {
+    let args = args.iter().map(|arg| &*arg.expr).collect::<Vec<_>>();
+    matches_sequence(patterns, &args)
+}
+
+fn matches_sequence(patterns: &[Pattern], exprs: &[&ast::Expr]) -> bool {
+    match patterns.split_first() {
+        None => exprs.is_empty(),
+        Some((Pattern::Rest, patterns)) => {
+            (0..=exprs.len()).any(|skipped| matches_sequence(patterns, &exprs[skipped..]))
+        }
+        Some((pattern, patterns)) => exprs.split_first().is_some_and(|(expr, exprs)| {
+            pattern.matches(expr) && matches_sequence(patterns, exprs)
+        }),
+    }
+}
+
+struct HasVisitor<'p> {
+    pattern: &'p Pattern,
+    found: bool,
+}
+
+impl Visit for HasVisitor<'_> {
+    fn visit_expr(&mut self, n: &ast::Expr) {
+        if self.found {
+            return;
+        }
+
+        if self.pattern.matches(n) {
+            self.found = true;
+            return;
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+struct FindVisitor<'p> {
+    pattern: &'p Pattern,
+    spans: Vec<Span>,
+}
+
+impl Visit for FindVisitor<'_> {
+    fn visit_expr(&mut self, n: &ast::Expr) {
+        // Parenthesized expressions would otherwise match twice.
+        if !matches!(n, ast::Expr::Paren(_)) && self.pattern.matches(n) {
+            self.spans.push(n.span());
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// An error encountered while parsing a pattern.
+#[derive(Error, Debug, Clone)]
+pub enum QueryError {
+    #[error("expected {expected} at offset {offset}, found {found}")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+        offset: usize,
+    },
+
+    #[error("unknown pattern `{0}`")]
+    UnknownPattern(String),
+
+    #[error("invalid regex: {0}")]
+    Regex(#[from] regex::Error),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Regex(String),
+    Num(f64),
+    Rest,
+    LeftParen,
+    RightParen,
+    Comma,
+    Colon,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Str(value) => write!(f, "{:?}", value),
+            Token::Regex(regex) => write!(f, "/{}/", regex),
+            Token::Num(value) => write!(f, "{}", value),
+            Token::Rest => write!(f, "`..`"),
+            Token::LeftParen => write!(f, "`(`"),
+            Token::RightParen => write!(f, "`)`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Colon => write!(f, "`:`"),
+        }
+    }
+}
+
+/// Parses a number like JavaScript would, including hexadecimal, octal, and
+/// binary literals (e.g. `0x10`) and numeric separators (e.g. `1_000`).
+fn parse_number(number: &str) -> Option<f64> {
+    let (negative, digits) = match number.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, number),
+    };
+
+    // Separators are only allowed between digits.
+    let bytes = digits.as_bytes();
+    let separated_by_digits = bytes.iter().enumerate().all(|(index, byte)| {
+        *byte != b'_'
+            || (index > 0
+                && bytes[index - 1].is_ascii_alphanumeric()
+                && bytes.get(index + 1).is_some_and(u8::is_ascii_alphanumeric))
+    });
+    if !separated_by_digits {
+        return None;
+    }
+    let digits = digits.replace('_', "");
+
+    let radix = match digits.get(..2) {
+        Some("0x" | "0X") => Some(16),
+        Some("0o" | "0O") => Some(8),
+        Some("0b" | "0B") => Some(2),
+        _ => None,
+    };
+
+    let value = match radix {
+        Some(radix) => u64::from_str_radix(&digits[2..], radix).ok()? as f64,
+        // Rust accepts some words that JavaScript doesn't, e.g. `inf`.
+        None if digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+            digits.parse::<f64>().ok()?
+        }
+        None => return None,
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = vec![];
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '.' if chars.next_if(|(_, c)| *c == '.').is_some() => Token::Rest,
+            '"' | '\'' | '/' => {
+                let mut value = String::new();
+                let mut terminated = false;
+
+                while let Some((_, next)) = chars.next() {
+                    match next {
+                        _ if next == c => {
+                            terminated = true;
+                            break;
+                        }
+                        // Keep escapes intact within regexes, except for
+                        // escaped slashes.
+                        '\\' => match chars.next() {
+                            Some((_, escaped)) if c != '/' || escaped == '/' => value.push(escaped),
+                            Some((_, escaped)) => {
+                                value.push('\\');
+                                value.push(escaped);
+                            }
+                            None => break,
+                        },
+                        _ => value.push(next),
+                    }
+                }
+
+                if !terminated {
+                    return Err(QueryError::Unexpected {
+                        expected: "closing delimiter",
+                        found: "end of pattern".to_owned(),
+                        offset: query.len(),
+                    });
+                }
+
+                if c == '/' {
+                    Token::Regex(value)
+                } else {
+                    Token::Str(value)
+                }
+            }
+            '-' | '0'..='9' => {
+                let mut number = c.to_string();
+                while let Some((_, next)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '.' || *c == '_')
+                {
+                    number.push(next);
+                }
+
+                Token::Num(parse_number(&number).ok_or_else(|| QueryError::Unexpected {
+                    expected: "number",
+                    found: format!("`{}`", number),
+                    offset,
+                })?)
+            }
+            _ if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut word = c.to_string();
+                while let Some((_, next)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+                {
+                    word.push(next);
+                }
+
+                Token::Word(word)
+            }
+            _ => {
+                return Err(QueryError::Unexpected {
+                    expected: "pattern",
+                    found: format!("`{}`", c),
+                    offset,
+                })
+            }
+        };
+
+        tokens.push((offset, token));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'q> {
+    query: &'q str,
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<Token, QueryError> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.unexpected(expected))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn unexpected(&self, expected: &'static str) -> QueryError {
+        match self.tokens.get(self.position) {
+            Some((offset, token)) => QueryError::Unexpected {
+                expected,
+                found: token.to_string(),
+                offset: *offset,
+            },
+            None => QueryError::Unexpected {
+                expected,
+                found: "end of pattern".to_owned(),
+                offset: self.query.len(),
+            },
+        }
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), QueryError> {
+        if self.peek() != Some(&token) {
+            return Err(self.unexpected(expected));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn eat(&mut self, token: Token) -> bool {
+        let eaten = self.peek() == Some(&token);
+        if eaten {
+            self.position += 1;
+        }
+        eaten
+    }
+
+    /// Parses a comma-separated list of items until a closing parenthesis,
+    /// assuming that the opening parenthesis was already consumed.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, QueryError>,
+    ) -> Result<Vec<T>, QueryError> {
+        let mut items = vec![];
+
+        while !self.eat(Token::RightParen) {
+            items.push(item(self)?);
+
+            if !self.eat(Token::Comma) {
+                self.expect(Token::RightParen, "`,` or `)`")?;
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    fn name(&mut self) -> Result<NamePattern, QueryError> {
+        match self.next("name")? {
+            Token::Word(word) if word == "_" => Ok(NamePattern::Any),
+            Token::Word(word) | Token::Str(word) => Ok(NamePattern::Exact(word)),
+            Token::Regex(regex) => Ok(NamePattern::Regex(Regex::new(&regex)?)),
+            _ => {
+                self.position -= 1;
+                Err(self.unexpected("name"))
+            }
+        }
+    }
+
+    fn element(&mut self) -> Result<Pattern, QueryError> {
+        if self.eat(Token::Rest) {
+            return Ok(Pattern::Rest);
+        }
+
+        self.pattern()
+    }
+
+    fn object_key(&mut self) -> Result<(NamePattern, Pattern), QueryError> {
+        let key = self.name()?;
+
+        if self.eat(Token::Colon) {
+            Ok((key, self.pattern()?))
+        } else {
+            Ok((key, Pattern::Any))
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, QueryError> {
+        let word = match self.next("pattern")? {
+            Token::Str(value) => return Ok(Pattern::Str(NamePattern::Exact(value))),
+            Token::Regex(regex) => {
+                return Ok(Pattern::Str(NamePattern::Regex(Regex::new(&regex)?)))
+            }
+            Token::Num(value) => return Ok(Pattern::Num(value)),
+            Token::Word(word) => word,
+            _ => {
+                self.position -= 1;
+                return Err(self.unexpected("pattern"));
+            }
+        };
+
+        let has_args = self.eat(Token::LeftParen);
+
+        let pattern = match (word.as_str(), has_args) {
+            ("_", false) => Pattern::Any,
+            ("fn", false) => Pattern::Function,
+            ("ident", false) => Pattern::Ident(NamePattern::Any),
+            ("ident", true) => {
+                let name = self.name()?;
+                self.expect(Token::RightParen, "`)`")?;
+                Pattern::Ident(name)
+            }
+            ("member", true) => {
+                let object = self.pattern()?;
+                self.expect(Token::Comma, "`,`")?;
+                let property = self.name()?;
+                self.expect(Token::RightParen, "`)`")?;
+                Pattern::member(object, property)
+            }
+            ("call" | "new", true) => {
+                let callee = Box::new(self.pattern()?);
+                let args = if self.eat(Token::Comma) {
+                    self.list(Self::element)?
+                } else {
+                    self.expect(Token::RightParen, "`,` or `)`")?;
+                    vec![]
+                };
+
+                if word == "call" {
+                    Pattern::Call { callee, args }
+                } else {
+                    Pattern::New { callee, args }
+                }
+            }
+            ("array", true) => Pattern::Array(self.list(Self::element)?),
+            ("object", true) => Pattern::Object(self.list(Self::object_key)?),
+            ("has", true) => {
+                let pattern = self.pattern()?;
+                self.expect(Token::RightParen, "`)`")?;
+                Pattern::has(pattern)
+            }
+            ("any", true) => Pattern::Either(self.list(Self::pattern)?),
+            _ => return Err(QueryError::UnknownPattern(word)),
+        };
+
+        Ok(pattern)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            query,
+            tokens: tokenize(query)?,
+            position: 0,
+        };
+
+        let pattern = parser.pattern()?;
+
+        if parser.peek().is_some() {
+            return Err(parser.unexpected("end of pattern"));
+        }
+
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_script, walk_webpack_chunk};
+
+    fn pattern(query: &str) -> Pattern {
+        query
+            .parse()
+            .unwrap_or_else(|err| panic!("failed to parse `{}`: {}", query, err))
+    }
+
+    /// Returns whether a query matches a JavaScript expression.
+    fn matches(query: &str, js: &str) -> bool {
+        let script = parse_script(&format!("({});", js)).unwrap();
+        let [ast::Stmt::Expr(ast::ExprStmt { expr, .. })] = script.body.as_slice() else {
+            panic!("expected an expression statement");
+        };
+
+        pattern(query).matches(expr)
+    }
+
+    /// Returns the offset of the error encountered while parsing a query.
+    fn error_offset(query: &str) -> usize {
+        match query.parse::<Pattern>() {
+            Err(QueryError::Unexpected { offset, .. }) => offset,
+            other => panic!("expected an unexpected token error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_patterns() {
+        assert!(matches!(pattern("_"), Pattern::Any));
+        assert!(matches!(pattern("fn"), Pattern::Function));
+        assert!(matches!(pattern("ident"), Pattern::Ident(NamePattern::Any)));
+        assert!(matches!(pattern("'a'"), Pattern::Str(NamePattern::Exact(value)) if value == "a"));
+        assert!(matches!(
+            pattern("/^a$/"),
+            Pattern::Str(NamePattern::Regex(_))
+        ));
+        assert!(matches!(pattern("-1.5"), Pattern::Num(value) if value == -1.5));
+
+        let Pattern::Call { callee, args } =
+            pattern("call(member(_, d), .., object(useState), ..)")
+        else {
+            panic!("expected a call pattern");
+        };
+        assert!(matches!(
+            *callee,
+            Pattern::Member { property: NamePattern::Exact(ref name), .. } if name == "d"
+        ));
+        assert!(matches!(
+            args.as_slice(),
+            [Pattern::Rest, Pattern::Object(keys), Pattern::Rest] if keys.len() == 1
+        ));
+
+        let Pattern::Object(keys) = pattern(r#"object(a, "b c": 1, /^d/: fn)"#) else {
+            panic!("expected an object pattern");
+        };
+        assert!(matches!(
+            keys.as_slice(),
+            [
+                (NamePattern::Exact(_), Pattern::Any),
+                (NamePattern::Exact(_), Pattern::Num(_)),
+                (NamePattern::Regex(_), Pattern::Function),
+            ]
+        ));
+    }
+
+    #[test]
+    fn parses_number_literals() {
+        assert!(matches!(pattern("0x10"), Pattern::Num(value) if value == 16.0));
+        assert!(matches!(pattern("0o17"), Pattern::Num(value) if value == 15.0));
+        assert!(matches!(pattern("0b101"), Pattern::Num(value) if value == 5.0));
+        assert!(matches!(pattern("1_000"), Pattern::Num(value) if value == 1000.0));
+        assert!(matches!(pattern("-0xff"), Pattern::Num(value) if value == -255.0));
+        assert!(matches!(pattern("1e3"), Pattern::Num(value) if value == 1000.0));
+
+        for invalid in ["0xzz", "1__0", "1_", "0x", "1.2.3", "-", "1abc"] {
+            assert!(
+                matches!(
+                    invalid.parse::<Pattern>(),
+                    Err(QueryError::Unexpected {
+                        expected: "number",
+                        offset: 0,
+                        ..
+                    })
+                ),
+                "`{}` shouldn't parse",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn reports_error_offsets() {
+        assert_eq!(error_offset("call(_ _)"), 7);
+        assert_eq!(error_offset("member(_, d"), 11);
+        assert_eq!(error_offset("ident(\"a"), 8);
+        assert_eq!(error_offset("array(1, #)"), 9);
+        assert_eq!(error_offset("_ _"), 2);
+        assert_eq!(error_offset("call(_, 0x1g)"), 8);
+        assert!(matches!(
+            "nope(_)".parse::<Pattern>(),
+            Err(QueryError::UnknownPattern(word)) if word == "nope"
+        ));
+        assert!(matches!(
+            "/(/".parse::<Pattern>(),
+            Err(QueryError::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn matches_literals() {
+        assert!(matches("'a'", r#""a""#));
+        assert!(!matches("'a'", r#""b""#));
+        assert!(matches("/^use/", r#""useState""#));
+        assert!(matches("16", "0x10"));
+        assert!(matches("0x10", "16"));
+        assert!(!matches("1", "2"));
+        assert!(matches("fn", "() => 1"));
+        assert!(matches("fn", "function () {}"));
+        assert!(matches("ident(a)", "((a))"));
+        assert!(!matches("ident(a)", "b"));
+    }
+
+    #[test]
+    fn matches_calls_and_members() {
+        let query = "call(member(_, d), .., object(useState), ..)";
+        assert!(matches(query, "r.d(t, { useState: () => s })"));
+        assert!(matches(query, "r.d({ useState: s }, 1, 2)"));
+        assert!(!matches(query, "r.e(t, { useState: s })"));
+        assert!(!matches(query, "r.d(t, { useEffect: s })"));
+
+        assert!(matches("call(ident, 1, ..)", "f(1)"));
+        assert!(!matches("call(ident, 1)", "f(1, 2)"));
+        assert!(matches("new(ident(Map))", "new Map"));
+        assert!(matches("array(.., 3)", "[1, 2, 3]"));
+        assert!(!matches("array(.., 3)", "[3, 2]"));
+        assert!(matches("object(a: fn)", "{ a() {} }"));
+        assert!(matches("object(a: has('x'))", "{ a() { return 'x'; } }"));
+    }
+
+    #[test]
+    fn matches_nested_patterns() {
+        assert!(matches("has(ident(x))", "f(g(x))"));
+        assert!(!matches("has(ident(x))", "x"));
+        assert!(matches("any(1, 'a')", "'a'"));
+        assert!(!matches("any(1, 'a')", "2"));
+    }
+
+    #[test]
+    fn finds_matches_in_modules() {
+        let script = parse_script(
+            r#"
+            (this.webpackChunk = this.webpackChunk || []).push([[1], {
+              5: (e, t, n) => {
+                n.d(t, { a: () => r });
+                var r = n(6).foo("x", n(7).foo("y"));
+              },
+            }]);
+            "#,
+        )
+        .unwrap();
+        let chunk = walk_webpack_chunk(&script).unwrap();
+        let module = &chunk.modules[&5];
+
+        let spans = pattern("call(member(_, foo), ..)").find_in_module(module);
+        assert_eq!(spans.len(), 2);
+        assert!(spans[0].contains(spans[1]));
+    }
+}
@@ -0,0 +1,557 @@
+//! Parsing the Webpack runtime (the chunk loader).
+//!
+//! The runtime contains functions that map chunk IDs to filenames, usually
+//! looking something like this once minified:
+//!
+//! ```js
+//! r.u = (e) => "" + ({ 123: "named" }[e] || e) + "." + { 123: "0a1b2c", 456: "3d4e5f" }[e] + ".js";
+//! r.miniCssF = (e) => "" + e + "." + { 789: "6a7b8c" }[e] + ".css";
+//! r.p = "/assets/";
+//! ```
+//!
+//! Prefetched and preloaded chunks are stored in objects mapping chunk IDs to
+//! arrays of chunk IDs, next to the assignment of `r.f.prefetch` and
+//! `r.f.preload` respectively.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::{
+    util::{member_prop_name, prop_name_str, returned_expr, str_lit},
+    ChunkId, ParseError,
+};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// The information contained within a Webpack runtime.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct ChunkLoader {
+    /// The path that chunk filenames are relative to, e.g. `/assets/`.
+    pub public_path: Option<String>,
+
+    /// Script filenames, keyed by chunk ID.
+    pub scripts: BTreeMap<ChunkId, String>,
+
+    /// Stylesheet filenames, keyed by chunk ID.
+    pub stylesheets: BTreeMap<ChunkId, String>,
+
+    /// The names of named chunks, keyed by chunk ID.
+    pub named_chunks: BTreeMap<ChunkId, String>,
+
+    /// The chunks prefetched when a chunk is loaded, keyed by chunk ID.
+    pub prefetches: BTreeMap<ChunkId, Vec<ChunkId>>,
+
+    /// The chunks preloaded when a chunk is loaded, keyed by chunk ID.
+    pub preloads: BTreeMap<ChunkId, Vec<ChunkId>>,
+}
+
+#[derive(Error, Debug)]
+pub enum ChunkLoaderError {
+    #[error("failed to parse the chunk loader: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("couldn't find a function mapping chunk IDs to {0} filenames")]
+    MissingFilenameFunction(&'static str),
+
+    #[error("the {kind} filename function doesn't look up chunk IDs in any object, so its chunks can't be enumerated")]
+    UnenumerableFilenames { kind: &'static str },
+}
+
+/// A piece of a filename expression.
+enum FilenamePiece<'a> {
+    Literal(&'a str),
+    ChunkId,
+
+    /// An object keyed by chunk ID, e.g. `{ 123: "0a1b2c" }[e]`.
+    Lookup(&'a ast::ObjectLit),
+
+    /// A lookup falling back to the chunk ID, e.g. `({ 123: "named" }[e] || e)`.
+    LookupOrChunkId(&'a ast::ObjectLit),
+}
+
+/// Returns the chunk ID that a property key represents.
+fn chunk_id_key(key: &ast::PropName) -> Option<ChunkId> {
+    match key {
+        ast::PropName::Num(ast::Number { value, .. }) => Some(*value as ChunkId),
+        _ => prop_name_str(key)?.parse().ok(),
+    }
+}
+
+/// Iterates over the string values of an object keyed by chunk ID.
+fn chunk_id_map(object: &ast::ObjectLit) -> impl Iterator<Item = (ChunkId, &str)> {
+    object.props.iter().filter_map(|prop_or_spread| {
+        let ast::PropOrSpread::Prop(prop) = prop_or_spread else {
+            return None;
+        };
+        let ast::Prop::KeyValue(ast::KeyValueProp { key, value }) = &**prop else {
+            return None;
+        };
+
+        Some((chunk_id_key(key)?, str_lit(value)?))
+    })
+}
+
+fn lookup_object<'a>(expr: &'a ast::Expr, param: &str) -> Option<&'a ast::ObjectLit> {
+    let ast::Expr::Member(member) = expr else {
+        return None;
+    };
+
+    match (&*member.obj, &member.prop) {
+        (
+            ast::Expr::Object(object),
+            ast::MemberProp::Computed(ast::ComputedPropName { expr, .. }),
+        ) if matches!(&**expr, ast::Expr::Ident(ident) if &*ident.sym == param) => Some(object),
+        _ => None,
+    }
+}
+
+/// Flattens a filename expression into its pieces, returning `None` if the
+/// expression contains anything else.
+fn filename_pieces<'a>(
+    expr: &'a ast::Expr,
+    param: &str,
+    pieces: &mut Vec<FilenamePiece<'a>>,
+) -> Option<()> {
+    match expr {
+        ast::Expr::Paren(ast::ParenExpr { expr, .. }) => filename_pieces(expr, param, pieces)?,
+        ast::Expr::Lit(ast::Lit::Str(ast::Str { value, .. })) => {
+            pieces.push(FilenamePiece::Literal(value))
+        }
+        ast::Expr::Ident(ident) if &*ident.sym == param => pieces.push(FilenamePiece::ChunkId),
+        ast::Expr::Bin(ast::BinExpr {
+            op: ast::BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => {
+            filename_pieces(left, param, pieces)?;
+            filename_pieces(right, param, pieces)?;
+        }
+        ast::Expr::Bin(ast::BinExpr {
+            op: ast::BinaryOp::LogicalOr,
+            left,
+            right,
+            ..
+        }) if matches!(&**right, ast::Expr::Ident(ident) if &*ident.sym == param) => {
+            pieces.push(FilenamePiece::LookupOrChunkId(lookup_object(left, param)?))
+        }
+        // The public path, which filenames are relative to anyways.
+        ast::Expr::Member(member) if member_prop_name(member) == Some("p") => {}
+        ast::Expr::Member(_) => pieces.push(FilenamePiece::Lookup(lookup_object(expr, param)?)),
+        ast::Expr::Call(call) => {
+            // "".concat(e, ".", {...}[e], ".js")
+            let ast::Callee::Expr(callee) = &call.callee else {
+                return None;
+            };
+            let ast::Expr::Member(member) = &**callee else {
+                return None;
+            };
+            if member_prop_name(member) != Some("concat") {
+                return None;
+            }
+
+            filename_pieces(&member.obj, param, pieces)?;
+            for arg in &call.args {
+                filename_pieces(&arg.expr, param, pieces)?;
+            }
+        }
+        ast::Expr::Tpl(ast::Tpl { exprs, quasis, .. }) => {
+            for (index, quasi) in quasis.iter().enumerate() {
+                pieces.push(FilenamePiece::Literal(quasi.cooked.as_deref()?));
+                if let Some(expr) = exprs.get(index) {
+                    filename_pieces(expr, param, pieces)?;
+                }
+            }
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// The filenames produced by a function that maps chunk IDs to filenames.
+struct Filenames {
+    /// The extension of the filenames, e.g. `js`.
+    extension: String,
+
+    /// Filenames keyed by chunk ID, or `None` if the function doesn't look up
+    /// chunk IDs in any object.
+    filenames: Option<BTreeMap<ChunkId, String>>,
+
+    /// Chunk names, from lookups that fall back to the chunk ID.
+    named_chunks: BTreeMap<ChunkId, String>,
+}
+
+/// Evaluates a filename function for every chunk ID that it can be called
+/// with, given its pieces.
+fn evaluate_filenames(pieces: &[FilenamePiece<'_>]) -> Option<Filenames> {
+    let extension = pieces.iter().rev().find_map(|piece| match piece {
+        FilenamePiece::Literal(literal) if !literal.is_empty() => {
+            literal.rsplit_once('.').map(|(_, extension)| extension)
+        }
+        _ => None,
+    })?;
+
+    let lookups: Vec<BTreeMap<ChunkId, &str>> = pieces
+        .iter()
+        .map(|piece| match piece {
+            FilenamePiece::Lookup(object) | FilenamePiece::LookupOrChunkId(object) => {
+                chunk_id_map(object).collect()
+            }
+            _ => BTreeMap::new(),
+        })
+        .collect();
+
+    let named_chunks = pieces
+        .iter()
+        .zip(&lookups)
+        .filter(|(piece, _)| matches!(piece, FilenamePiece::LookupOrChunkId(_)))
+        .flat_map(|(_, lookup)| lookup.iter())
+        .map(|(chunk_id, name)| (*chunk_id, (*name).to_owned()))
+        .collect();
+
+    // Chunks without an entry in every lookup don't have this kind of file.
+    let required_lookups: Vec<&BTreeMap<ChunkId, &str>> = pieces
+        .iter()
+        .zip(&lookups)
+        .filter(|(piece, _)| matches!(piece, FilenamePiece::Lookup(_)))
+        .map(|(_, lookup)| lookup)
+        .collect();
+
+    let filenames = required_lookups.first().map(|first_lookup| {
+        first_lookup
+            .keys()
+            .filter(|chunk_id| {
+                required_lookups
+                    .iter()
+                    .all(|lookup| lookup.contains_key(chunk_id))
+            })
+            .map(|&chunk_id| {
+                let filename = pieces
+                    .iter()
+                    .zip(&lookups)
+                    .map(|(piece, lookup)| match piece {
+                        FilenamePiece::Literal(literal) => (*literal).to_owned(),
+                        FilenamePiece::ChunkId => chunk_id.to_string(),
+                        FilenamePiece::Lookup(_) => lookup[&chunk_id].to_owned(),
+                        FilenamePiece::LookupOrChunkId(_) => lookup
+                            .get(&chunk_id)
+                            .map_or_else(|| chunk_id.to_string(), |name| (*name).to_owned()),
+                    })
+                    .collect::<String>();
+
+                (chunk_id, filename)
+            })
+            .collect()
+    });
+
+    Some(Filenames {
+        extension: extension.to_owned(),
+        filenames,
+        named_chunks,
+    })
+}
+
+/// Returns whether an object maps chunk IDs to arrays of chunk IDs, like the
+/// prefetch and preload graphs do.
+fn chunk_graph(object: &ast::ObjectLit) -> Option<BTreeMap<ChunkId, Vec<ChunkId>>> {
+    if object.props.is_empty() {
+        return None;
+    }
+
+    object
+        .props
+        .iter()
+        .map(|prop_or_spread| {
+            let ast::PropOrSpread::Prop(prop) = prop_or_spread else {
+                return None;
+            };
+            let ast::Prop::KeyValue(ast::KeyValueProp { key, value }) = &**prop else {
+                return None;
+            };
+            let ast::Expr::Array(array) = &**value else {
+                return None;
+            };
+
+            let targets = array
+                .elems
+                .iter()
+                .map(|elem| match elem.as_ref().map(|elem| &*elem.expr) {
+                    Some(ast::Expr::Lit(ast::Lit::Num(number))) => Some(number.value as ChunkId),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            Some((chunk_id_key(key)?, targets))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct ChunkLoaderVisitor {
+    public_path: Option<String>,
+    filenames: Vec<Filenames>,
+    prefetches: Option<BTreeMap<ChunkId, Vec<ChunkId>>>,
+    preloads: Option<BTreeMap<ChunkId, Vec<ChunkId>>>,
+
+    /// The chunk graphs declared within each enclosing block.
+    graphs: Vec<Vec<BTreeMap<ChunkId, Vec<ChunkId>>>>,
+}
+
+impl ChunkLoaderVisitor {
+    fn visit_filename_function(&mut self, param: Option<&ast::Pat>, returned: Option<&ast::Expr>) {
+        if_chain::if_chain! {
+            if let Some(ast::Pat::Ident(binding)) = param;
+            if let Some(returned) = returned;
+            let mut pieces = vec![];
+            if filename_pieces(returned, &binding.id.sym, &mut pieces).is_some();
+            if let Some(filenames) = evaluate_filenames(&pieces);
+            then {
+                self.filenames.push(filenames);
+            }
+        }
+    }
+}
+
+impl Visit for ChunkLoaderVisitor {
+    fn visit_arrow_expr(&mut self, n: &ast::ArrowExpr) {
+        if let [param] = n.params.as_slice() {
+            let returned = match &n.body {
+                ast::BlockStmtOrExpr::Expr(expr) => Some(&**expr),
+                ast::BlockStmtOrExpr::BlockStmt(block) => returned_expr(block),
+            };
+            self.visit_filename_function(Some(param), returned);
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_function(&mut self, n: &ast::Function) {
+        if let [param] = n.params.as_slice() {
+            self.visit_filename_function(Some(&param.pat), n.body.as_ref().and_then(returned_expr));
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_block_stmt(&mut self, n: &ast::BlockStmt) {
+        // var e = { 123: [456, 789] };
+        let graphs = n
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                ast::Stmt::Decl(ast::Decl::Var(var)) => Some(var.decls.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|declarator| match declarator.init.as_deref() {
+                Some(ast::Expr::Object(object)) => chunk_graph(object),
+                _ => None,
+            })
+            .collect();
+
+        self.graphs.push(graphs);
+        n.visit_children_with(self);
+        self.graphs.pop();
+    }
+
+    fn visit_assign_expr(&mut self, n: &ast::AssignExpr) {
+        let target = match &n.left {
+            ast::PatOrExpr::Expr(expr) => Some(&**expr),
+            ast::PatOrExpr::Pat(pat) => match &**pat {
+                ast::Pat::Expr(expr) => Some(&**expr),
+                _ => None,
+            },
+        };
+
+        if let Some(ast::Expr::Member(member)) = target {
+            match member_prop_name(member) {
+                // r.p = "/assets/"
+                Some("p") if self.public_path.is_none() => {
+                    self.public_path = str_lit(&n.right).map(ToOwned::to_owned);
+                }
+                // r.f.prefetch = (e, t) => { ... }
+                Some(kind @ ("prefetch" | "preload")) if matches!(&*member.obj, ast::Expr::Member(f) if member_prop_name(f) == Some("f")) =>
+                {
+                    let graph = self.graphs.iter().rev().flatten().next().cloned();
+                    if kind == "prefetch" {
+                        self.prefetches = graph;
+                    } else {
+                        self.preloads = graph;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Walks the Webpack runtime.
+///
+/// A function mapping chunk IDs to script filenames must be present; the rest
+/// of the information is optional.
+pub fn walk_chunk_loader(script: &ast::Script) -> Result<ChunkLoader, ChunkLoaderError> {
+    let mut visitor = ChunkLoaderVisitor::default();
+    script.visit_with(&mut visitor);
+
+    let mut loader = ChunkLoader {
+        public_path: visitor.public_path,
+        prefetches: visitor.prefetches.unwrap_or_default(),
+        preloads: visitor.preloads.unwrap_or_default(),
+        ..Default::default()
+    };
+
+    for (kind, extension) in [("script", "js"), ("stylesheet", "css")] {
+        // There might be other functions producing filenames, such as the
+        // ones for hot updates. Prefer the one producing the most filenames.
+        let Some(filenames) = visitor
+            .filenames
+            .iter()
+            .filter(|filenames| filenames.extension == extension)
+            .max_by_key(|filenames| filenames.filenames.as_ref().map_or(0, BTreeMap::len))
+        else {
+            if kind == "script" {
+                return Err(ChunkLoaderError::MissingFilenameFunction(kind));
+            }
+            continue;
+        };
+
+        let Some(chunk_filenames) = filenames.filenames.clone() else {
+            return Err(ChunkLoaderError::UnenumerableFilenames { kind });
+        };
+
+        loader.named_chunks.extend(filenames.named_chunks.clone());
+
+        if kind == "script" {
+            loader.scripts = chunk_filenames;
+        } else {
+            loader.stylesheets = chunk_filenames;
+        }
+    }
+
+    tracing::info!(
+        "walked chunk loader with {} script(s), {} stylesheet(s), and {} named chunk(s)",
+        loader.scripts.len(),
+        loader.stylesheets.len(),
+        loader.named_chunks.len()
+    );
+
+    Ok(loader)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_script;
+
+    /// An excerpt of a minified Webpack 5 runtime.
+    const RUNTIME: &str = r#"
+(() => {
+  "use strict";
+  var e, t, r = {}, n = {};
+  function o(e) {
+    var t = n[e];
+    if (void 0 !== t) return t.exports;
+    var a = n[e] = { id: e, loaded: !1, exports: {} };
+    return r[e].call(a.exports, a, a.exports, o), a.loaded = !0, a.exports;
+  }
+  o.m = r,
+  o.u = (e) => "" + ({ 4567: "lazy-settings" }[e] || e) + "." + { 123: "0a1b2c3d4e5f6a7b8c9d", 4567: "f9e8d7c6b5a4f3e2d1c0" }[e] + ".js",
+  o.miniCssF = (e) => "" + e + "." + { 890: "aabbccddeeff00112233" }[e] + ".css",
+  o.hu = (e) => "" + e + "." + o.h() + ".hot-update.js",
+  o.p = "/assets/",
+  (() => {
+    var e = { 123: [4567], 4567: [890] };
+    o.f.prefetch = (t, r) => {
+      o.O(0, [t], () => {
+        e[t] && e[t].forEach((e) => o.E(e));
+      });
+    };
+  })(),
+  (() => {
+    var e = { 4567: [890] };
+    o.f.preload = (t) => {
+      var r = e[t];
+      Array.isArray(r) && r.map(o.G);
+    };
+  })();
+})();
+"#;
+
+    fn walk(js: &str) -> Result<ChunkLoader, ChunkLoaderError> {
+        walk_chunk_loader(&parse_script(js).unwrap())
+    }
+
+    #[test]
+    fn walks_runtime() {
+        let loader = walk(RUNTIME).unwrap();
+
+        assert_eq!(loader.public_path.as_deref(), Some("/assets/"));
+        assert_eq!(
+            loader.scripts,
+            BTreeMap::from([
+                (123, "123.0a1b2c3d4e5f6a7b8c9d.js".to_owned()),
+                (4567, "lazy-settings.f9e8d7c6b5a4f3e2d1c0.js".to_owned()),
+            ])
+        );
+        assert_eq!(
+            loader.stylesheets,
+            BTreeMap::from([(890, "890.aabbccddeeff00112233.css".to_owned())])
+        );
+        assert_eq!(
+            loader.named_chunks,
+            BTreeMap::from([(4567, "lazy-settings".to_owned())])
+        );
+        assert_eq!(
+            loader.prefetches,
+            BTreeMap::from([(123, vec![4567]), (4567, vec![890])])
+        );
+        assert_eq!(loader.preloads, BTreeMap::from([(4567, vec![890])]));
+    }
+
+    #[test]
+    fn walks_concat_and_function_filenames() {
+        let loader = walk(
+            r#"
+            r.u = function (e) {
+              return "".concat(e, ".").concat({ 1: "aaaa", 2: "bbbb" }[e], ".js");
+            };
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            loader.scripts,
+            BTreeMap::from([(1, "1.aaaa.js".to_owned()), (2, "2.bbbb.js".to_owned())])
+        );
+        assert!(loader.stylesheets.is_empty());
+    }
+
+    #[test]
+    fn walks_hash_only_filenames() {
+        let loader =
+            walk(r#"r.u = (e) => r.p + { 5: "0123456789abcdef0123" }[e] + ".js";"#).unwrap();
+
+        assert_eq!(
+            loader.scripts,
+            BTreeMap::from([(5, "0123456789abcdef0123.js".to_owned())])
+        );
+    }
+
+    #[test]
+    fn requires_script_filenames() {
+        assert!(matches!(
+            walk(r#"r.miniCssF = (e) => e + "." + { 1: "aaaa" }[e] + ".css";"#),
+            Err(ChunkLoaderError::MissingFilenameFunction("script"))
+        ));
+        assert!(matches!(
+            walk(r#"r.u = (e) => e + ".js";"#),
+            Err(ChunkLoaderError::UnenumerableFilenames { kind: "script" })
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+//! Helpers for picking apart common shapes of AST nodes.
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Returns the name of a property if it's an identifier or a string, e.g. `a`
+/// in `{ a: ... }` or `"some key"` in `{ "some key": ... }`.
+pub fn prop_name_str(name: &ast::PropName) -> Option<&str> {
+    match name {
+        ast::PropName::Ident(ast::Ident { sym: atom, .. }) => Some(atom),
+        ast::PropName::Str(ast::Str { value: atom, .. }) => Some(atom),
+        _ => None,
+    }
+}
+
+/// Returns the value of a string literal expression.
+pub fn str_lit(expr: &ast::Expr) -> Option<&str> {
+    match expr {
+        ast::Expr::Lit(ast::Lit::Str(ast::Str { value: atom, .. })) => Some(atom),
+        _ => None,
+    }
+}
+
+/// Returns the value of a (possibly negated) number literal expression.
+pub fn num_lit(expr: &ast::Expr) -> Option<f64> {
+    match expr {
+        ast::Expr::Lit(ast::Lit::Num(ast::Number { value, .. })) => Some(*value),
+        // -1
+        ast::Expr::Unary(ast::UnaryExpr {
+            op: ast::UnaryOp::Minus,
+            arg,
+            ..
+        }) => num_lit(arg).map(|value| -value),
+        _ => None,
+    }
+}
+
+/// Returns the expression that a block returns, if it consists solely of a
+/// return statement, e.g. the body of `function () { return a; }`.
+pub fn returned_expr(block: &ast::BlockStmt) -> Option<&ast::Expr> {
+    match block.stmts.as_slice() {
+        [ast::Stmt::Return(ast::ReturnStmt { arg: Some(arg), .. })] => Some(arg),
+        _ => None,
+    }
+}
+
+/// Iterates over the elements of an array literal expression, skipping holes
+/// and yielding nothing if the expression isn't an array.
+pub fn array_elems(expr: &ast::Expr) -> impl Iterator<Item = &ast::Expr> {
+    let elems = match expr {
+        ast::Expr::Array(ast::ArrayLit { elems, .. }) => elems.as_slice(),
+        _ => &[],
+    };
+
+    elems
+        .iter()
+        .filter_map(|elem| elem.as_ref().map(|elem| &*elem.expr))
+}
+
+/// Returns the name of the property being accessed by a member expression,
+/// e.g. `b` in `a.b` or `a["b"]`.
+pub fn member_prop_name(member: &ast::MemberExpr) -> Option<&str> {
+    match &member.prop {
+        ast::MemberProp::Ident(ast::Ident { sym: atom, .. }) => Some(atom),
+        ast::MemberProp::Computed(ast::ComputedPropName { expr, .. }) => str_lit(expr),
+        _ => None,
+    }
+}
+
+/// Returns the name of the property being called, e.g. `c` in `a.b.c()`.
+pub fn callee_prop_name(call: &ast::CallExpr) -> Option<&str> {
+    match &call.callee {
+        ast::Callee::Expr(expr) => match &**expr {
+            ast::Expr::Member(member) => member_prop_name(member),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Iterates over the key-value properties of an object literal that have
+/// identifier or string keys.
+///
+/// Methods (`{ a() {} }`) are yielded too, with the function as the value.
+pub fn object_props(object: &ast::ObjectLit) -> impl Iterator<Item = (&str, PropValue<'_>)> {
+    object.props.iter().filter_map(|prop_or_spread| {
+        let ast::PropOrSpread::Prop(prop) = prop_or_spread else {
+            return None;
+        };
+
+        match &**prop {
+            ast::Prop::KeyValue(ast::KeyValueProp { key, value }) => {
+                Some((prop_name_str(key)?, PropValue::Expr(value)))
+            }
+            ast::Prop::Method(ast::MethodProp { key, function }) => {
+                Some((prop_name_str(key)?, PropValue::Method(function)))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Returns the value of a property in an object literal by its key.
+pub fn object_prop<'a>(object: &'a ast::ObjectLit, key: &str) -> Option<&'a ast::Expr> {
+    object_props(object).find_map(|(name, value)| match value {
+        PropValue::Expr(expr) if name == key => Some(expr),
+        _ => None,
+    })
+}
+
+/// The value of an object literal property.
+#[derive(Debug, Clone, Copy)]
+pub enum PropValue<'a> {
+    Expr(&'a ast::Expr),
+    Method(&'a ast::Function),
+}
+
+impl PropValue<'_> {
+    /// Returns whether the value is a function, arrow function, or method.
+    pub fn is_function_like(&self) -> bool {
+        match self {
+            PropValue::Method(_) => true,
+            PropValue::Expr(expr) => matches!(expr, ast::Expr::Fn(_) | ast::Expr::Arrow(_)),
+        }
+    }
+}
+
+/// A `displayName` assigned to something.
+#[derive(Debug, Clone)]
+pub struct DisplayName {
+    /// The identifier that the display name was assigned to, if known.
+    pub target: Option<String>,
+    pub name: String,
+}
+
+/// Collects the string literals assigned as `displayName`s within a node.
+///
+/// The following shapes are recognized:
+///
+/// ```js
+/// Foo.displayName = "Foo";
+/// class Foo { static displayName = "Foo"; }
+/// defineProperty(Foo, "displayName", "Foo"); // emitted by swc/babel helpers
+/// ```
+#[derive(Default)]
+pub struct DisplayNameVisitor {
+    pub display_names: Vec<DisplayName>,
+    class_idents: Vec<Option<String>>,
+}
+
+impl DisplayNameVisitor {
+    fn push(&mut self, target: Option<&ast::Expr>, name: &str) {
+        let target = match target {
+            Some(ast::Expr::Ident(ident)) => Some(ident.sym.to_string()),
+            _ => None,
+        };
+
+        self.display_names.push(DisplayName {
+            target,
+            name: name.to_owned(),
+        });
+    }
+}
+
+impl Visit for DisplayNameVisitor {
+    fn visit_assign_expr(&mut self, n: &ast::AssignExpr) {
+        let target = match &n.left {
+            ast::PatOrExpr::Expr(expr) => Some(&**expr),
+            ast::PatOrExpr::Pat(pat) => match &**pat {
+                ast::Pat::Expr(expr) => Some(&**expr),
+                _ => None,
+            },
+        };
+
+        if_chain::if_chain! {
+            if let Some(ast::Expr::Member(member)) = target;
+            if member_prop_name(member) == Some("displayName");
+            if let Some(display_name) = str_lit(&n.right);
+            then {
+                self.push(Some(&member.obj), display_name);
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_class_decl(&mut self, n: &ast::ClassDecl) {
+        self.class_idents.push(Some(n.ident.sym.to_string()));
+        n.visit_children_with(self);
+        self.class_idents.pop();
+    }
+
+    fn visit_class_expr(&mut self, n: &ast::ClassExpr) {
+        self.class_idents
+            .push(n.ident.as_ref().map(|ident| ident.sym.to_string()));
+        n.visit_children_with(self);
+        self.class_idents.pop();
+    }
+
+    fn visit_class_prop(&mut self, n: &ast::ClassProp) {
+        if_chain::if_chain! {
+            if n.is_static;
+            if prop_name_str(&n.key) == Some("displayName");
+            if let Some(display_name) = n.value.as_deref().and_then(str_lit);
+            then {
+                self.display_names.push(DisplayName {
+                    target: self.class_idents.last().cloned().flatten(),
+                    name: display_name.to_owned(),
+                });
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        if_chain::if_chain! {
+            if let [target, key, value, ..] = n.args.as_slice();
+            if str_lit(&key.expr) == Some("displayName");
+            if let Some(display_name) = str_lit(&value.expr);
+            then {
+                self.push(Some(&target.expr), display_name);
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
@@ -3,7 +3,9 @@ use std::convert::{TryFrom, TryInto};
 
 use serde::Serialize;
 extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
 
+use super::util::{array_elems, member_prop_name, num_lit, object_props, returned_expr, PropValue};
 use super::ParseError;
 
 /// A webpack chunk ID.
@@ -47,6 +49,14 @@ impl FunctionLike<'_> {
             FunctionLike::Arrow(arrow_expr) => arrow_expr.span,
         }
     }
+
+    /// Visits this function-like AST node with a visitor.
+    pub fn visit_with<V: Visit>(&self, visitor: &mut V) {
+        match self {
+            FunctionLike::Function(function) => function.visit_with(visitor),
+            FunctionLike::Arrow(arrow_expr) => arrow_expr.visit_with(visitor),
+        }
+    }
 }
 
 /// A fallible conversion from an AST expression into a `FunctionLike`.
@@ -72,9 +82,122 @@ pub struct WebpackModule<'a> {
     pub func: FunctionLike<'a>,
 }
 
+impl WebpackModule<'_> {
+    /// Returns the name of the module's `require` parameter (usually `n`), if
+    /// the module function accepts one.
+    pub fn require_ident(&self) -> Option<&str> {
+        let param = match &self.func {
+            FunctionLike::Function(function) => &function.params.get(2)?.pat,
+            FunctionLike::Arrow(arrow_expr) => arrow_expr.params.get(2)?,
+        };
+
+        match param {
+            ast::Pat::Ident(binding) => Some(&binding.id.sym),
+            _ => None,
+        }
+    }
+}
+
+/// The modules that a module imports and the names that it exports.
+#[derive(Debug, Default)]
+pub struct ModuleInterface {
+    /// Imported modules, keyed by the local variable that they're bound to,
+    /// e.g. `o` in `var o = n(123)`.
+    pub imports: HashMap<String, ModuleId>,
+
+    /// Exported local variables, keyed by their export name, e.g. `Z` in
+    /// `n.d(t, { Z: () => o })`.
+    pub exports: HashMap<String, String>,
+}
+
+/// Returns the local variable returned by an export getter, e.g. `o` in
+/// `() => o` or `function () { return o; }`.
+fn export_getter_ident(getter: PropValue<'_>) -> Option<String> {
+    let returned = match getter {
+        PropValue::Expr(ast::Expr::Arrow(ast::ArrowExpr {
+            body: ast::BlockStmtOrExpr::Expr(expr),
+            ..
+        })) => &**expr,
+        PropValue::Expr(ast::Expr::Fn(ast::FnExpr { function, .. })) => {
+            returned_expr(function.body.as_ref()?)?
+        }
+        PropValue::Method(function) => returned_expr(function.body.as_ref()?)?,
+        _ => return None,
+    };
+
+    match returned {
+        ast::Expr::Ident(ident) => Some(ident.sym.to_string()),
+        _ => None,
+    }
+}
+
+struct ModuleInterfaceVisitor<'r> {
+    require: &'r str,
+    interface: ModuleInterface,
+}
+
+impl Visit for ModuleInterfaceVisitor<'_> {
+    fn visit_var_declarator(&mut self, n: &ast::VarDeclarator) {
+        // var o = n(123);
+        if_chain::if_chain! {
+            if let ast::Pat::Ident(binding) = &n.name;
+            if let Some(ast::Expr::Call(call)) = n.init.as_deref();
+            if let ast::Callee::Expr(callee) = &call.callee;
+            if matches!(&**callee, ast::Expr::Ident(ident) if &*ident.sym == self.require);
+            if let [arg] = call.args.as_slice();
+            if let Some(module_id) = num_lit(&arg.expr);
+            then {
+                self.interface
+                    .imports
+                    .insert(binding.id.sym.to_string(), module_id as ModuleId);
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, n: &ast::CallExpr) {
+        // n.d(t, { Z: () => o, ... });
+        if_chain::if_chain! {
+            if let ast::Callee::Expr(callee) = &n.callee;
+            if let ast::Expr::Member(member) = &**callee;
+            if matches!(&*member.obj, ast::Expr::Ident(ident) if &*ident.sym == self.require);
+            if member_prop_name(member) == Some("d");
+            if let [_, exports] = n.args.as_slice();
+            if let ast::Expr::Object(exports) = &*exports.expr;
+            then {
+                for (name, getter) in object_props(exports) {
+                    if let Some(local) = export_getter_ident(getter) {
+                        self.interface.exports.insert(name.to_owned(), local);
+                    }
+                }
+            }
+        }
+
+        n.visit_children_with(self);
+    }
+}
+
+/// Finds the modules that a module imports and the names that it exports.
+///
+/// Only imports bound directly to a variable and exports that resolve to a
+/// local variable are found.
+pub fn walk_module_interface(module: &WebpackModule<'_>) -> ModuleInterface {
+    let Some(require) = module.require_ident() else {
+        return ModuleInterface::default();
+    };
+
+    let mut visitor = ModuleInterfaceVisitor {
+        require,
+        interface: ModuleInterface::default(),
+    };
+    module.func.visit_with(&mut visitor);
+    visitor.interface
+}
+
 /// Walks a generic Webpack chunk that contains modules.
 #[tracing::instrument(skip_all)]
-pub fn walk_webpack_chunk(script: &ast::Script) -> Result<WebpackChunk, ParseError> {
+pub fn walk_webpack_chunk(script: &ast::Script) -> Result<WebpackChunk<'_>, ParseError> {
     use ParseError::MissingNode;
 
     // NOTE: This is the format for `webpackJsonp`/`webpackChunk`:
@@ -92,7 +215,7 @@ pub fn walk_webpack_chunk(script: &ast::Script) -> Result<WebpackChunk, ParseErr
     //
     // ]);
 
-    let body = script.body.get(0).ok_or(MissingNode("script body"))?;
+    let body = script.body.first().ok_or(MissingNode("script body"))?;
 
     let mut webpack_chunk = WebpackChunk {
         chunks: vec![],
@@ -113,12 +236,17 @@ pub fn walk_webpack_chunk(script: &ast::Script) -> Result<WebpackChunk, ParseErr
         // the elements of the array
         if let [chunk_ids_eos, modules_eos, ..] = array_lit.elems.as_slice();
         if let (
-            Some(ast::ExprOrSpread { expr: _boxed_chunk_ids_expr, .. }),
+            Some(ast::ExprOrSpread { expr: boxed_chunk_ids_expr, .. }),
             Some(ast::ExprOrSpread { expr: boxed_modules_expr, .. })
         ) = (chunk_ids_eos, modules_eos);
         let modules_expr = boxed_modules_expr;
 
         then {
+            webpack_chunk.chunks = array_elems(boxed_chunk_ids_expr)
+                .filter_map(num_lit)
+                .map(|chunk_id| chunk_id as ChunkId)
+                .collect();
+
             for (module_id, func) in walk_module_listing(modules_expr) {
                 let span = func.span();
                 let module = WebpackModule { id: module_id, func };
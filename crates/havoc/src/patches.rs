@@ -0,0 +1,210 @@
+//! Checking client mod patch definitions against a build.
+//!
+//! Client mods locate the module to patch by a string that should be unique
+//! to it, then apply regex replacements within that module. When a build
+//! changes, either step can silently stop working.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::dump::LocatedModule;
+use crate::parse::{ChunkId, ModuleId};
+
+/// A patch definition, deserialized from JSON such as:
+///
+/// ```json
+/// { "name": "typing indicator", "find": "TYPING_START", "match": "\\.typing\\((\\w+)\\)" }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub name: Option<String>,
+
+    /// The string used to find the module to patch.
+    pub find: String,
+
+    /// The regex that should match within the module.
+    pub pattern: Regex,
+}
+
+#[derive(Deserialize)]
+struct RawPatch {
+    name: Option<String>,
+    find: String,
+    #[serde(rename = "match")]
+    pattern: String,
+}
+
+#[derive(Error, Debug)]
+pub enum PatchError {
+    #[error("failed to deserialize patch definitions: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid match regex for patch finding {find:?}: {source}")]
+    Regex { find: String, source: regex::Error },
+}
+
+/// Parses a JSON array of patch definitions.
+pub fn parse_patches(json: &str) -> Result<Vec<Patch>, PatchError> {
+    let raw_patches: Vec<RawPatch> = serde_json::from_str(json)?;
+
+    raw_patches
+        .into_iter()
+        .map(|raw| {
+            let pattern = Regex::new(&raw.pattern).map_err(|source| PatchError::Regex {
+                find: raw.find.clone(),
+                source,
+            })?;
+
+            Ok(Patch {
+                name: raw.name,
+                find: raw.find,
+                pattern,
+            })
+        })
+        .collect()
+}
+
+/// The outcome of checking a patch.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchStatus {
+    /// Exactly one module contains the find string, and the regex matches
+    /// within it.
+    Ok,
+
+    /// No module contains the find string.
+    FindNotFound,
+
+    /// More than one module contains the find string.
+    FindAmbiguous,
+
+    /// Exactly one module contains the find string, but the regex doesn't
+    /// match within it.
+    MatchNotFound,
+}
+
+/// A module that a broken patch might have meant to target.
+#[derive(Serialize, Debug, Clone)]
+pub struct NearestModule {
+    pub module_id: ModuleId,
+    pub chunk_id: Option<ChunkId>,
+
+    /// The fraction of the find string's words that the module contains,
+    /// from 0 to 1.
+    pub similarity: f64,
+}
+
+/// The result of checking a patch against a build.
+#[derive(Serialize, Debug, Clone)]
+pub struct PatchReport {
+    pub name: Option<String>,
+    pub find: String,
+    pub status: PatchStatus,
+
+    /// The modules containing the find string.
+    pub found_in: Vec<ModuleId>,
+
+    /// The modules that the regex matches within. Only populated when the
+    /// patch isn't ok, to help with finding where the code moved to.
+    pub matched_in: Vec<ModuleId>,
+
+    /// The module most resembling the find string, if no module contains it.
+    pub nearest: Option<NearestModule>,
+}
+
+impl PatchReport {
+    pub fn is_ok(&self) -> bool {
+        self.status == PatchStatus::Ok
+    }
+}
+
+/// Splits a find string into the words used to compare it against modules,
+/// ignoring punctuation (including underscores) and short words.
+fn find_words(find: &str) -> Vec<&str> {
+    let mut words: Vec<&str> = find
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 3)
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+/// Finds the module containing the largest fraction of the words of a find
+/// string, preferring smaller modules when tied.
+fn nearest_module(modules: &[LocatedModule<'_>], find: &str) -> Option<NearestModule> {
+    let words = find_words(find);
+    if words.is_empty() {
+        return None;
+    }
+
+    modules
+        .iter()
+        .map(|module| {
+            let source = module.source();
+            let contained = words.iter().filter(|word| source.contains(*word)).count();
+            (module, contained, source.len())
+        })
+        .filter(|(_, contained, _)| *contained > 0)
+        .max_by(|(_, a, a_len), (_, b, b_len)| a.cmp(b).then(b_len.cmp(a_len)))
+        .map(|(module, contained, _)| NearestModule {
+            module_id: module.id(),
+            chunk_id: module.chunk_id,
+            similarity: contained as f64 / words.len() as f64,
+        })
+}
+
+/// Checks a patch against a set of modules.
+pub fn check_patch(modules: &[LocatedModule<'_>], patch: &Patch) -> PatchReport {
+    let found: Vec<&LocatedModule<'_>> = modules
+        .iter()
+        .filter(|module| module.source().contains(&patch.find))
+        .collect();
+
+    let status = match found.as_slice() {
+        [] => PatchStatus::FindNotFound,
+        [module] if patch.pattern.is_match(module.source()) => PatchStatus::Ok,
+        [_] => PatchStatus::MatchNotFound,
+        _ => PatchStatus::FindAmbiguous,
+    };
+
+    let matched_in = if status == PatchStatus::Ok {
+        vec![]
+    } else {
+        modules
+            .iter()
+            .filter(|module| patch.pattern.is_match(module.source()))
+            .map(LocatedModule::id)
+            .collect()
+    };
+
+    PatchReport {
+        name: patch.name.clone(),
+        find: patch.find.clone(),
+        status,
+        found_in: found.iter().map(|module| module.id()).collect(),
+        matched_in,
+        nearest: if status == PatchStatus::FindNotFound {
+            nearest_module(modules, &patch.find)
+        } else {
+            None
+        },
+    }
+}
+
+/// Checks many patches against a set of modules.
+pub fn check_patches(modules: &[LocatedModule<'_>], patches: &[Patch]) -> Vec<PatchReport> {
+    let reports: Vec<PatchReport> = patches
+        .iter()
+        .map(|patch| check_patch(modules, patch))
+        .collect();
+
+    tracing::info!(
+        "checked {} patch(es), {} ok",
+        reports.len(),
+        reports.iter().filter(|report| report.is_ok()).count()
+    );
+
+    reports
+}
@@ -9,6 +9,7 @@ use thiserror::Error;
 use url::Url;
 
 use crate::discord::{self, AssetCache, AssetsExt, FeAsset, FeAssetType, RootScript};
+use crate::parse::runtime::{walk_chunk_loader, ChunkLoader, ChunkLoaderError};
 use crate::parse::ChunkId;
 
 #[derive(Error, Debug)]
@@ -27,6 +28,9 @@ pub enum ScrapeError {
 
     #[error("missing networked build information")]
     MissingNetworkBuildInformation,
+
+    #[error("unrecognized chunk loader: {0}")]
+    ChunkLoader(#[from] ChunkLoaderError),
 }
 
 #[derive(Error, Debug)]
@@ -99,46 +103,46 @@ pub async fn scrape_fe_manifest(
     })
 }
 
-/// Identifies script and stylesheet chunks present in the chunkloader.
-pub async fn extract_assets_from_chunk_loader(
-    manifest: &discord::FeManifest,
+/// Fetches and walks the chunk loader, given the surface assets of a build.
+pub async fn scrape_chunk_loader(
+    assets: &[FeAsset],
     cache: &mut AssetCache,
-) -> Result<Vec<(ChunkId, FeAsset)>, ScrapeError> {
-    let chunk_loader = manifest
-        .assets
+) -> Result<ChunkLoader, ScrapeError> {
+    let chunk_loader = assets
         .iter()
         .find_root_script(RootScript::ChunkLoader)
         .ok_or(ScrapeError::MissingBranchPageAssets("chunk loader"))?;
     let data = cache.raw_content(chunk_loader).await?;
     let text = std::str::from_utf8(data)?;
 
-    // The chunk loader is bisected into two sections that handle scripts and
-    // stylesheets accordingly.
-    //
-    // Whatever Discord is using to process their stylesheets emits a ton of
-    // garbage hashes that don't correspond to actual assets, so the second
-    // section is mostly useless data. There's only one real stylesheet that is
-    // already present as a <link> tag in the HTML.
-    //
-    // Here, split the chunk loader via an arbitrary landmark.
-    let script_section = text
-        .split_once(r#"+".js""#)
-        .map(|(script_section, _)| script_section)
-        .ok_or(ScrapeError::MissingStaticBuildInformation)?;
+    let script = crate::parse::parse_script(text).map_err(ChunkLoaderError::Parse)?;
+    Ok(walk_chunk_loader(&script)?)
+}
 
-    lazy_static::lazy_static! {
-        static ref HASH_REGEX: Regex = Regex::new(r#"(?P<chunk_id>\d+):"(?P<name>[a-f0-9]{20})""#).unwrap();
-    }
+/// Identifies the script chunks present in the chunk loader, given the surface
+/// assets of a build.
+///
+/// Chunks are named after their filename without the extension, which is
+/// usually prefixed with the chunk ID or name (e.g. `123.0a1b2c`). Runtimes
+/// that name scripts after their hash alone yield the bare hash, as before.
+pub async fn extract_assets_from_chunk_loader(
+    assets: &[FeAsset],
+    cache: &mut AssetCache,
+) -> Result<Vec<(ChunkId, FeAsset)>, ScrapeError> {
+    let chunk_loader = scrape_chunk_loader(assets, cache).await?;
+
+    let assets = chunk_loader
+        .scripts
+        .into_iter()
+        .map(|(chunk_id, filename)| {
+            let name = filename
+                .strip_suffix(".js")
+                .map_or_else(|| filename.clone(), ToOwned::to_owned);
 
-    let assets = HASH_REGEX
-        .captures_iter(script_section)
-        .map(|captures| {
             (
-                captures["chunk_id"]
-                    .parse::<ChunkId>()
-                    .expect("chunk ID can't fit into u32"),
+                chunk_id,
                 FeAsset {
-                    name: captures["name"].to_owned(),
+                    name,
                     typ: FeAssetType::Js,
                 },
             )
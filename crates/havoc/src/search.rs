@@ -0,0 +1,325 @@
+//! Searching through the modules of a build.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::dump::LocatedModule;
+use crate::parse::{ChunkId, ModuleId};
+
+extern crate swc_ecma_ast as ast;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// What kind of string was indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StringKind {
+    /// A string literal, or a segment of a template literal.
+    Literal,
+
+    /// An identifier, including property names.
+    Identifier,
+}
+
+/// Where an indexed string occurs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Occurrence {
+    pub module_id: ModuleId,
+    pub chunk_id: Option<ChunkId>,
+
+    /// The byte range of the occurrence within the module's source, including
+    /// any quotes.
+    pub range: Range<usize>,
+}
+
+/// An index of the string literals and identifiers within a set of modules.
+///
+/// Each distinct string is stored once alongside all of its occurrences, so
+/// searching only has to consider every distinct string instead of every
+/// occurrence.
+#[derive(Debug, Default, Serialize)]
+pub struct StringIndex {
+    literals: BTreeMap<String, Vec<Occurrence>>,
+    identifiers: BTreeMap<String, Vec<Occurrence>>,
+}
+
+struct IndexingVisitor<'i, 'm> {
+    index: &'i mut StringIndex,
+    module: &'m LocatedModule<'m>,
+}
+
+impl IndexingVisitor<'_, '_> {
+    fn record(&mut self, kind: StringKind, value: &str, span: swc_common::Span) {
+        let occurrence = Occurrence {
+            module_id: self.module.id(),
+            chunk_id: self.module.chunk_id,
+            range: self.module.source_range(span),
+        };
+
+        self.index
+            .strings_mut(kind)
+            .entry(value.to_owned())
+            .or_default()
+            .push(occurrence);
+    }
+}
+
+impl Visit for IndexingVisitor<'_, '_> {
+    fn visit_str(&mut self, n: &ast::Str) {
+        self.record(StringKind::Literal, &n.value, n.span);
+    }
+
+    fn visit_tpl_element(&mut self, n: &ast::TplElement) {
+        if let Some(cooked) = &n.cooked {
+            self.record(StringKind::Literal, cooked, n.span);
+        }
+    }
+
+    fn visit_ident(&mut self, n: &ast::Ident) {
+        self.record(StringKind::Identifier, &n.sym, n.span);
+    }
+}
+
+impl StringIndex {
+    /// Builds an index from a set of modules.
+    pub fn build(modules: &[LocatedModule<'_>]) -> Self {
+        let mut index = StringIndex::default();
+
+        for module in modules {
+            let mut visitor = IndexingVisitor {
+                index: &mut index,
+                module,
+            };
+            module.module.func.visit_with(&mut visitor);
+        }
+
+        tracing::info!(
+            "indexed {} distinct literal(s) and {} distinct identifier(s) across {} module(s)",
+            index.literals.len(),
+            index.identifiers.len(),
+            modules.len()
+        );
+
+        index
+    }
+
+    fn strings(&self, kind: StringKind) -> &BTreeMap<String, Vec<Occurrence>> {
+        match kind {
+            StringKind::Literal => &self.literals,
+            StringKind::Identifier => &self.identifiers,
+        }
+    }
+
+    fn strings_mut(&mut self, kind: StringKind) -> &mut BTreeMap<String, Vec<Occurrence>> {
+        match kind {
+            StringKind::Literal => &mut self.literals,
+            StringKind::Identifier => &mut self.identifiers,
+        }
+    }
+
+    /// Returns the occurrences of an exact string.
+    pub fn get(&self, kind: StringKind, value: &str) -> &[Occurrence] {
+        self.strings(kind).get(value).map_or(&[], Vec::as_slice)
+    }
+
+    /// Searches for strings matching a pattern, yielding each matching string
+    /// alongside its occurrences.
+    pub fn search<'a>(
+        &'a self,
+        kind: StringKind,
+        pattern: &'a Regex,
+    ) -> impl Iterator<Item = (&'a str, &'a [Occurrence])> + 'a {
+        self.strings(kind)
+            .iter()
+            .filter(move |(value, _)| pattern.is_match(value))
+            .map(|(value, occurrences)| (value.as_str(), occurrences.as_slice()))
+    }
+}
+
+struct LiteralVisitor {
+    literals: BTreeSet<String>,
+}
+
+impl LiteralVisitor {
+    fn record(&mut self, value: &str) {
+        if !value.is_empty() {
+            self.literals.insert(value.to_owned());
+        }
+    }
+}
+
+impl Visit for LiteralVisitor {
+    fn visit_str(&mut self, n: &ast::Str) {
+        self.record(&n.value);
+    }
+
+    fn visit_tpl_element(&mut self, n: &ast::TplElement) {
+        if let Some(cooked) = &n.cooked {
+            self.record(cooked);
+        }
+    }
+}
+
+/// Collects the distinct, non-empty string literals within a script,
+/// including the segments of template literals.
+pub fn string_literals(script: &ast::Script) -> BTreeSet<String> {
+    let mut visitor = LiteralVisitor {
+        literals: BTreeSet::new(),
+    };
+    script.visit_with(&mut visitor);
+    visitor.literals
+}
+
+/// Searches the raw source code of modules for a pattern.
+pub fn search_source<'m>(
+    modules: &'m [LocatedModule<'m>],
+    pattern: &'m Regex,
+) -> impl Iterator<Item = Occurrence> + 'm {
+    modules.iter().flat_map(move |module| {
+        pattern.find_iter(module.source()).map(|found| Occurrence {
+            module_id: module.id(),
+            chunk_id: module.chunk_id,
+            range: found.range(),
+        })
+    })
+}
+
+/// A snippet of source code surrounding a range.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snippet<'s> {
+    pub before: &'s str,
+    pub highlighted: &'s str,
+    pub after: &'s str,
+}
+
+/// Extracts a snippet from some source code, including up to `context` bytes
+/// on either side of the range.
+pub fn snippet(source: &str, range: Range<usize>, context: usize) -> Snippet<'_> {
+    let floor = |mut index: usize| {
+        while !source.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    };
+    let ceil = |mut index: usize| {
+        while !source.is_char_boundary(index) {
+            index += 1;
+        }
+        index
+    };
+
+    let start = floor(range.start.saturating_sub(context));
+    let end = ceil((range.end + context).min(source.len()));
+
+    Snippet {
+        before: &source[start..range.start],
+        highlighted: &source[range.clone()],
+        after: &source[range.end..end],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dump::ParsedScript;
+
+    #[test]
+    fn indexes_strings() {
+        let script = ParsedScript::test_chunk(
+            r#"(self.webpackChunk = self.webpackChunk || []).push([[1], {
+                10: (e, t, n) => { e.exports = ["hello", `hi ${e}`, "hello"]; },
+                11: (e, t, n) => { t.greeting = "hello"; }
+            }]);"#,
+        );
+        let modules = script.test_modules();
+        let index = StringIndex::build(&modules);
+
+        let hello = index.get(StringKind::Literal, "hello");
+        assert_eq!(
+            hello
+                .iter()
+                .map(|occurrence| occurrence.module_id)
+                .collect::<Vec<_>>(),
+            vec![10, 10, 11]
+        );
+        for occurrence in hello {
+            let module = modules
+                .iter()
+                .find(|module| module.id() == occurrence.module_id)
+                .unwrap();
+            assert_eq!(&module.source()[occurrence.range.clone()], r#""hello""#);
+        }
+
+        assert_eq!(index.get(StringKind::Literal, "hi ").len(), 1);
+        assert_eq!(index.get(StringKind::Identifier, "greeting").len(), 1);
+        assert!(index.get(StringKind::Identifier, "hello").is_empty());
+
+        let pattern = Regex::new("^h").unwrap();
+        assert_eq!(
+            index
+                .search(StringKind::Literal, &pattern)
+                .map(|(value, occurrences)| (value, occurrences.len()))
+                .collect::<Vec<_>>(),
+            vec![("hello", 3), ("hi ", 1)]
+        );
+    }
+
+    #[test]
+    fn collects_string_literals() {
+        let script = crate::parse::parse_script(r#"f("a", `b${c}d`, "", "a");"#).unwrap();
+        assert_eq!(
+            string_literals(&script).into_iter().collect::<Vec<_>>(),
+            vec!["a", "b", "d"]
+        );
+    }
+
+    #[test]
+    fn extracts_snippets() {
+        let source = "let a = 1; find(me); let b = 2;";
+        let range = 11..19;
+        assert_eq!(
+            snippet(source, range.clone(), 4),
+            Snippet {
+                before: " 1; ",
+                highlighted: "find(me)",
+                after: "; le",
+            }
+        );
+        assert_eq!(
+            snippet(source, range, 100),
+            Snippet {
+                before: "let a = 1; ",
+                highlighted: "find(me)",
+                after: "; let b = 2;",
+            }
+        );
+    }
+
+    #[test]
+    fn widens_snippets_to_char_boundaries() {
+        // Each accented character is two bytes long.
+        let source = "éé abc öö";
+        let range = 5..8;
+
+        // Two bytes of context land in the middle of a character on either
+        // side, so the snippet is widened to include all of it.
+        assert_eq!(
+            snippet(source, range.clone(), 2),
+            Snippet {
+                before: "é ",
+                highlighted: "abc",
+                after: " ö",
+            }
+        );
+        assert_eq!(
+            snippet(source, range, 0),
+            Snippet {
+                before: "",
+                highlighted: "abc",
+                after: "",
+            }
+        );
+    }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    // Migrations are embedded into the binary, so it needs to be rebuilt when
+    // they change.
+    println!("cargo:rerun-if-changed=migrations");
+}
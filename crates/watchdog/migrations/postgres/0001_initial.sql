@@ -1,4 +1,6 @@
-BEGIN;
+-- The schema as it was when it was applied by hand. Like every migration, this
+-- can be applied to a database that was already set up by hand, in which case
+-- it does nothing.
 
 -- Every frontend build that has been witnessed.
 CREATE TABLE IF NOT EXISTS builds (
@@ -19,28 +21,34 @@ CREATE TABLE IF NOT EXISTS builds (
   -- into this table before it may be inserted into `build_deploys`.
 );
 
-CREATE TYPE discord_branch AS ENUM (
-  'development',
-  'canary',
-  'ptb',
-  'stable'
-);
-
-CREATE TYPE surface_script_type AS ENUM (
-  -- The script that handles kickstarting the loading of other Webpack chunks
-  -- that aren't surface level assets.
-  'chunkloader',
-
-  -- The Webpack chunk containing CSS class mappings.
-  'classes',
-
-  -- The Webpack chunk assumed to contain various vendor packages, such as
-  -- Sentry.
-  'vendor',
-
-  -- The Webpack chunk containing the bulk of the application code.
-  'entrypoint'
-);
+DO $$ BEGIN
+  CREATE TYPE discord_branch AS ENUM (
+    'development',
+    'canary',
+    'ptb',
+    'stable'
+  );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+  CREATE TYPE surface_script_type AS ENUM (
+    -- The script that handles kickstarting the loading of other Webpack chunks
+    -- that aren't surface level assets.
+    'chunkloader',
+
+    -- The Webpack chunk containing CSS class mappings.
+    'classes',
+
+    -- The Webpack chunk assumed to contain various vendor packages, such as
+    -- Sentry.
+    'vendor',
+
+    -- The Webpack chunk containing the bulk of the application code.
+    'entrypoint'
+  );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
 
 -- WItnessed frontend assets (CSS/JS/etc. files).
 CREATE TABLE IF NOT EXISTS assets (
@@ -100,13 +108,14 @@ CREATE TABLE IF NOT EXISTS build_deploys (
 
 -- A view that includes the build number alongside the build ID. Useful, since
 -- we also want the build number a lot of the time.
-CREATE VIEW detections AS
-  SELECT
-    builds.build_id,
-    builds.build_number,
-    deploys.branch,
-    deploys.detected_at
-  FROM build_deploys deploys
-  INNER JOIN builds ON deploys.build_id = builds.build_id;
-
-COMMIT;
+DO $$ BEGIN
+  CREATE VIEW detections AS
+    SELECT
+      builds.build_id,
+      builds.build_number,
+      deploys.branch,
+      deploys.detected_at
+    FROM build_deploys deploys
+    INNER JOIN builds ON deploys.build_id = builds.build_id;
+EXCEPTION WHEN duplicate_table THEN NULL;
+END $$;
@@ -0,0 +1,35 @@
+-- Tracks how the build deployed to a branch changed.
+
+DO $$ BEGIN
+  CREATE TYPE build_change_kind AS ENUM (
+    -- A build that had never been seen before on any branch.
+    'new',
+
+    -- An already seen build replacing an older build, usually because it moved
+    -- from one branch to another.
+    'promotion',
+
+    -- A build replacing a newer build.
+    'rollback'
+  );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+ALTER TABLE build_deploys
+  -- How the build deployed to the branch changed. NULL for deploys detected
+  -- before this was tracked.
+  ADD COLUMN IF NOT EXISTS change_kind build_change_kind,
+
+  -- The build that was deployed to the branch beforehand, if any.
+  ADD COLUMN IF NOT EXISTS previous_build_id TEXT REFERENCES builds(build_id);
+
+CREATE OR REPLACE VIEW detections AS
+  SELECT
+    builds.build_id,
+    builds.build_number,
+    deploys.branch,
+    deploys.detected_at,
+    deploys.change_kind,
+    deploys.previous_build_id
+  FROM build_deploys deploys
+  INNER JOIN builds ON deploys.build_id = builds.build_id;
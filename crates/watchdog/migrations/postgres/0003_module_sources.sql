@@ -0,0 +1,27 @@
+-- Indexes module IDs, and stores the sources of modules alongside them.
+
+-- For finding the assets that contain a module.
+CREATE INDEX IF NOT EXISTS module_ids_module_id_idx ON module_ids (module_id);
+
+-- The source code of Webpack modules, deduplicated by content. Most modules
+-- don't change between builds, so this is much smaller than storing the
+-- scripts themselves, and stays around after Discord purges old assets from
+-- their CDN.
+CREATE TABLE IF NOT EXISTS module_sources (
+  -- The SHA-256 hash of the source, in hexadecimal.
+  content_hash TEXT PRIMARY KEY,
+
+  source TEXT NOT NULL
+);
+
+ALTER TABLE module_ids
+  -- The hash of the module's source code. See `module_sources`.
+  ADD COLUMN IF NOT EXISTS content_hash TEXT REFERENCES module_sources(content_hash),
+
+  -- A hash of the parts of the module's code that survive minification,
+  -- which stays the same between builds unless the module meaningfully
+  -- changes.
+  ADD COLUMN IF NOT EXISTS fingerprint TEXT,
+
+  -- The names that the module exports.
+  ADD COLUMN IF NOT EXISTS exports TEXT[];
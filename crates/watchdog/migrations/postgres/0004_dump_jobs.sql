@@ -0,0 +1,44 @@
+-- Runs dumpers on catalogued builds in the background.
+
+DO $$ BEGIN
+  CREATE TYPE dump_job_status AS ENUM (
+    'pending',
+    'running',
+    'succeeded',
+    'failed'
+  );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- Dumpers run on catalogued builds, alongside their results.
+--
+-- Jobs that were running when watchdog stopped are set back to pending when it
+-- starts again.
+CREATE TABLE IF NOT EXISTS dump_jobs (
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+
+  -- The dumper and its options, as specified in the config (e.g.
+  -- `modules:beautify=true`).
+  dumper TEXT NOT NULL,
+
+  status dump_job_status NOT NULL DEFAULT 'pending',
+  attempts INTEGER NOT NULL DEFAULT 0,
+
+  -- Why the job failed, if it did.
+  error TEXT,
+
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  started_at TIMESTAMP WITH TIME ZONE,
+  finished_at TIMESTAMP WITH TIME ZONE,
+
+  -- The filename of the result (e.g. `classes.json`), once succeeded.
+  filename TEXT,
+
+  -- The content of the result, if it's stored in the database.
+  content TEXT,
+
+  -- The path to the result, if it's stored in the artifacts directory.
+  path TEXT,
+
+  PRIMARY KEY (build_id, dumper)
+);
@@ -0,0 +1,17 @@
+-- Stores the differences between builds.
+
+-- Differences between builds, computed when a build is detected on a branch
+-- (against the build that was deployed to it beforehand) or when requested.
+CREATE TABLE IF NOT EXISTS build_diffs (
+  old_build_id TEXT NOT NULL REFERENCES builds(build_id),
+  new_build_id TEXT NOT NULL REFERENCES builds(build_id),
+  computed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  -- How many things changed, by kind (e.g. `{"modules": {"added": 34, ...}}`).
+  summary JSONB NOT NULL,
+
+  -- The changes themselves, by kind.
+  changes JSONB NOT NULL,
+
+  PRIMARY KEY (old_build_id, new_build_id)
+);
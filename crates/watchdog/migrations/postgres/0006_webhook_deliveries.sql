@@ -0,0 +1,38 @@
+-- Queues webhook posts, so that they can be retried.
+
+DO $$ BEGIN
+  CREATE TYPE webhook_delivery_status AS ENUM (
+    'pending',
+    'delivered',
+    -- Gave up after too many attempts, or Discord rejected the post outright.
+    'dead'
+  );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- Posts of detected builds to the webhooks of subscriptions.
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  delivery_id BIGSERIAL PRIMARY KEY,
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+  branch discord_branch NOT NULL,
+  webhook_url TEXT NOT NULL,
+
+  -- The JSON body to post, built when the build was detected.
+  payload JSONB NOT NULL,
+
+  status webhook_delivery_status NOT NULL DEFAULT 'pending',
+
+  -- How many times the post has been attempted, not counting rate limits.
+  attempts INTEGER NOT NULL DEFAULT 0,
+
+  -- Why the last attempt failed, if it did.
+  last_error TEXT,
+
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_pending_idx
+  ON webhook_deliveries (next_attempt_at)
+  WHERE status = 'pending';
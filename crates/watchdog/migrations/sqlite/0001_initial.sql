@@ -0,0 +1,107 @@
+-- The same schema as the Postgres migrations produce. SQLite has no enums, so
+-- enum columns are checked against their possible values instead, and arrays
+-- and JSON are stored as JSON text.
+--
+-- Timestamps are stored as UTC text with millisecond precision, e.g.
+-- `2023-03-14 01:23:45.678`.
+
+-- Every frontend build that has been witnessed.
+CREATE TABLE builds (
+  build_number INTEGER PRIMARY KEY,
+  build_id TEXT UNIQUE NOT NULL
+);
+
+-- Witnessed frontend assets (CSS/JS/etc. files).
+CREATE TABLE assets (
+  name TEXT PRIMARY KEY,
+  surface BOOLEAN NOT NULL DEFAULT FALSE,
+
+  -- Surface script types are listed in the order that the scripts appear in
+  -- the HTML.
+  surface_script_type TEXT CHECK (
+    surface_script_type IN ('chunkloader', 'classes', 'vendor', 'entrypoint')
+  ),
+
+  script_chunk_id INTEGER
+);
+
+-- Witnessed frontend assets associated with a frontend build.
+CREATE TABLE build_assets (
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+  asset_name TEXT NOT NULL REFERENCES assets(name),
+
+  PRIMARY KEY (build_id, asset_name)
+);
+
+-- The source code of Webpack modules, deduplicated by content.
+CREATE TABLE module_sources (
+  -- The SHA-256 hash of the source, in hexadecimal.
+  content_hash TEXT PRIMARY KEY,
+
+  source TEXT NOT NULL
+);
+
+CREATE TABLE module_ids (
+  name TEXT NOT NULL REFERENCES assets(name),
+  module_id INTEGER NOT NULL,
+  content_hash TEXT REFERENCES module_sources(content_hash),
+  fingerprint TEXT,
+
+  -- The names that the module exports, as a JSON array.
+  exports TEXT,
+
+  UNIQUE (name, module_id)
+);
+
+-- For finding the assets that contain a module.
+CREATE INDEX module_ids_module_id_idx ON module_ids (module_id);
+
+-- Instances of a Discord build detected on a specific branch.
+CREATE TABLE build_deploys (
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+  branch TEXT NOT NULL CHECK (branch IN ('development', 'canary', 'ptb', 'stable')),
+  detected_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+  change_kind TEXT CHECK (change_kind IN ('new', 'promotion', 'rollback')),
+  previous_build_id TEXT REFERENCES builds(build_id)
+);
+
+-- Dumpers run on catalogued builds, alongside their results.
+CREATE TABLE dump_jobs (
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+  dumper TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (
+    status IN ('pending', 'running', 'succeeded', 'failed')
+  ),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  error TEXT,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+  started_at TEXT,
+  finished_at TEXT,
+  filename TEXT,
+  content TEXT,
+  path TEXT,
+
+  PRIMARY KEY (build_id, dumper)
+);
+
+-- Differences between builds, with the summary and changes as JSON.
+CREATE TABLE build_diffs (
+  old_build_id TEXT NOT NULL REFERENCES builds(build_id),
+  new_build_id TEXT NOT NULL REFERENCES builds(build_id),
+  computed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+  summary TEXT NOT NULL,
+  changes TEXT NOT NULL,
+
+  PRIMARY KEY (old_build_id, new_build_id)
+);
+
+CREATE VIEW detections AS
+  SELECT
+    builds.build_id,
+    builds.build_number,
+    deploys.branch,
+    deploys.detected_at,
+    deploys.change_kind,
+    deploys.previous_build_id
+  FROM build_deploys deploys
+  INNER JOIN builds ON deploys.build_id = builds.build_id;
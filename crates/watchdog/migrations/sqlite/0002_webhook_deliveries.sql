@@ -0,0 +1,20 @@
+-- Posts of detected builds to the webhooks of subscriptions.
+CREATE TABLE webhook_deliveries (
+  delivery_id INTEGER PRIMARY KEY AUTOINCREMENT,
+  build_id TEXT NOT NULL REFERENCES builds(build_id),
+  branch TEXT NOT NULL CHECK (branch IN ('development', 'canary', 'ptb', 'stable')),
+  webhook_url TEXT NOT NULL,
+  payload TEXT NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (
+    status IN ('pending', 'delivered', 'dead')
+  ),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+  next_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+  finished_at TEXT
+);
+
+CREATE INDEX webhook_deliveries_pending_idx
+  ON webhook_deliveries (next_attempt_at)
+  WHERE status = 'pending';
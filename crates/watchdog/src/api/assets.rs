@@ -0,0 +1,28 @@
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use super::{ApiPath, AppError, AppResult, AppState};
+use crate::db::{AssetRecord, BuildRecord};
+
+#[derive(Serialize)]
+pub struct AssetDetails {
+    #[serde(flatten)]
+    asset: AssetRecord,
+
+    /// The builds that the asset is a part of, newest first.
+    builds: Vec<BuildRecord>,
+}
+
+/// `GET /assets/:name`: Fetches an asset by filename, along with the builds
+/// it's a part of.
+pub async fn get_asset(
+    State(state): State<AppState>,
+    ApiPath(name): ApiPath<String>,
+) -> AppResult<Json<AssetDetails>> {
+    let Some(asset) = state.db.asset(&name).await? else {
+        return Err(AppError::not_found(format!("unknown asset {:?}", name)));
+    };
+    let builds = state.db.builds_with_asset(&name).await?;
+
+    Ok(Json(AssetDetails { asset, builds }))
+}
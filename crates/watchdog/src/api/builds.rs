@@ -0,0 +1,116 @@
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use havoc::discord::Branch;
+use serde::{Deserialize, Serialize};
+
+use super::{ApiPath, ApiQuery, AppError, AppResult, AppState};
+use crate::db::{AssetRecord, BuildFilter, BuildRecord, BuildRef, Db, DetectionRecord};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Deserialize)]
+pub struct BuildsQuery {
+    branch: Option<Branch>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    before: Option<u32>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct BuildsPage {
+    builds: Vec<BuildRecord>,
+
+    /// The value to pass as `before` to fetch the next page, if there might
+    /// be one.
+    next_before: Option<u32>,
+}
+
+/// `GET /builds`: Lists builds, newest first.
+pub async fn list_builds(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<BuildsQuery>,
+) -> AppResult<Json<BuildsPage>> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::bad_request(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+
+    let filter = BuildFilter {
+        branch: query.branch,
+        since: query.since,
+        until: query.until,
+        before: query.before,
+        limit,
+    };
+    let builds = state.db.builds(&filter).await?;
+
+    let next_before = if builds.len() == limit as usize {
+        builds.last().map(|build| build.number)
+    } else {
+        None
+    };
+
+    Ok(Json(BuildsPage {
+        builds,
+        next_before,
+    }))
+}
+
+/// Fetches a build by hash or number, as requested in a path.
+pub(super) async fn find_build(db: &Db, requested: &str) -> AppResult<BuildRecord> {
+    let build_ref: BuildRef = requested.parse().unwrap();
+    db.build(&build_ref)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("unknown build {:?}", requested)))
+}
+
+#[derive(Serialize)]
+pub struct BuildDetails {
+    #[serde(flatten)]
+    build: BuildRecord,
+    detections: Vec<DetectionRecord>,
+    assets: Vec<AssetRecord>,
+}
+
+/// `GET /builds/:build`: Fetches a build by hash or number, along with its
+/// detections and assets.
+pub async fn get_build(
+    State(state): State<AppState>,
+    ApiPath(requested): ApiPath<String>,
+) -> AppResult<Json<BuildDetails>> {
+    let build = find_build(&state.db, &requested).await?;
+
+    let detections = state.db.detections_of_build(&build.hash).await?;
+    let assets = state.db.assets_of_build(&build.hash).await?;
+
+    Ok(Json(BuildDetails {
+        build,
+        detections,
+        assets,
+    }))
+}
+
+/// `GET /latest`: Fetches the most recent detection on every branch.
+pub async fn latest_builds(State(state): State<AppState>) -> AppResult<Json<Vec<DetectionRecord>>> {
+    Ok(Json(state.db.latest_detections().await?))
+}
+
+/// `GET /latest/:branch`: Fetches the most recent detection on a branch.
+pub async fn latest_build_on_branch(
+    State(state): State<AppState>,
+    ApiPath(branch): ApiPath<Branch>,
+) -> AppResult<Json<DetectionRecord>> {
+    state
+        .db
+        .latest_detections()
+        .await?
+        .into_iter()
+        .find(|detection| detection.branch == branch)
+        .map(Json)
+        .ok_or_else(|| AppError::not_found(format!("no builds detected on {}", branch)))
+}
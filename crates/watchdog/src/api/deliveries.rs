@@ -0,0 +1,61 @@
+use axum::{extract::State, Json};
+use serde::Deserialize;
+
+use super::{builds::find_build, ApiPath, ApiQuery, AppError, AppResult, AppState};
+use crate::db::WebhookDeliveryRecord;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Dead,
+}
+
+impl DeliveryStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Dead => "dead",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeliveriesQuery {
+    status: Option<DeliveryStatus>,
+    limit: Option<u32>,
+}
+
+/// `GET /deliveries`: Lists webhook deliveries, most recently queued first.
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<DeliveriesQuery>,
+) -> AppResult<Json<Vec<WebhookDeliveryRecord>>> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::bad_request(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        )));
+    }
+
+    let status = query.status.map(DeliveryStatus::as_str);
+    Ok(Json(state.db.webhook_deliveries(status, limit).await?))
+}
+
+/// `GET /builds/:build/deliveries`: Lists the webhook deliveries of a build
+/// and their statuses.
+pub async fn deliveries_of_build(
+    State(state): State<AppState>,
+    ApiPath(requested): ApiPath<String>,
+) -> AppResult<Json<Vec<WebhookDeliveryRecord>>> {
+    let build = find_build(&state.db, &requested).await?;
+    Ok(Json(
+        state.db.webhook_deliveries_of_build(&build.hash).await?,
+    ))
+}
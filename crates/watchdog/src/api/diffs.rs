@@ -0,0 +1,49 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+use serde::Serialize;
+
+use super::{builds::find_build, ApiPath, AppError, AppResult, AppState};
+use crate::{
+    db::{BuildDiffRecord, BuildRecord},
+    diff::diff_builds,
+};
+
+#[derive(Serialize)]
+pub struct BuildDiffDetails {
+    old: BuildRecord,
+    new: BuildRecord,
+
+    #[serde(flatten)]
+    diff: BuildDiffRecord,
+}
+
+/// `GET /builds/:build/diff/:other`: Fetches the difference from one build to
+/// another.
+///
+/// Only diffs between consecutive builds on a branch are stored, as builds are
+/// detected. Diffs between other builds are computed on request, and aren't
+/// stored.
+pub async fn get_diff(
+    State(state): State<AppState>,
+    ApiPath((old, new)): ApiPath<(String, String)>,
+) -> AppResult<Json<BuildDiffDetails>> {
+    let old = find_build(&state.db, &old).await?;
+    let new = find_build(&state.db, &new).await?;
+    if old.hash == new.hash {
+        return Err(AppError::bad_request("can't compare a build to itself"));
+    }
+
+    let diff = match state.db.build_diff(&old.hash, &new.hash).await? {
+        Some(diff) => diff,
+        None => {
+            let diff = diff_builds(&state.db, &old.hash, &new.hash).await?;
+            BuildDiffRecord {
+                computed_at: Utc::now(),
+                summary: diff.summary,
+                changes: diff.changes,
+            }
+        }
+    };
+
+    Ok(Json(BuildDiffDetails { old, new, diff }))
+}
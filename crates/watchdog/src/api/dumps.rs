@@ -0,0 +1,47 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::{builds::find_build, ApiPath, AppError, AppResult, AppState};
+use crate::db::DumpJobRecord;
+
+/// `GET /builds/:build/dumps`: Lists the dump jobs of a build and their
+/// statuses.
+pub async fn list_dumps(
+    State(state): State<AppState>,
+    ApiPath(requested): ApiPath<String>,
+) -> AppResult<Json<Vec<DumpJobRecord>>> {
+    let build = find_build(&state.db, &requested).await?;
+    Ok(Json(state.db.dump_jobs_of_build(&build.hash).await?))
+}
+
+/// `GET /builds/:build/dumps/:dumper`: Fetches the result of a succeeded dump
+/// job.
+pub async fn get_dump(
+    State(state): State<AppState>,
+    ApiPath((requested, dumper)): ApiPath<(String, String)>,
+) -> AppResult<Response> {
+    let build = find_build(&state.db, &requested).await?;
+
+    let Some((filename, stored)) = state.db.dump_job_result(&build.hash, &dumper).await? else {
+        return Err(AppError::not_found(format!(
+            "no result for dumper {:?} on build {}",
+            dumper, build.number
+        )));
+    };
+
+    // The job says that it succeeded, so failing to read its result is our
+    // fault rather than the client's.
+    let content = stored.read().await?;
+
+    let content_type = if filename.ends_with(".json") {
+        "application/json"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], content).into_response())
+}
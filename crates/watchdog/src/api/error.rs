@@ -0,0 +1,64 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+pub type AppResult<T> = Result<T, AppError>;
+
+/// An error returned from the API, responded with as a JSON body in the shape
+/// of `{"error": {"status": 404, "message": "..."}}`.
+#[derive(Debug)]
+pub struct AppError {
+    status: StatusCode,
+    message: String,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({
+                "error": {
+                    "status": self.status.as_u16(),
+                    "message": self.message,
+                }
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Internal errors are logged and responded to with an opaque 500, so that
+/// database details don't leak to API consumers.
+impl<E> From<E> for AppError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        let err = err.into();
+        tracing::error!("internal error while handling request: {:?}", err);
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "an internal error occurred",
+        )
+    }
+}
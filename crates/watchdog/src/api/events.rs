@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+};
+use serde::Deserialize;
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+
+use super::{ApiQuery, AppError, AppResult, AppState};
+use crate::events::Envelope;
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    /// An alternative to the `Last-Event-ID` header, for WebSocket clients
+    /// that can't set headers.
+    last_event_id: Option<u64>,
+}
+
+/// Combines the replayed events and the live events into one stream.
+///
+/// The stream ends if the subscriber falls too far behind, letting it
+/// reconnect and catch up through replay instead of silently missing events.
+fn envelope_stream(
+    replayed: Vec<Arc<Envelope>>,
+    receiver: broadcast::Receiver<Arc<Envelope>>,
+) -> impl Stream<Item = Arc<Envelope>> + Send + 'static {
+    let live = BroadcastStream::new(receiver).map_while(|result| match result {
+        Ok(envelope) => Some(envelope),
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            tracing::warn!(skipped, "event subscriber fell behind, disconnecting");
+            None
+        }
+    });
+
+    tokio_stream::iter(replayed).chain(live)
+}
+
+/// `GET /events`: Streams events as they happen, over a WebSocket if the
+/// request asks to upgrade to one and Server-Sent Events otherwise.
+///
+/// Events published after the `Last-Event-ID` header (or `last_event_id`
+/// query parameter) are replayed first, as far back as the history goes.
+pub async fn events(
+    State(state): State<AppState>,
+    ws: Option<WebSocketUpgrade>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<EventsQuery>,
+) -> AppResult<Response> {
+    let last_event_id = match headers.get("last-event-id") {
+        Some(header) => Some(
+            header
+                .to_str()
+                .ok()
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| AppError::bad_request("invalid Last-Event-ID header"))?,
+        ),
+        None => query.last_event_id,
+    };
+
+    let (replayed, receiver) = state.events.subscribe(last_event_id);
+    let stream = envelope_stream(replayed, receiver);
+
+    Ok(match ws {
+        Some(ws) => ws
+            .on_upgrade(move |socket| forward_to_socket(socket, stream))
+            .into_response(),
+        None => {
+            let stream = stream.map(|envelope| {
+                SseEvent::default()
+                    .id(envelope.id.to_string())
+                    .event(envelope.event.name())
+                    .json_data(&*envelope)
+            });
+
+            Sse::new(stream)
+                .keep_alive(KeepAlive::default())
+                .into_response()
+        }
+    })
+}
+
+async fn forward_to_socket(mut socket: WebSocket, stream: impl Stream<Item = Arc<Envelope>>) {
+    tokio::pin!(stream);
+
+    loop {
+        tokio::select! {
+            envelope = stream.next() => {
+                let Some(envelope) = envelope else {
+                    break;
+                };
+                let text = serde_json::to_string(&*envelope).expect("failed to serialize event");
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                // Consumers aren't expected to send anything besides pings,
+                // which are responded to automatically.
+                if matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    return;
+                }
+            }
+        }
+    }
+
+    let _ = socket.close().await;
+}
@@ -0,0 +1,49 @@
+//! Extractors that reject with [`AppError`]s instead of axum's plaintext
+//! rejections.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query},
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+
+use super::AppError;
+
+/// Like [`Query`], but rejects with a JSON error body.
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Self(value)),
+            Err(rejection) => Err(AppError::new(rejection.status(), rejection.body_text())),
+        }
+    }
+}
+
+/// Like [`Path`], but rejects with a JSON error body.
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(Self(value)),
+            Err(rejection) => Err(AppError::new(rejection.status(), rejection.body_text())),
+        }
+    }
+}
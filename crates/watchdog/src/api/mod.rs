@@ -0,0 +1,74 @@
+use axum::{extract::State, routing::get, Router};
+use tower_http::trace::{DefaultMakeSpan, TraceLayer};
+
+use crate::{db::Db, events::EventBus, scraping::ScrapeStatuses};
+
+mod assets;
+mod builds;
+mod deliveries;
+mod diffs;
+mod dumps;
+mod error;
+mod events;
+mod extract;
+mod modules;
+mod scraping;
+
+pub use error::{AppError, AppResult};
+pub use extract::{ApiPath, ApiQuery};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Db,
+    pub events: EventBus,
+    pub scrape_statuses: ScrapeStatuses,
+}
+
+async fn handler(State(state): State<AppState>) -> AppResult<String> {
+    let two = state.db.ping().await?;
+
+    Ok(format!("1 + 1 = {}", two))
+}
+
+async fn not_found() -> AppError {
+    AppError::not_found("no such route")
+}
+
+pub fn create_router() -> Router<AppState> {
+    let api_v1_routes = Router::new()
+        .route("/ping", get(|| async { "\"pong\"" }))
+        .route("/ping/database", get(handler))
+        .route("/builds", get(builds::list_builds))
+        .route("/builds/:build", get(builds::get_build))
+        .route(
+            "/builds/:build/modules/:module_id",
+            get(modules::locate_module),
+        )
+        .route(
+            "/builds/:build/modules/:module_id/source",
+            get(modules::module_source),
+        )
+        .route("/builds/:build/diff/:other", get(diffs::get_diff))
+        .route(
+            "/builds/:build/deliveries",
+            get(deliveries::deliveries_of_build),
+        )
+        .route("/builds/:build/dumps", get(dumps::list_dumps))
+        .route("/builds/:build/dumps/:dumper", get(dumps::get_dump))
+        .route("/modules/:module_id/history", get(modules::module_history))
+        .route("/deliveries", get(deliveries::list_deliveries))
+        .route("/latest", get(builds::latest_builds))
+        .route("/latest/:branch", get(builds::latest_build_on_branch))
+        .route("/scraping", get(scraping::scrape_statuses))
+        .route("/scraping/:branch", get(scraping::scrape_status))
+        .route("/assets/:name", get(assets::get_asset))
+        .route("/events", get(events::events));
+
+    let trace_layer =
+        TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default().include_headers(true));
+
+    Router::new()
+        .layer(trace_layer)
+        .nest("/api/v1", api_v1_routes)
+        .fallback(not_found)
+}
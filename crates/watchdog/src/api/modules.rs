@@ -0,0 +1,145 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use havoc::parse::ModuleId;
+use serde::Serialize;
+
+use super::{builds::find_build, ApiPath, AppError, AppResult, AppState};
+use crate::db::{ModuleRecord, ModuleVersion};
+
+#[derive(Serialize)]
+pub struct ModuleLocation {
+    module_id: ModuleId,
+
+    /// The assets of the build that contain the module. Usually one, but
+    /// Webpack is free to duplicate a module across chunks.
+    assets: Vec<ModuleRecord>,
+}
+
+/// `GET /builds/:build/modules/:module_id`: Finds the assets of a build that
+/// contain a module.
+pub async fn locate_module(
+    State(state): State<AppState>,
+    ApiPath((requested, module_id)): ApiPath<(String, ModuleId)>,
+) -> AppResult<Json<ModuleLocation>> {
+    let build = find_build(&state.db, &requested).await?;
+
+    let assets = state.db.modules_in_build(&build.hash, module_id).await?;
+    if assets.is_empty() {
+        return Err(AppError::not_found(format!(
+            "module {} isn't in build {}",
+            module_id, build.number
+        )));
+    }
+
+    Ok(Json(ModuleLocation { module_id, assets }))
+}
+
+/// `GET /builds/:build/modules/:module_id/source`: Fetches the source code of
+/// a module as of a build.
+pub async fn module_source(
+    State(state): State<AppState>,
+    ApiPath((requested, module_id)): ApiPath<(String, ModuleId)>,
+) -> AppResult<Response> {
+    let build = find_build(&state.db, &requested).await?;
+
+    let content_hash = state
+        .db
+        .modules_in_build(&build.hash, module_id)
+        .await?
+        .into_iter()
+        .find_map(|module| module.content_hash)
+        .ok_or_else(|| {
+            AppError::not_found(format!(
+                "no source for module {} in build {}",
+                module_id, build.number
+            ))
+        })?;
+
+    let source = state
+        .db
+        .module_source(&content_hash)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("unknown source {}", content_hash)))?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        source,
+    )
+        .into_response())
+}
+
+#[derive(Serialize)]
+pub struct ModuleHistory {
+    module_id: ModuleId,
+
+    /// The build in which the module last changed, if it was ever seen.
+    last_changed_in: Option<u32>,
+
+    /// The distinct versions of the module, oldest first.
+    versions: Vec<ModuleVersion>,
+}
+
+/// `GET /modules/:module_id/history`: Fetches the versions of a module across
+/// every catalogued build.
+pub async fn module_history(
+    State(state): State<AppState>,
+    ApiPath(module_id): ApiPath<ModuleId>,
+) -> AppResult<Json<ModuleHistory>> {
+    let versions = state.db.module_history(module_id).await?;
+    let content_hashes = state.db.module_content_hashes_by_build(module_id).await?;
+
+    Ok(Json(ModuleHistory {
+        module_id,
+        last_changed_in: last_changed_in(&content_hashes),
+        versions,
+    }))
+}
+
+/// Finds the last build in which a module's content hash differs from the
+/// build before it, given its content hash within each build, oldest first.
+///
+/// This can't be derived from the distinct versions of a module alone, since
+/// a module may revert to a version that it had before.
+fn last_changed_in(content_hashes: &[(u32, String)]) -> Option<u32> {
+    let changed = content_hashes
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0].1 != pair[1].1)
+        .map(|pair| pair[1].0);
+
+    changed.or_else(|| content_hashes.first().map(|(number, _)| *number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(builds: &[(u32, &str)]) -> Vec<(u32, String)> {
+        builds
+            .iter()
+            .map(|(number, hash)| (*number, (*hash).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn finds_last_change() {
+        assert_eq!(last_changed_in(&[]), None);
+        assert_eq!(last_changed_in(&history(&[(1, "a"), (2, "a")])), Some(1));
+        assert_eq!(
+            last_changed_in(&history(&[(1, "a"), (2, "b"), (3, "b")])),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn finds_last_change_after_revert() {
+        assert_eq!(
+            last_changed_in(&history(&[(1, "a"), (2, "b"), (3, "a"), (4, "a")])),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use axum::{extract::State, Json};
+use havoc::discord::Branch;
+
+use super::{ApiPath, AppError, AppResult, AppState};
+use crate::scraping::ScrapeStatus;
+
+/// `GET /scraping`: Lists the scrape status of every branch being scraped.
+pub async fn scrape_statuses(State(state): State<AppState>) -> Json<Vec<ScrapeStatus>> {
+    Json(state.scrape_statuses.all())
+}
+
+/// `GET /scraping/:branch`: Shows the scrape status of a branch.
+pub async fn scrape_status(
+    State(state): State<AppState>,
+    ApiPath(branch): ApiPath<Branch>,
+) -> AppResult<Json<ScrapeStatus>> {
+    state
+        .scrape_statuses
+        .get(branch)
+        .map(Json)
+        .ok_or_else(|| AppError::not_found(format!("{} isn't being scraped", branch)))
+}
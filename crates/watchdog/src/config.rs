@@ -1,4 +1,9 @@
-use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use havoc::discord::Branch;
+use serde::{de, Deserialize, Deserializer};
 
 use crate::subscription::Subscription;
 
@@ -8,16 +13,145 @@ pub struct PostgresConfig {
 
     #[serde(default = "default_max_connections")]
     pub max_connections: u32,
+
+    /// Whether to apply pending migrations on startup. Otherwise, watchdog
+    /// refuses to start until they're applied with `watchdog migrate`.
+    #[serde(default = "default_migrate_on_startup")]
+    pub migrate_on_startup: bool,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SqliteConfig {
+    /// The path to the database file, which is created if it doesn't exist.
+    pub path: PathBuf,
+
+    /// See [`PostgresConfig::migrate_on_startup`].
+    #[serde(default = "default_migrate_on_startup")]
+    pub migrate_on_startup: bool,
+}
+
+fn default_migrate_on_startup() -> bool {
+    true
+}
+
+/// The database to store everything in, selected by including either a
+/// `[postgres]` or a `[sqlite]` section in the config.
+#[derive(Clone, Deserialize)]
+pub enum DatabaseConfig {
+    #[serde(rename = "postgres")]
+    Postgres(PostgresConfig),
+
+    #[serde(rename = "sqlite")]
+    Sqlite(SqliteConfig),
+}
+
+impl DatabaseConfig {
+    pub fn migrate_on_startup(&self) -> bool {
+        match self {
+            DatabaseConfig::Postgres(config) => config.migrate_on_startup,
+            DatabaseConfig::Sqlite(config) => config.migrate_on_startup,
+        }
+    }
 }
 
 fn default_max_connections() -> u32 {
     10
 }
 
+fn default_parsing_threads() -> usize {
+    // Leave some breathing room for the async runtime.
+    std::thread::available_parallelism().map_or(1, |threads| (threads.get() / 2).max(1))
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct DumpsConfig {
+    /// The dumpers to run on newly catalogued builds, written like havoc's
+    /// `--dump` arguments (e.g. `classes` or `modules:beautify=true`).
+    #[serde(default)]
+    pub dumpers: Vec<String>,
+
+    /// A directory to write dump results into, within a subdirectory per
+    /// build hash. Results are stored in the database if this isn't set.
+    pub artifacts_directory: Option<PathBuf>,
+}
+
+/// Overrides for how a single branch is scraped.
+#[derive(Clone, Default, Deserialize)]
+pub struct BranchConfig {
+    pub interval_milliseconds: Option<u64>,
+    pub jitter_milliseconds: Option<u64>,
+}
+
+fn deserialize_branches<'de, D>(deserializer: D) -> Result<HashMap<Branch, BranchConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Branches are parsed by hand, as enums can't be deserialized from table
+    // keys through the flattened database config.
+    HashMap::<String, BranchConfig>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, config)| match name.parse() {
+            Ok(branch) => Ok((branch, config)),
+            Err(()) => Err(de::Error::custom(format!("unknown branch {:?}", name))),
+        })
+        .collect()
+}
+
+/// How often a branch is scraped.
+#[derive(Clone, Copy, Debug)]
+pub struct ScrapeSchedule {
+    pub interval: Duration,
+
+    /// The upper bound of a random delay added to every wait, so that
+    /// branches don't end up being scraped in lockstep.
+    pub jitter: Duration,
+}
+
 #[derive(Clone, Deserialize)]
 pub struct Config {
+    /// How long to wait between scrapes of a branch.
     pub interval_milliseconds: u64,
+
+    /// The upper bound of a random delay added to the interval.
+    #[serde(default)]
+    pub jitter_milliseconds: u64,
+
+    /// Per-branch overrides of the interval and jitter. Branches listed here
+    /// are scraped even if no subscription wants them.
+    #[serde(default, deserialize_with = "deserialize_branches")]
+    pub branches: HashMap<Branch, BranchConfig>,
+
+    /// How many threads to parse scripts with.
+    #[serde(default = "default_parsing_threads")]
+    pub parsing_threads: usize,
+
     pub subscriptions: Vec<Subscription>,
     pub http_api_server_bind_address: std::net::SocketAddr,
-    pub postgres: PostgresConfig,
+
+    #[serde(flatten)]
+    pub database: DatabaseConfig,
+
+    #[serde(default)]
+    pub dumps: DumpsConfig,
+}
+
+impl Config {
+    /// Returns the schedule to scrape a branch on, taking any overrides into
+    /// account.
+    pub fn scrape_schedule(&self, branch: Branch) -> ScrapeSchedule {
+        let overrides = self.branches.get(&branch).cloned().unwrap_or_default();
+
+        ScrapeSchedule {
+            interval: Duration::from_millis(
+                overrides
+                    .interval_milliseconds
+                    .unwrap_or(self.interval_milliseconds),
+            ),
+            jitter: Duration::from_millis(
+                overrides
+                    .jitter_milliseconds
+                    .unwrap_or(self.jitter_milliseconds),
+            ),
+        }
+    }
 }
@@ -0,0 +1,607 @@
+//! Storing what watchdog has seen.
+//!
+//! Everything is accessed through the [`Storage`] trait, which is implemented
+//! for Postgres and SQLite. [`Db`] is a cheaply cloneable handle to whichever
+//! was selected in the config.
+
+use std::{ops::Deref, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use havoc::{
+    discord::{
+        AssetCache, AssetsExt, Branch, FeAsset, FeAssetType, FeBuild, FeManifest, RootScript,
+    },
+    parse::ModuleId,
+    scrape::extract_assets_from_chunk_loader,
+};
+use serde::Serialize;
+use sqlx::migrate::Migration;
+
+use crate::{
+    config::DatabaseConfig,
+    diff::{BuildDiff, DiffSummary},
+    lineage::{BuildChange, KnownBuild},
+};
+
+mod postgres;
+mod sqlite;
+
+pub use postgres::PostgresStorage;
+pub use sqlite::SqliteStorage;
+
+/// A catalogued build.
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildRecord {
+    pub hash: String,
+    pub number: u32,
+
+    /// When the build was first detected on any branch.
+    pub first_detected_at: DateTime<Utc>,
+}
+
+/// An instance of a build being detected on a branch.
+#[derive(Serialize, Debug, Clone)]
+pub struct DetectionRecord {
+    pub hash: String,
+    pub number: u32,
+    pub branch: Branch,
+    pub detected_at: DateTime<Utc>,
+
+    /// How the build deployed to the branch changed. Absent for detections
+    /// made before this was tracked.
+    pub change_kind: Option<String>,
+
+    /// The hash of the build that was deployed to the branch beforehand.
+    pub previous_hash: Option<String>,
+}
+
+/// A catalogued asset.
+#[derive(Serialize, Debug, Clone)]
+pub struct AssetRecord {
+    pub name: String,
+    pub surface: bool,
+    pub surface_script_type: Option<String>,
+    pub script_chunk_id: Option<i32>,
+}
+
+/// A Webpack module contained within an asset.
+#[derive(Serialize, Debug, Clone)]
+pub struct ModuleRecord {
+    pub module_id: ModuleId,
+
+    #[serde(flatten)]
+    pub asset: AssetRecord,
+
+    /// The hash of the module's source code. Absent for modules indexed
+    /// before sources were stored, as are the fingerprint and exports.
+    pub content_hash: Option<String>,
+    pub fingerprint: Option<String>,
+    pub exports: Option<Vec<String>>,
+}
+
+/// A distinct version of a module's source code, and the range of builds it
+/// was present in.
+#[derive(Serialize, Debug, Clone)]
+pub struct ModuleVersion {
+    pub content_hash: String,
+    pub fingerprint: Option<String>,
+    pub first_build_number: u32,
+    pub last_build_number: u32,
+}
+
+/// The version of a module within a build.
+#[derive(Debug, Clone)]
+pub struct BuildModule {
+    pub module_id: ModuleId,
+    pub content_hash: Option<String>,
+    pub fingerprint: Option<String>,
+}
+
+/// A dumper run on a build.
+#[derive(Serialize, Debug, Clone)]
+pub struct DumpJobRecord {
+    pub dumper: String,
+    pub status: String,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub filename: Option<String>,
+}
+
+/// Where the result of a dump job is kept.
+#[derive(Debug, Clone)]
+pub enum StoredDump {
+    Database(String),
+    File(PathBuf),
+}
+
+impl StoredDump {
+    /// Reads the content of the result.
+    pub async fn read(self) -> Result<String> {
+        match self {
+            StoredDump::Database(content) => Ok(content),
+            StoredDump::File(path) => tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("failed to read {:?}", path)),
+        }
+    }
+}
+
+/// A stored difference between two builds.
+#[derive(Serialize, Debug, Clone)]
+pub struct BuildDiffRecord {
+    pub computed_at: DateTime<Utc>,
+    pub summary: DiffSummary,
+    pub changes: serde_json::Value,
+}
+
+/// A post of a detected build to the webhook of a subscription.
+///
+/// The webhook URL is left out, as it contains the webhook's token.
+#[derive(Serialize, Debug, Clone)]
+pub struct WebhookDeliveryRecord {
+    pub id: i64,
+    pub hash: String,
+    pub branch: Branch,
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A webhook delivery that's due to be attempted.
+#[derive(Debug, Clone)]
+pub struct DueWebhookDelivery {
+    pub id: i64,
+    pub webhook_url: String,
+    pub payload: serde_json::Value,
+    pub attempts: u32,
+}
+
+/// Webhook deliveries to queue about a detected build.
+#[derive(Debug, Clone, Copy)]
+pub struct NewWebhookDeliveries<'a> {
+    pub webhook_urls: &'a [String],
+    pub payload: &'a serde_json::Value,
+
+    /// When the deliveries become due. Deliveries are held back while the
+    /// build is catalogued and compared so that they can describe what
+    /// changed, but are delivered regardless if that never finishes.
+    pub due_at: DateTime<Utc>,
+}
+
+/// A reference to a build, either by hash or by number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildRef {
+    Hash(String),
+    Number(u32),
+}
+
+impl std::str::FromStr for BuildRef {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Build hashes are far too long to fit into a `u32`, even if they
+        // happen to consist entirely of digits.
+        Ok(s.parse()
+            .map_or_else(|_| BuildRef::Hash(s.to_owned()), BuildRef::Number))
+    }
+}
+
+/// Criteria for listing builds.
+#[derive(Debug, Clone, Default)]
+pub struct BuildFilter {
+    /// Only include builds detected on this branch.
+    pub branch: Option<Branch>,
+
+    /// Only include builds detected at or after this time.
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only include builds detected before this time.
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only include builds with a number lower than this, for pagination.
+    pub before: Option<u32>,
+
+    pub limit: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum DetectedAssetKind {
+    Deep,
+    Surface,
+    SurfaceScript(RootScript),
+}
+
+impl DetectedAssetKind {
+    fn is_surface(&self) -> bool {
+        use DetectedAssetKind::*;
+        matches!(self, Surface | SurfaceScript(..))
+    }
+}
+
+/// An asset of a build that's about to be catalogued.
+struct Cataloger {
+    asset: FeAsset,
+    chunk_id: Option<i32>,
+    kind: DetectedAssetKind,
+}
+
+impl Cataloger {
+    fn new(asset: &FeAsset) -> Self {
+        Self {
+            asset: asset.clone(),
+            chunk_id: None,
+            kind: DetectedAssetKind::Surface,
+        }
+    }
+
+    fn kind(mut self, kind: DetectedAssetKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    fn chunk_id(mut self, chunk_id: Option<i32>) -> Self {
+        self.chunk_id = chunk_id;
+        self
+    }
+
+    fn surface_script_type(&self) -> Option<String> {
+        match self.kind {
+            DetectedAssetKind::Deep | DetectedAssetKind::Surface => None,
+            DetectedAssetKind::SurfaceScript(kind) => Some(format!("{:?}", kind).to_lowercase()),
+        }
+    }
+
+    /// Whether the asset is a script containing Webpack modules.
+    fn has_modules(&self) -> bool {
+        match self.kind {
+            DetectedAssetKind::Deep => true,
+            // The chunk loader only contains the Webpack runtime.
+            DetectedAssetKind::SurfaceScript(kind) => kind != RootScript::ChunkLoader,
+            DetectedAssetKind::Surface => false,
+        }
+    }
+}
+
+/// Determines the assets of a build to catalog: its stylesheets, the deep
+/// scripts loaded by its chunk loader, and its surface scripts.
+async fn catalogers(build: &FeBuild, cache: &mut AssetCache) -> Result<Vec<Cataloger>> {
+    let mut catalogers: Vec<Cataloger> = build
+        .manifest
+        .assets
+        .iter()
+        .filter_by_type(FeAssetType::Css)
+        .map(Cataloger::new)
+        .collect();
+
+    let chunks = extract_assets_from_chunk_loader(&build.manifest.assets, cache).await?;
+    for (chunk_id, chunk_asset) in chunks.iter() {
+        let chunk_id = (*chunk_id)
+            .try_into()
+            .expect("chunk id couldn't fit into i32");
+
+        catalogers.push(
+            Cataloger::new(chunk_asset)
+                .kind(DetectedAssetKind::Deep)
+                .chunk_id(Some(chunk_id)),
+        );
+    }
+
+    for (script, detected_kind) in build
+        .manifest
+        .assets
+        .iter()
+        .filter_by_type(FeAssetType::Js)
+        .zip(RootScript::assumed_ordering())
+    {
+        catalogers
+            .push(Cataloger::new(script).kind(DetectedAssetKind::SurfaceScript(detected_kind)));
+    }
+
+    Ok(catalogers)
+}
+
+/// Everything watchdog stores, independent of the database it's stored in.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Checks that the database is reachable by having it compute `1 + 1`.
+    async fn ping(&self) -> Result<i32>;
+
+    /// Fetch the migrations that haven't been applied to the database yet,
+    /// without changing anything. Errors if the applied migrations don't line
+    /// up with the embedded ones.
+    async fn pending_migrations(&self) -> Result<Vec<&'static Migration>>;
+
+    /// Apply every pending migration.
+    async fn apply_migrations(&self) -> Result<()>;
+
+    /// Fetch the last known build on a branch.
+    async fn last_known_build_on_branch(&self, branch: Branch) -> Result<Option<KnownBuild>>;
+
+    /// Fetch the branches that a build has been detected on.
+    async fn branches_with_build(&self, build_hash: &str) -> Result<Vec<Branch>>;
+
+    /// Catalog the assets of a build, including the deep scripts loaded by its
+    /// chunk loader, and index the modules of newly seen scripts.
+    async fn catalog_and_extract_assets(
+        &self,
+        build: &FeBuild,
+        cache: &mut AssetCache,
+    ) -> Result<()>;
+
+    /// Check whether a build hash is present in the database.
+    async fn build_hash_is_catalogued(&self, build_hash: &str) -> Result<bool>;
+
+    /// Log an instance of a build being present on a branch, inserting the
+    /// build into the database if necessary, and queue webhook deliveries
+    /// about it.
+    async fn detected_build_change_on_branch(
+        &self,
+        build: &FeBuild,
+        branch: Branch,
+        change: &BuildChange,
+        previous: Option<&KnownBuild>,
+        deliveries: NewWebhookDeliveries<'_>,
+    ) -> Result<()>;
+
+    /// List catalogued builds with a detection matching a filter, newest
+    /// first.
+    async fn builds(&self, filter: &BuildFilter) -> Result<Vec<BuildRecord>>;
+
+    /// Fetch a catalogued build by hash or number.
+    async fn build(&self, build: &BuildRef) -> Result<Option<BuildRecord>>;
+
+    /// Fetch every detection of a build, oldest first.
+    async fn detections_of_build(&self, build_hash: &str) -> Result<Vec<DetectionRecord>>;
+
+    /// Fetch the most recent detection on every branch.
+    async fn latest_detections(&self) -> Result<Vec<DetectionRecord>>;
+
+    /// Fetch the assets associated with a build.
+    async fn assets_of_build(&self, build_hash: &str) -> Result<Vec<AssetRecord>>;
+
+    /// Fetch a catalogued asset by name.
+    async fn asset(&self, name: &str) -> Result<Option<AssetRecord>>;
+
+    /// Fetch the builds that an asset is associated with, newest first.
+    async fn builds_with_asset(&self, name: &str) -> Result<Vec<BuildRecord>>;
+
+    /// Fetch the instances of a module within the assets of a build.
+    async fn modules_in_build(
+        &self,
+        build_hash: &str,
+        module_id: ModuleId,
+    ) -> Result<Vec<ModuleRecord>>;
+
+    /// Fetch the source code of a module by its content hash.
+    async fn module_source(&self, content_hash: &str) -> Result<Option<String>>;
+
+    /// Fetch the source code of several modules by their content hashes,
+    /// alongside the content hashes. Unknown content hashes are omitted.
+    async fn module_sources(&self, content_hashes: &[String]) -> Result<Vec<(String, String)>>;
+
+    /// Fetch every module within the assets of a build, ordered by ID.
+    async fn modules_of_build(&self, build_hash: &str) -> Result<Vec<BuildModule>>;
+
+    /// Fetch the distinct versions of a module across every catalogued build,
+    /// oldest first.
+    async fn module_history(&self, module_id: ModuleId) -> Result<Vec<ModuleVersion>>;
+
+    /// Fetch the content hash of a module within every catalogued build that
+    /// contains it, alongside the build numbers, oldest build first.
+    async fn module_content_hashes_by_build(
+        &self,
+        module_id: ModuleId,
+    ) -> Result<Vec<(u32, String)>>;
+
+    /// Fetch the filenames of the surface assets of a build, with surface
+    /// scripts in the order that they appear in the HTML.
+    async fn surface_asset_names(&self, build_hash: &str) -> Result<Vec<String>>;
+
+    /// Reconstruct a build from its catalogued surface assets, so that it can
+    /// be dumped from.
+    async fn surface_build(&self, build_hash: &str) -> Result<Option<FeBuild>> {
+        let Some(build) = self.build(&BuildRef::Hash(build_hash.to_owned())).await? else {
+            return Ok(None);
+        };
+        let Some(detection) = self
+            .detections_of_build(build_hash)
+            .await?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let assets = self
+            .surface_asset_names(build_hash)
+            .await?
+            .iter()
+            .filter_map(|filename| {
+                let (name, extension) = filename.rsplit_once('.')?;
+                let typ = match extension {
+                    "js" => FeAssetType::Js,
+                    "css" => FeAssetType::Css,
+                    _ => return None,
+                };
+
+                Some(FeAsset {
+                    name: name.to_owned(),
+                    typ,
+                })
+            })
+            .collect();
+
+        Ok(Some(FeBuild {
+            manifest: FeManifest {
+                branch: detection.branch,
+                hash: build.hash,
+                assets,
+            },
+            number: build.number,
+        }))
+    }
+
+    /// Queue dumpers to run on a build, ignoring any that were already queued.
+    async fn enqueue_dump_jobs(&self, build_hash: &str, dumpers: &[String]) -> Result<()>;
+
+    /// Set dump jobs that were interrupted while running back to pending,
+    /// returning how many there were.
+    async fn requeue_interrupted_dump_jobs(&self) -> Result<u64>;
+
+    /// Fetch the hash of the build that has been waiting the longest for its
+    /// dump jobs to run, alongside the pending dumpers.
+    async fn next_pending_dump_jobs(&self) -> Result<Option<(String, Vec<String>)>>;
+
+    /// Mark a dump job as running.
+    async fn dump_job_started(&self, build_hash: &str, dumper: &str) -> Result<()>;
+
+    /// Mark a dump job as succeeded, recording where its result is kept.
+    async fn dump_job_succeeded(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+        filename: &str,
+        stored: &StoredDump,
+    ) -> Result<()>;
+
+    /// Mark a dump job as failed.
+    async fn dump_job_failed(&self, build_hash: &str, dumper: &str, error: &str) -> Result<()>;
+
+    /// Fetch the dump jobs of a build.
+    async fn dump_jobs_of_build(&self, build_hash: &str) -> Result<Vec<DumpJobRecord>>;
+
+    /// Fetch the filename and stored result of a succeeded dump job.
+    async fn dump_job_result(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+    ) -> Result<Option<(String, StoredDump)>>;
+
+    /// Store the difference between two builds, replacing any that was
+    /// stored before.
+    async fn store_build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+        diff: &BuildDiff,
+    ) -> Result<()>;
+
+    /// Fetch the stored difference between two builds.
+    async fn build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+    ) -> Result<Option<BuildDiffRecord>>;
+
+    /// Fetch the pairs of builds with a stored difference that involves a
+    /// build, as pairs of old and new build hashes.
+    async fn diffs_involving(&self, build_hash: &str) -> Result<Vec<(String, String)>>;
+
+    /// Replace the payload of the deliveries about a build detected on a
+    /// branch that haven't been attempted yet, and make them due immediately.
+    async fn release_webhook_deliveries(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+        payload: &serde_json::Value,
+    ) -> Result<()>;
+
+    /// Fetch the payload of the deliveries about a build detected on a branch
+    /// that haven't been attempted yet, if there are any.
+    async fn unattempted_webhook_payload(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+    ) -> Result<Option<serde_json::Value>>;
+
+    /// Fetch the pending webhook delivery that has been due the longest, if
+    /// any are due.
+    async fn next_due_webhook_delivery(&self) -> Result<Option<DueWebhookDelivery>>;
+
+    /// Mark a webhook delivery as delivered.
+    async fn webhook_delivery_succeeded(&self, id: i64) -> Result<()>;
+
+    /// Record a failed attempt at a webhook delivery, retrying it at
+    /// `retry_at`. Without a time to retry at, the delivery is given up on.
+    async fn webhook_delivery_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+
+    /// Hold off on the pending deliveries to a webhook until a time, without
+    /// counting it as an attempt.
+    async fn postpone_webhook_deliveries(
+        &self,
+        webhook_url: &str,
+        until: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Fetch the webhook deliveries of a build.
+    async fn webhook_deliveries_of_build(
+        &self,
+        build_hash: &str,
+    ) -> Result<Vec<WebhookDeliveryRecord>>;
+
+    /// Fetch the most recently queued webhook deliveries, optionally only
+    /// those with a status.
+    async fn webhook_deliveries(
+        &self,
+        status: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDeliveryRecord>>;
+}
+
+/// A handle to a storage backend.
+#[derive(Clone)]
+pub struct Db {
+    storage: Arc<dyn Storage>,
+}
+
+impl Db {
+    pub fn new(storage: impl Storage + 'static) -> Self {
+        Self {
+            storage: Arc::new(storage),
+        }
+    }
+
+    /// Connects to the database selected in the config.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self> {
+        Ok(match config {
+            DatabaseConfig::Postgres(config) => Db::new(PostgresStorage::connect(config).await?),
+            DatabaseConfig::Sqlite(config) => Db::new(SqliteStorage::connect(config).await?),
+        })
+    }
+
+    /// Applies every pending migration, returning how many there were.
+    pub async fn migrate(&self) -> Result<usize> {
+        let pending = self.pending_migrations().await?;
+        for migration in &pending {
+            tracing::info!(
+                "applying migration {} ({})",
+                migration.version,
+                migration.description
+            );
+        }
+
+        self.apply_migrations().await?;
+        Ok(pending.len())
+    }
+}
+
+impl Deref for Db {
+    type Target = dyn Storage;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.storage
+    }
+}
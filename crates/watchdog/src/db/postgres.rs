@@ -0,0 +1,1060 @@
+//! Storage in Postgres.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use havoc::{
+    discord::{AssetCache, Branch, FeAsset, FeBuild},
+    parse::ModuleId,
+};
+use sqlx::{
+    migrate::Migration,
+    postgres::{PgPoolOptions, PgRow},
+    PgPool, Postgres, Row,
+};
+
+use super::{
+    catalogers, AssetRecord, BuildDiffRecord, BuildFilter, BuildModule, BuildRecord, BuildRef,
+    Cataloger, DetectionRecord, DueWebhookDelivery, DumpJobRecord, ModuleRecord, ModuleVersion,
+    NewWebhookDeliveries, Storage, StoredDump, WebhookDeliveryRecord,
+};
+use crate::{
+    config::PostgresConfig,
+    diff::BuildDiff,
+    lineage::{BuildChange, KnownBuild},
+    migrations,
+    modules::{fetch_and_walk_modules, WalkedModule, PARSING_BATCH_SIZE},
+};
+
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub async fn connect(config: &PostgresConfig) -> Result<Self> {
+        tracing::info!("connecting to postgres: {}", config.url);
+
+        let pool = PgPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(&config.url)
+            .await?;
+
+        Ok(Self { pool })
+    }
+}
+
+fn build_from_row(row: PgRow) -> BuildRecord {
+    BuildRecord {
+        hash: row.get("build_id"),
+        number: row.get::<i32, _>("build_number") as u32,
+        first_detected_at: row.get("first_detected_at"),
+    }
+}
+
+fn detection_from_row(row: PgRow) -> DetectionRecord {
+    DetectionRecord {
+        hash: row.get("build_id"),
+        number: row.get::<i32, _>("build_number") as u32,
+        branch: row
+            .get::<String, _>("branch")
+            .parse()
+            .expect("unknown branch in database"),
+        detected_at: row.get("detected_at"),
+        change_kind: row.get("change_kind"),
+        previous_hash: row.get("previous_build_id"),
+    }
+}
+
+fn asset_from_row(row: PgRow) -> AssetRecord {
+    AssetRecord {
+        name: row.get("name"),
+        surface: row.get("surface"),
+        surface_script_type: row.get("surface_script_type"),
+        script_chunk_id: row.get("script_chunk_id"),
+    }
+}
+
+fn webhook_delivery_from_row(row: PgRow) -> WebhookDeliveryRecord {
+    WebhookDeliveryRecord {
+        id: row.get("delivery_id"),
+        hash: row.get("build_id"),
+        branch: row
+            .get::<String, _>("branch")
+            .parse()
+            .expect("unknown branch in database"),
+        status: row.get("status"),
+        attempts: row.get::<i32, _>("attempts") as u32,
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        next_attempt_at: row.get("next_attempt_at"),
+        finished_at: row.get("finished_at"),
+    }
+}
+
+/// Fetches and parses script assets, walking the Webpack modules they
+/// contain. Scripts that fail to parse are skipped.
+async fn walk_scripts(
+    scripts: &[FeAsset],
+    cache: &mut AssetCache,
+) -> Result<Vec<(String, Vec<WalkedModule>)>> {
+    let mut walked_scripts = Vec::with_capacity(scripts.len());
+
+    for batch in scripts.chunks(PARSING_BATCH_SIZE) {
+        let walked = fetch_and_walk_modules(batch, cache).await?;
+
+        for (script, modules) in batch.iter().zip(walked) {
+            let name = script.filename();
+            match modules {
+                Ok(modules) => walked_scripts.push((name, modules)),
+                Err(err) => {
+                    tracing::warn!(asset = name, "failed to walk script for modules: {}", err);
+                }
+            }
+        }
+    }
+
+    Ok(walked_scripts)
+}
+
+/// Records the Webpack modules of walked scripts.
+async fn index_modules(
+    transaction: &mut sqlx::Transaction<'_, Postgres>,
+    walked_scripts: &[(String, Vec<WalkedModule>)],
+) -> Result<()> {
+    for (name, modules) in walked_scripts {
+        tracing::debug!(asset = name, modules = modules.len(), "indexing modules");
+
+        sqlx::query(
+            "INSERT INTO module_sources (content_hash, source)
+            SELECT * FROM UNNEST($1::text[], $2::text[])
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(
+            modules
+                .iter()
+                .map(|module| module.content_hash.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            modules
+                .iter()
+                .map(|module| module.source.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        // Exports are passed as JSON, since Postgres can't unnest an array
+        // of arrays into rows of arrays.
+        sqlx::query(
+            "INSERT INTO module_ids (name, module_id, content_hash, fingerprint, exports)
+            SELECT $1, module_id, content_hash, fingerprint,
+              ARRAY(SELECT jsonb_array_elements_text(exports::jsonb))
+            FROM UNNEST($2::integer[], $3::text[], $4::text[], $5::text[])
+              AS modules (module_id, content_hash, fingerprint, exports)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(name)
+        .bind(
+            modules
+                .iter()
+                .map(|module| module.id as i32)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            modules
+                .iter()
+                .map(|module| module.content_hash.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            modules
+                .iter()
+                .map(|module| module.fingerprint.as_str())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            modules
+                .iter()
+                .map(|module| serde_json::to_string(&module.exports).unwrap())
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    tracing::info!("indexed modules of {} script(s)", walked_scripts.len());
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn ping(&self) -> Result<i32> {
+        Ok(sqlx::query("SELECT 1 + 1")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0))
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<&'static Migration>> {
+        let mut conn = self.pool.acquire().await?;
+
+        let has_migrations_table: bool =
+            sqlx::query("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&mut conn)
+                .await?
+                .get(0);
+
+        migrations::pending_migrations(&migrations::POSTGRES, &mut *conn, has_migrations_table)
+            .await
+    }
+
+    async fn apply_migrations(&self) -> Result<()> {
+        migrations::POSTGRES.run(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn last_known_build_on_branch(&self, branch: Branch) -> Result<Option<KnownBuild>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number
+            FROM detections
+            WHERE branch = $1::discord_branch
+            ORDER BY detected_at DESC
+            LIMIT 1",
+        )
+        .bind(branch.to_string().to_lowercase())
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: PgRow| KnownBuild {
+            hash: row.get(0),
+            number: row.get::<i32, _>(1) as u32,
+        }))
+    }
+
+    async fn branches_with_build(&self, build_hash: &str) -> Result<Vec<Branch>> {
+        let branches: Vec<String> = sqlx::query(
+            "SELECT DISTINCT branch::text
+            FROM build_deploys
+            WHERE build_id = $1",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| row.get(0))
+        .collect();
+
+        Ok(branches
+            .iter()
+            .filter_map(|branch| branch.parse().ok())
+            .collect())
+    }
+
+    async fn catalog_and_extract_assets(
+        &self,
+        build: &FeBuild,
+        cache: &mut AssetCache,
+    ) -> Result<()> {
+        let catalogers = catalogers(build, cache).await?;
+
+        // Assets are immutable, so scripts only need to be indexed for their
+        // modules when they're catalogued for the first time.
+        let names = catalogers
+            .iter()
+            .map(|c| c.asset.filename())
+            .collect::<Vec<_>>();
+        let known: HashSet<String> = sqlx::query("SELECT name FROM assets WHERE name = ANY($1)")
+            .bind(&names)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row: PgRow| row.get(0))
+            .collect();
+
+        let unindexed_scripts = catalogers
+            .iter()
+            .filter(|c| c.has_modules() && !known.contains(&c.asset.filename()))
+            .map(|c| c.asset.clone())
+            .collect::<Vec<_>>();
+        let walked_scripts = walk_scripts(&unindexed_scripts, cache).await?;
+
+        let mut transaction = self.pool.begin().await?;
+
+        for c in &catalogers {
+            insert_asset(&mut transaction, c).await?;
+            associate_asset(&mut transaction, c, build).await?;
+        }
+
+        index_modules(&mut transaction, &walked_scripts).await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn build_hash_is_catalogued(&self, build_hash: &str) -> Result<bool> {
+        Ok(
+            sqlx::query("SELECT build_id FROM builds WHERE build_id = $1")
+                .bind(build_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn detected_build_change_on_branch(
+        &self,
+        build: &FeBuild,
+        branch: Branch,
+        change: &BuildChange,
+        previous: Option<&KnownBuild>,
+        deliveries: NewWebhookDeliveries<'_>,
+    ) -> Result<()> {
+        let number: i32 = build
+            .number
+            .try_into()
+            .expect("build number doesn't fit in i32");
+        let hash = build.manifest.hash.clone();
+
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO builds (build_id, build_number)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(&hash)
+        .bind(number)
+        .execute(&mut transaction)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO build_deploys (build_id, branch, change_kind, previous_build_id)
+            VALUES ($1, $2::discord_branch, $3::build_change_kind, $4)",
+        )
+        .bind(&hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(change.kind())
+        .bind(previous.map(|previous| &previous.hash))
+        .execute(&mut transaction)
+        .await?;
+
+        // Queued along with the deploy, so that subscribers hear about it
+        // even if cataloguing the build fails.
+        sqlx::query(
+            "INSERT INTO webhook_deliveries (build_id, branch, webhook_url, payload, next_attempt_at)
+            SELECT $1, $2::discord_branch, UNNEST($3::text[]), $4::jsonb, $5",
+        )
+        .bind(&hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(deliveries.webhook_urls)
+        .bind(deliveries.payload.to_string())
+        .bind(deliveries.due_at)
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn builds(&self, filter: &BuildFilter) -> Result<Vec<BuildRecord>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number, MIN(detected_at) AS first_detected_at
+            FROM detections
+            WHERE ($4::integer IS NULL OR build_number < $4)
+              AND EXISTS (
+                SELECT 1
+                FROM build_deploys deploys
+                WHERE deploys.build_id = detections.build_id
+                  AND ($1::discord_branch IS NULL OR deploys.branch = $1::discord_branch)
+                  AND ($2::timestamptz IS NULL OR deploys.detected_at >= $2)
+                  AND ($3::timestamptz IS NULL OR deploys.detected_at < $3)
+              )
+            GROUP BY build_id, build_number
+            ORDER BY build_number DESC
+            LIMIT $5",
+        )
+        .bind(
+            filter
+                .branch
+                .map(|branch| branch.to_string().to_lowercase()),
+        )
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.before.map(|number| number as i32))
+        .bind(filter.limit as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(build_from_row)
+        .collect())
+    }
+
+    async fn build(&self, build: &BuildRef) -> Result<Option<BuildRecord>> {
+        let (hash, number) = match build {
+            BuildRef::Hash(hash) => (Some(hash.as_str()), None),
+            BuildRef::Number(number) => (None, Some(*number as i32)),
+        };
+
+        Ok(sqlx::query(
+            "SELECT builds.build_id, builds.build_number, MIN(deploys.detected_at) AS first_detected_at
+            FROM builds
+            INNER JOIN build_deploys deploys ON deploys.build_id = builds.build_id
+            WHERE builds.build_id = $1 OR builds.build_number = $2
+            GROUP BY builds.build_id, builds.build_number",
+        )
+        .bind(hash)
+        .bind(number)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(build_from_row))
+    }
+
+    async fn detections_of_build(&self, build_hash: &str) -> Result<Vec<DetectionRecord>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number, branch::text, detected_at,
+              change_kind::text, previous_build_id
+            FROM detections
+            WHERE build_id = $1
+            ORDER BY detected_at ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(detection_from_row)
+        .collect())
+    }
+
+    async fn latest_detections(&self) -> Result<Vec<DetectionRecord>> {
+        Ok(sqlx::query(
+            "SELECT DISTINCT ON (branch) build_id, build_number, branch::text,
+              detected_at, change_kind::text, previous_build_id
+            FROM detections
+            ORDER BY branch, detected_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(detection_from_row)
+        .collect())
+    }
+
+    async fn assets_of_build(&self, build_hash: &str) -> Result<Vec<AssetRecord>> {
+        Ok(sqlx::query(
+            "SELECT assets.name, assets.surface, assets.surface_script_type::text,
+              assets.script_chunk_id
+            FROM build_assets
+            INNER JOIN assets ON assets.name = build_assets.asset_name
+            WHERE build_assets.build_id = $1
+            ORDER BY assets.surface DESC, assets.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(asset_from_row)
+        .collect())
+    }
+
+    async fn asset(&self, name: &str) -> Result<Option<AssetRecord>> {
+        Ok(sqlx::query(
+            "SELECT name, surface, surface_script_type::text, script_chunk_id
+            FROM assets
+            WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(asset_from_row))
+    }
+
+    async fn builds_with_asset(&self, name: &str) -> Result<Vec<BuildRecord>> {
+        Ok(sqlx::query(
+            "SELECT builds.build_id, builds.build_number, MIN(deploys.detected_at) AS first_detected_at
+            FROM build_assets
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            INNER JOIN build_deploys deploys ON deploys.build_id = builds.build_id
+            WHERE build_assets.asset_name = $1
+            GROUP BY builds.build_id, builds.build_number
+            ORDER BY builds.build_number DESC",
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(build_from_row)
+        .collect())
+    }
+
+    async fn modules_in_build(
+        &self,
+        build_hash: &str,
+        module_id: ModuleId,
+    ) -> Result<Vec<ModuleRecord>> {
+        Ok(sqlx::query(
+            "SELECT assets.name, assets.surface, assets.surface_script_type::text,
+              assets.script_chunk_id, module_ids.module_id, module_ids.content_hash,
+              module_ids.fingerprint, module_ids.exports
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN assets ON assets.name = module_ids.name
+            WHERE build_assets.build_id = $1 AND module_ids.module_id = $2
+            ORDER BY assets.name ASC",
+        )
+        .bind(build_hash)
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| ModuleRecord {
+            module_id: row.get::<i32, _>("module_id") as ModuleId,
+            content_hash: row.get("content_hash"),
+            fingerprint: row.get("fingerprint"),
+            exports: row.get("exports"),
+            asset: asset_from_row(row),
+        })
+        .collect())
+    }
+
+    async fn module_source(&self, content_hash: &str) -> Result<Option<String>> {
+        Ok(
+            sqlx::query("SELECT source FROM module_sources WHERE content_hash = $1")
+                .bind(content_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|row: PgRow| row.get(0)),
+        )
+    }
+
+    async fn module_sources(&self, content_hashes: &[String]) -> Result<Vec<(String, String)>> {
+        Ok(sqlx::query(
+            "SELECT content_hash, source FROM module_sources WHERE content_hash = ANY($1)",
+        )
+        .bind(content_hashes)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| (row.get("content_hash"), row.get("source")))
+        .collect())
+    }
+
+    async fn modules_of_build(&self, build_hash: &str) -> Result<Vec<BuildModule>> {
+        Ok(sqlx::query(
+            "SELECT module_ids.module_id, module_ids.content_hash, module_ids.fingerprint
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            WHERE build_assets.build_id = $1
+            ORDER BY module_ids.module_id ASC, module_ids.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| BuildModule {
+            module_id: row.get::<i32, _>("module_id") as ModuleId,
+            content_hash: row.get("content_hash"),
+            fingerprint: row.get("fingerprint"),
+        })
+        .collect())
+    }
+
+    async fn module_history(&self, module_id: ModuleId) -> Result<Vec<ModuleVersion>> {
+        Ok(sqlx::query(
+            "SELECT module_ids.content_hash, module_ids.fingerprint,
+              MIN(builds.build_number) AS first_build_number,
+              MAX(builds.build_number) AS last_build_number
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            WHERE module_ids.module_id = $1 AND module_ids.content_hash IS NOT NULL
+            GROUP BY module_ids.content_hash, module_ids.fingerprint
+            ORDER BY first_build_number ASC",
+        )
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| ModuleVersion {
+            content_hash: row.get("content_hash"),
+            fingerprint: row.get("fingerprint"),
+            first_build_number: row.get::<i32, _>("first_build_number") as u32,
+            last_build_number: row.get::<i32, _>("last_build_number") as u32,
+        })
+        .collect())
+    }
+
+    async fn module_content_hashes_by_build(
+        &self,
+        module_id: ModuleId,
+    ) -> Result<Vec<(u32, String)>> {
+        Ok(sqlx::query(
+            "SELECT builds.build_number, MIN(module_ids.content_hash) AS content_hash
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            WHERE module_ids.module_id = $1 AND module_ids.content_hash IS NOT NULL
+            GROUP BY builds.build_number
+            ORDER BY builds.build_number ASC",
+        )
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| {
+            (
+                row.get::<i32, _>("build_number") as u32,
+                row.get("content_hash"),
+            )
+        })
+        .collect())
+    }
+
+    async fn surface_asset_names(&self, build_hash: &str) -> Result<Vec<String>> {
+        // Surface script types are declared in the order that the scripts
+        // appear in the HTML.
+        Ok(sqlx::query(
+            "SELECT assets.name
+            FROM build_assets
+            INNER JOIN assets ON assets.name = build_assets.asset_name
+            WHERE build_assets.build_id = $1 AND assets.surface
+            ORDER BY assets.surface_script_type ASC NULLS LAST, assets.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| row.get(0))
+        .collect())
+    }
+
+    async fn enqueue_dump_jobs(&self, build_hash: &str, dumpers: &[String]) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO dump_jobs (build_id, dumper)
+            SELECT $1, UNNEST($2::text[])
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(build_hash)
+        .bind(dumpers)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn requeue_interrupted_dump_jobs(&self) -> Result<u64> {
+        Ok(sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'pending', started_at = NULL
+            WHERE status = 'running'",
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected())
+    }
+
+    async fn next_pending_dump_jobs(&self) -> Result<Option<(String, Vec<String>)>> {
+        let Some(build_hash) = sqlx::query(
+            "SELECT build_id
+            FROM dump_jobs
+            WHERE status = 'pending'
+            ORDER BY created_at ASC
+            LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: PgRow| row.get::<String, _>(0)) else {
+            return Ok(None);
+        };
+
+        let dumpers = sqlx::query(
+            "SELECT dumper
+            FROM dump_jobs
+            WHERE build_id = $1 AND status = 'pending'
+            ORDER BY dumper ASC",
+        )
+        .bind(&build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| row.get(0))
+        .collect();
+
+        Ok(Some((build_hash, dumpers)))
+    }
+
+    async fn dump_job_started(&self, build_hash: &str, dumper: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'running', attempts = attempts + 1, started_at = CURRENT_TIMESTAMP,
+              finished_at = NULL, error = NULL
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_job_succeeded(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+        filename: &str,
+        stored: &StoredDump,
+    ) -> Result<()> {
+        let (content, path) = match stored {
+            StoredDump::Database(content) => (Some(content.as_str()), None),
+            StoredDump::File(path) => (None, Some(path.to_string_lossy())),
+        };
+
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'succeeded', finished_at = CURRENT_TIMESTAMP, filename = $3,
+              content = $4, path = $5
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .bind(filename)
+        .bind(content)
+        .bind(path)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_job_failed(&self, build_hash: &str, dumper: &str, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'failed', finished_at = CURRENT_TIMESTAMP, error = $3
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_jobs_of_build(&self, build_hash: &str) -> Result<Vec<DumpJobRecord>> {
+        Ok(sqlx::query(
+            "SELECT dumper, status::text, attempts, error, created_at, started_at,
+              finished_at, filename
+            FROM dump_jobs
+            WHERE build_id = $1
+            ORDER BY dumper ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| DumpJobRecord {
+            dumper: row.get("dumper"),
+            status: row.get("status"),
+            attempts: row.get::<i32, _>("attempts") as u32,
+            error: row.get("error"),
+            created_at: row.get("created_at"),
+            started_at: row.get("started_at"),
+            finished_at: row.get("finished_at"),
+            filename: row.get("filename"),
+        })
+        .collect())
+    }
+
+    async fn dump_job_result(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+    ) -> Result<Option<(String, StoredDump)>> {
+        let Some(row) = sqlx::query(
+            "SELECT filename, content, path
+            FROM dump_jobs
+            WHERE build_id = $1 AND dumper = $2 AND status = 'succeeded'",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let stored = match (row.get("content"), row.get::<Option<String>, _>("path")) {
+            (Some(content), _) => StoredDump::Database(content),
+            (None, Some(path)) => StoredDump::File(path.into()),
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some((row.get("filename"), stored)))
+    }
+
+    async fn store_build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+        diff: &BuildDiff,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO build_diffs (old_build_id, new_build_id, summary, changes)
+            VALUES ($1, $2, $3::jsonb, $4::jsonb)
+            ON CONFLICT (old_build_id, new_build_id) DO UPDATE
+            SET computed_at = CURRENT_TIMESTAMP, summary = excluded.summary,
+              changes = excluded.changes",
+        )
+        .bind(old_build_hash)
+        .bind(new_build_hash)
+        .bind(serde_json::to_string(&diff.summary)?)
+        .bind(diff.changes.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+    ) -> Result<Option<BuildDiffRecord>> {
+        let Some(row) = sqlx::query(
+            "SELECT computed_at, summary::text, changes::text
+            FROM build_diffs
+            WHERE old_build_id = $1 AND new_build_id = $2",
+        )
+        .bind(old_build_hash)
+        .bind(new_build_hash)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(BuildDiffRecord {
+            computed_at: row.get("computed_at"),
+            summary: serde_json::from_str(row.get("summary"))?,
+            changes: serde_json::from_str(row.get("changes"))?,
+        }))
+    }
+
+    async fn diffs_involving(&self, build_hash: &str) -> Result<Vec<(String, String)>> {
+        Ok(sqlx::query(
+            "SELECT old_build_id, new_build_id
+            FROM build_diffs
+            WHERE old_build_id = $1 OR new_build_id = $1",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: PgRow| (row.get("old_build_id"), row.get("new_build_id")))
+        .collect())
+    }
+
+    async fn release_webhook_deliveries(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+        payload: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET payload = $3::jsonb, next_attempt_at = CURRENT_TIMESTAMP
+            WHERE build_id = $1 AND branch = $2::discord_branch
+              AND status = 'pending' AND attempts = 0",
+        )
+        .bind(build_hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(payload.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unattempted_webhook_payload(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+    ) -> Result<Option<serde_json::Value>> {
+        sqlx::query(
+            "SELECT payload::text
+            FROM webhook_deliveries
+            WHERE build_id = $1 AND branch = $2::discord_branch AND status = 'pending' AND attempts = 0
+            LIMIT 1",
+        )
+        .bind(build_hash)
+        .bind(branch.to_string().to_lowercase())
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: PgRow| Ok(serde_json::from_str(row.get(0))?))
+        .transpose()
+    }
+
+    async fn next_due_webhook_delivery(&self) -> Result<Option<DueWebhookDelivery>> {
+        sqlx::query(
+            "SELECT delivery_id, webhook_url, payload::text, attempts
+            FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+            ORDER BY next_attempt_at ASC, delivery_id ASC
+            LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: PgRow| {
+            Ok(DueWebhookDelivery {
+                id: row.get("delivery_id"),
+                webhook_url: row.get("webhook_url"),
+                payload: serde_json::from_str(row.get("payload"))?,
+                attempts: row.get::<i32, _>("attempts") as u32,
+            })
+        })
+        .transpose()
+    }
+
+    async fn webhook_delivery_succeeded(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET status = 'delivered', attempts = attempts + 1, last_error = NULL,
+              finished_at = CURRENT_TIMESTAMP
+            WHERE delivery_id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn webhook_delivery_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET attempts = attempts + 1, last_error = $2,
+              status = CASE WHEN $3::timestamptz IS NULL
+                THEN 'dead'::webhook_delivery_status
+                ELSE 'pending'::webhook_delivery_status
+              END,
+              next_attempt_at = COALESCE($3, next_attempt_at),
+              finished_at = CASE WHEN $3 IS NULL THEN CURRENT_TIMESTAMP END
+            WHERE delivery_id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn postpone_webhook_deliveries(
+        &self,
+        webhook_url: &str,
+        until: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET next_attempt_at = $2
+            WHERE webhook_url = $1 AND status = 'pending' AND next_attempt_at < $2",
+        )
+        .bind(webhook_url)
+        .bind(until)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn webhook_deliveries_of_build(
+        &self,
+        build_hash: &str,
+    ) -> Result<Vec<WebhookDeliveryRecord>> {
+        Ok(sqlx::query(
+            "SELECT delivery_id, build_id, branch::text, status::text, attempts, last_error,
+              created_at, next_attempt_at, finished_at
+            FROM webhook_deliveries
+            WHERE build_id = $1
+            ORDER BY delivery_id ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(webhook_delivery_from_row)
+        .collect())
+    }
+
+    async fn webhook_deliveries(
+        &self,
+        status: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDeliveryRecord>> {
+        Ok(sqlx::query(
+            "SELECT delivery_id, build_id, branch::text, status::text, attempts, last_error,
+              created_at, next_attempt_at, finished_at
+            FROM webhook_deliveries
+            WHERE $1::webhook_delivery_status IS NULL OR status = $1::webhook_delivery_status
+            ORDER BY delivery_id DESC
+            LIMIT $2",
+        )
+        .bind(status)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(webhook_delivery_from_row)
+        .collect())
+    }
+}
+
+/// Inserts an asset, returning whether it wasn't already present.
+async fn insert_asset(
+    transaction: &mut sqlx::Transaction<'_, Postgres>,
+    c: &Cataloger,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO assets (name, surface, surface_script_type, script_chunk_id)
+        VALUES ($1, $2, $3::surface_script_type, $4)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(c.asset.filename())
+    .bind(c.kind.is_surface())
+    .bind(c.surface_script_type())
+    .bind(c.chunk_id)
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+async fn associate_asset(
+    transaction: &mut sqlx::Transaction<'_, Postgres>,
+    c: &Cataloger,
+    build: &FeBuild,
+) -> Result<()> {
+    tracing::debug!(?build.number, asset = c.asset.filename(), "associating asset");
+
+    sqlx::query(
+        "INSERT INTO build_assets (build_id, asset_name)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(&build.manifest.hash)
+    .bind(c.asset.filename())
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,1423 @@
+//! Storage in SQLite.
+//!
+//! Mirrors the Postgres storage, with enums stored as checked text and arrays
+//! passed around as JSON. SQLite only allows one writer at a time, so slow
+//! work such as fetching and parsing scripts happens before opening a write
+//! transaction instead of during one.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use havoc::{
+    discord::{AssetCache, Branch, FeAsset, FeBuild},
+    parse::ModuleId,
+};
+use sqlx::{
+    migrate::Migration,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+    Row, Sqlite, SqlitePool,
+};
+
+use super::{
+    catalogers, AssetRecord, BuildDiffRecord, BuildFilter, BuildModule, BuildRecord, BuildRef,
+    Cataloger, DetectionRecord, DueWebhookDelivery, DumpJobRecord, ModuleRecord, ModuleVersion,
+    NewWebhookDeliveries, Storage, StoredDump, WebhookDeliveryRecord,
+};
+use crate::{
+    config::SqliteConfig,
+    diff::BuildDiff,
+    lineage::{BuildChange, KnownBuild},
+    migrations,
+    modules::{fetch_and_walk_modules, WalkedModule, PARSING_BATCH_SIZE},
+};
+
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    pub async fn connect(config: &SqliteConfig) -> Result<Self> {
+        tracing::info!("opening sqlite database: {:?}", config.path);
+
+        let options = SqliteConnectOptions::new()
+            .filename(&config.path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+
+        Ok(Self { pool })
+    }
+}
+
+fn build_from_row(row: SqliteRow) -> BuildRecord {
+    BuildRecord {
+        hash: row.get("build_id"),
+        number: row.get::<i32, _>("build_number") as u32,
+        first_detected_at: row.get("first_detected_at"),
+    }
+}
+
+fn detection_from_row(row: SqliteRow) -> DetectionRecord {
+    DetectionRecord {
+        hash: row.get("build_id"),
+        number: row.get::<i32, _>("build_number") as u32,
+        branch: row
+            .get::<String, _>("branch")
+            .parse()
+            .expect("unknown branch in database"),
+        detected_at: row.get("detected_at"),
+        change_kind: row.get("change_kind"),
+        previous_hash: row.get("previous_build_id"),
+    }
+}
+
+fn asset_from_row(row: SqliteRow) -> AssetRecord {
+    AssetRecord {
+        name: row.get("name"),
+        surface: row.get("surface"),
+        surface_script_type: row.get("surface_script_type"),
+        script_chunk_id: row.get("script_chunk_id"),
+    }
+}
+
+fn webhook_delivery_from_row(row: SqliteRow) -> WebhookDeliveryRecord {
+    WebhookDeliveryRecord {
+        id: row.get("delivery_id"),
+        hash: row.get("build_id"),
+        branch: row
+            .get::<String, _>("branch")
+            .parse()
+            .expect("unknown branch in database"),
+        status: row.get("status"),
+        attempts: row.get::<i32, _>("attempts") as u32,
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        next_attempt_at: row.get("next_attempt_at"),
+        finished_at: row.get("finished_at"),
+    }
+}
+
+/// Fetches and parses script assets, walking the Webpack modules they
+/// contain. Scripts that fail to parse are skipped.
+async fn walk_scripts(
+    scripts: &[FeAsset],
+    cache: &mut AssetCache,
+) -> Result<Vec<(String, Vec<WalkedModule>)>> {
+    let mut walked_scripts = Vec::with_capacity(scripts.len());
+
+    for batch in scripts.chunks(PARSING_BATCH_SIZE) {
+        let walked = fetch_and_walk_modules(batch, cache).await?;
+
+        for (script, modules) in batch.iter().zip(walked) {
+            let name = script.filename();
+            match modules {
+                Ok(modules) => walked_scripts.push((name, modules)),
+                Err(err) => {
+                    tracing::warn!(asset = name, "failed to walk script for modules: {}", err);
+                }
+            }
+        }
+    }
+
+    Ok(walked_scripts)
+}
+
+/// Records the Webpack modules of walked scripts.
+async fn index_modules(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    walked_scripts: &[(String, Vec<WalkedModule>)],
+) -> Result<()> {
+    for (name, modules) in walked_scripts {
+        tracing::debug!(asset = name, modules = modules.len(), "indexing modules");
+
+        for module in modules {
+            sqlx::query(
+                "INSERT INTO module_sources (content_hash, source)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(&module.content_hash)
+            .bind(&module.source)
+            .execute(&mut *transaction)
+            .await?;
+
+            sqlx::query(
+                "INSERT INTO module_ids (name, module_id, content_hash, fingerprint, exports)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(name)
+            .bind(module.id as i32)
+            .bind(&module.content_hash)
+            .bind(&module.fingerprint)
+            .bind(serde_json::to_string(&module.exports)?)
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
+
+    tracing::info!("indexed modules of {} script(s)", walked_scripts.len());
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn ping(&self) -> Result<i32> {
+        Ok(sqlx::query("SELECT 1 + 1")
+            .fetch_one(&self.pool)
+            .await?
+            .get(0))
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<&'static Migration>> {
+        let mut conn = self.pool.acquire().await?;
+
+        let has_migrations_table: bool = sqlx::query(
+            "SELECT EXISTS (
+              SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'
+            )",
+        )
+        .fetch_one(&mut conn)
+        .await?
+        .get(0);
+
+        migrations::pending_migrations(&migrations::SQLITE, &mut *conn, has_migrations_table).await
+    }
+
+    async fn apply_migrations(&self) -> Result<()> {
+        migrations::SQLITE.run(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn last_known_build_on_branch(&self, branch: Branch) -> Result<Option<KnownBuild>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number
+            FROM detections
+            WHERE branch = $1
+            ORDER BY detected_at DESC
+            LIMIT 1",
+        )
+        .bind(branch.to_string().to_lowercase())
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: SqliteRow| KnownBuild {
+            hash: row.get(0),
+            number: row.get::<i32, _>(1) as u32,
+        }))
+    }
+
+    async fn branches_with_build(&self, build_hash: &str) -> Result<Vec<Branch>> {
+        let branches: Vec<String> = sqlx::query(
+            "SELECT DISTINCT branch
+            FROM build_deploys
+            WHERE build_id = $1",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| row.get(0))
+        .collect();
+
+        Ok(branches
+            .iter()
+            .filter_map(|branch| branch.parse().ok())
+            .collect())
+    }
+
+    async fn catalog_and_extract_assets(
+        &self,
+        build: &FeBuild,
+        cache: &mut AssetCache,
+    ) -> Result<()> {
+        let catalogers = catalogers(build, cache).await?;
+
+        // Assets are immutable, so scripts only need to be indexed for their
+        // modules when they're catalogued for the first time.
+        let names = catalogers
+            .iter()
+            .map(|c| c.asset.filename())
+            .collect::<Vec<_>>();
+        let known: HashSet<String> =
+            sqlx::query("SELECT name FROM assets WHERE name IN (SELECT value FROM json_each($1))")
+                .bind(serde_json::to_string(&names)?)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|row: SqliteRow| row.get(0))
+                .collect();
+
+        let unindexed_scripts = catalogers
+            .iter()
+            .filter(|c| c.has_modules() && !known.contains(&c.asset.filename()))
+            .map(|c| c.asset.clone())
+            .collect::<Vec<_>>();
+        let walked_scripts = walk_scripts(&unindexed_scripts, cache).await?;
+
+        let mut transaction = self.pool.begin().await?;
+
+        for c in &catalogers {
+            insert_asset(&mut transaction, c).await?;
+            associate_asset(&mut transaction, c, build).await?;
+        }
+
+        index_modules(&mut transaction, &walked_scripts).await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn build_hash_is_catalogued(&self, build_hash: &str) -> Result<bool> {
+        Ok(
+            sqlx::query("SELECT build_id FROM builds WHERE build_id = $1")
+                .bind(build_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some(),
+        )
+    }
+
+    async fn detected_build_change_on_branch(
+        &self,
+        build: &FeBuild,
+        branch: Branch,
+        change: &BuildChange,
+        previous: Option<&KnownBuild>,
+        deliveries: NewWebhookDeliveries<'_>,
+    ) -> Result<()> {
+        let number: i32 = build
+            .number
+            .try_into()
+            .expect("build number doesn't fit in i32");
+        let hash = build.manifest.hash.clone();
+
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO builds (build_id, build_number)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(&hash)
+        .bind(number)
+        .execute(&mut transaction)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO build_deploys (build_id, branch, change_kind, previous_build_id)
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(change.kind())
+        .bind(previous.map(|previous| &previous.hash))
+        .execute(&mut transaction)
+        .await?;
+
+        // Queued along with the deploy, so that subscribers hear about it
+        // even if cataloguing the build fails.
+        sqlx::query(
+            "INSERT INTO webhook_deliveries (build_id, branch, webhook_url, payload, next_attempt_at)
+            SELECT $1, $2, value, $4, strftime('%Y-%m-%d %H:%M:%f', $5) FROM json_each($3)",
+        )
+        .bind(&hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(serde_json::to_string(deliveries.webhook_urls)?)
+        .bind(deliveries.payload.to_string())
+        .bind(deliveries.due_at)
+        .execute(&mut transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn builds(&self, filter: &BuildFilter) -> Result<Vec<BuildRecord>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number, MIN(detected_at) AS first_detected_at
+            FROM detections
+            WHERE ($4 IS NULL OR build_number < $4)
+              AND EXISTS (
+                SELECT 1
+                FROM build_deploys deploys
+                WHERE deploys.build_id = detections.build_id
+                  AND ($1 IS NULL OR deploys.branch = $1)
+                  AND ($2 IS NULL OR julianday(deploys.detected_at) >= julianday($2))
+                  AND ($3 IS NULL OR julianday(deploys.detected_at) < julianday($3))
+              )
+            GROUP BY build_id, build_number
+            ORDER BY build_number DESC
+            LIMIT $5",
+        )
+        .bind(
+            filter
+                .branch
+                .map(|branch| branch.to_string().to_lowercase()),
+        )
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.before.map(|number| number as i32))
+        .bind(filter.limit as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(build_from_row)
+        .collect())
+    }
+
+    async fn build(&self, build: &BuildRef) -> Result<Option<BuildRecord>> {
+        let (hash, number) = match build {
+            BuildRef::Hash(hash) => (Some(hash.as_str()), None),
+            BuildRef::Number(number) => (None, Some(*number as i32)),
+        };
+
+        Ok(sqlx::query(
+            "SELECT builds.build_id, builds.build_number, MIN(deploys.detected_at) AS first_detected_at
+            FROM builds
+            INNER JOIN build_deploys deploys ON deploys.build_id = builds.build_id
+            WHERE builds.build_id = $1 OR builds.build_number = $2
+            GROUP BY builds.build_id, builds.build_number",
+        )
+        .bind(hash)
+        .bind(number)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(build_from_row))
+    }
+
+    async fn detections_of_build(&self, build_hash: &str) -> Result<Vec<DetectionRecord>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number, branch, detected_at, change_kind, previous_build_id
+            FROM detections
+            WHERE build_id = $1
+            ORDER BY detected_at ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(detection_from_row)
+        .collect())
+    }
+
+    async fn latest_detections(&self) -> Result<Vec<DetectionRecord>> {
+        Ok(sqlx::query(
+            "SELECT build_id, build_number, branch, detected_at, change_kind, previous_build_id
+            FROM (
+              SELECT *, ROW_NUMBER() OVER (PARTITION BY branch ORDER BY detected_at DESC) AS rank
+              FROM detections
+            )
+            WHERE rank = 1
+            ORDER BY branch",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(detection_from_row)
+        .collect())
+    }
+
+    async fn assets_of_build(&self, build_hash: &str) -> Result<Vec<AssetRecord>> {
+        Ok(sqlx::query(
+            "SELECT assets.name, assets.surface, assets.surface_script_type,
+              assets.script_chunk_id
+            FROM build_assets
+            INNER JOIN assets ON assets.name = build_assets.asset_name
+            WHERE build_assets.build_id = $1
+            ORDER BY assets.surface DESC, assets.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(asset_from_row)
+        .collect())
+    }
+
+    async fn asset(&self, name: &str) -> Result<Option<AssetRecord>> {
+        Ok(sqlx::query(
+            "SELECT name, surface, surface_script_type, script_chunk_id
+            FROM assets
+            WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(asset_from_row))
+    }
+
+    async fn builds_with_asset(&self, name: &str) -> Result<Vec<BuildRecord>> {
+        Ok(sqlx::query(
+            "SELECT builds.build_id, builds.build_number, MIN(deploys.detected_at) AS first_detected_at
+            FROM build_assets
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            INNER JOIN build_deploys deploys ON deploys.build_id = builds.build_id
+            WHERE build_assets.asset_name = $1
+            GROUP BY builds.build_id, builds.build_number
+            ORDER BY builds.build_number DESC",
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(build_from_row)
+        .collect())
+    }
+
+    async fn modules_in_build(
+        &self,
+        build_hash: &str,
+        module_id: ModuleId,
+    ) -> Result<Vec<ModuleRecord>> {
+        sqlx::query(
+            "SELECT assets.name, assets.surface, assets.surface_script_type,
+              assets.script_chunk_id, module_ids.module_id, module_ids.content_hash,
+              module_ids.fingerprint, module_ids.exports
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN assets ON assets.name = module_ids.name
+            WHERE build_assets.build_id = $1 AND module_ids.module_id = $2
+            ORDER BY assets.name ASC",
+        )
+        .bind(build_hash)
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| {
+            let exports: Option<String> = row.get("exports");
+            Ok(ModuleRecord {
+                module_id: row.get::<i32, _>("module_id") as ModuleId,
+                content_hash: row.get("content_hash"),
+                fingerprint: row.get("fingerprint"),
+                exports: exports
+                    .map(|exports| serde_json::from_str(&exports))
+                    .transpose()?,
+                asset: asset_from_row(row),
+            })
+        })
+        .collect()
+    }
+
+    async fn module_source(&self, content_hash: &str) -> Result<Option<String>> {
+        Ok(
+            sqlx::query("SELECT source FROM module_sources WHERE content_hash = $1")
+                .bind(content_hash)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|row: SqliteRow| row.get(0)),
+        )
+    }
+
+    async fn module_sources(&self, content_hashes: &[String]) -> Result<Vec<(String, String)>> {
+        Ok(sqlx::query(
+            "SELECT content_hash, source
+            FROM module_sources
+            WHERE content_hash IN (SELECT value FROM json_each($1))",
+        )
+        .bind(serde_json::to_string(content_hashes)?)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| (row.get("content_hash"), row.get("source")))
+        .collect())
+    }
+
+    async fn modules_of_build(&self, build_hash: &str) -> Result<Vec<BuildModule>> {
+        Ok(sqlx::query(
+            "SELECT module_ids.module_id, module_ids.content_hash, module_ids.fingerprint
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            WHERE build_assets.build_id = $1
+            ORDER BY module_ids.module_id ASC, module_ids.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| BuildModule {
+            module_id: row.get::<i32, _>("module_id") as ModuleId,
+            content_hash: row.get("content_hash"),
+            fingerprint: row.get("fingerprint"),
+        })
+        .collect())
+    }
+
+    async fn module_history(&self, module_id: ModuleId) -> Result<Vec<ModuleVersion>> {
+        Ok(sqlx::query(
+            "SELECT module_ids.content_hash, module_ids.fingerprint,
+              MIN(builds.build_number) AS first_build_number,
+              MAX(builds.build_number) AS last_build_number
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            WHERE module_ids.module_id = $1 AND module_ids.content_hash IS NOT NULL
+            GROUP BY module_ids.content_hash, module_ids.fingerprint
+            ORDER BY first_build_number ASC",
+        )
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| ModuleVersion {
+            content_hash: row.get("content_hash"),
+            fingerprint: row.get("fingerprint"),
+            first_build_number: row.get::<i32, _>("first_build_number") as u32,
+            last_build_number: row.get::<i32, _>("last_build_number") as u32,
+        })
+        .collect())
+    }
+
+    async fn module_content_hashes_by_build(
+        &self,
+        module_id: ModuleId,
+    ) -> Result<Vec<(u32, String)>> {
+        Ok(sqlx::query(
+            "SELECT builds.build_number, MIN(module_ids.content_hash) AS content_hash
+            FROM module_ids
+            INNER JOIN build_assets ON build_assets.asset_name = module_ids.name
+            INNER JOIN builds ON builds.build_id = build_assets.build_id
+            WHERE module_ids.module_id = $1 AND module_ids.content_hash IS NOT NULL
+            GROUP BY builds.build_number
+            ORDER BY builds.build_number ASC",
+        )
+        .bind(module_id as i32)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| {
+            (
+                row.get::<i32, _>("build_number") as u32,
+                row.get("content_hash"),
+            )
+        })
+        .collect())
+    }
+
+    async fn surface_asset_names(&self, build_hash: &str) -> Result<Vec<String>> {
+        Ok(sqlx::query(
+            "SELECT assets.name
+            FROM build_assets
+            INNER JOIN assets ON assets.name = build_assets.asset_name
+            WHERE build_assets.build_id = $1 AND assets.surface
+            ORDER BY
+              CASE assets.surface_script_type
+                WHEN 'chunkloader' THEN 0
+                WHEN 'classes' THEN 1
+                WHEN 'vendor' THEN 2
+                WHEN 'entrypoint' THEN 3
+                ELSE 4
+              END ASC,
+              assets.name ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| row.get(0))
+        .collect())
+    }
+
+    async fn enqueue_dump_jobs(&self, build_hash: &str, dumpers: &[String]) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO dump_jobs (build_id, dumper)
+            SELECT $1, value FROM json_each($2)
+            WHERE true
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(build_hash)
+        .bind(serde_json::to_string(dumpers)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn requeue_interrupted_dump_jobs(&self) -> Result<u64> {
+        Ok(sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'pending', started_at = NULL
+            WHERE status = 'running'",
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected())
+    }
+
+    async fn next_pending_dump_jobs(&self) -> Result<Option<(String, Vec<String>)>> {
+        let Some(build_hash) = sqlx::query(
+            "SELECT build_id
+            FROM dump_jobs
+            WHERE status = 'pending'
+            ORDER BY created_at ASC
+            LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: SqliteRow| row.get::<String, _>(0)) else {
+            return Ok(None);
+        };
+
+        let dumpers = sqlx::query(
+            "SELECT dumper
+            FROM dump_jobs
+            WHERE build_id = $1 AND status = 'pending'
+            ORDER BY dumper ASC",
+        )
+        .bind(&build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| row.get(0))
+        .collect();
+
+        Ok(Some((build_hash, dumpers)))
+    }
+
+    async fn dump_job_started(&self, build_hash: &str, dumper: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'running', attempts = attempts + 1,
+              started_at = strftime('%Y-%m-%d %H:%M:%f', 'now'), finished_at = NULL, error = NULL
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_job_succeeded(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+        filename: &str,
+        stored: &StoredDump,
+    ) -> Result<()> {
+        let (content, path) = match stored {
+            StoredDump::Database(content) => (Some(content.as_str()), None),
+            StoredDump::File(path) => (None, Some(path.to_string_lossy())),
+        };
+
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'succeeded', finished_at = strftime('%Y-%m-%d %H:%M:%f', 'now'),
+              filename = $3, content = $4, path = $5
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .bind(filename)
+        .bind(content)
+        .bind(path)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_job_failed(&self, build_hash: &str, dumper: &str, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE dump_jobs
+            SET status = 'failed', finished_at = strftime('%Y-%m-%d %H:%M:%f', 'now'),
+              error = $3
+            WHERE build_id = $1 AND dumper = $2",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn dump_jobs_of_build(&self, build_hash: &str) -> Result<Vec<DumpJobRecord>> {
+        Ok(sqlx::query(
+            "SELECT dumper, status, attempts, error, created_at, started_at, finished_at,
+              filename
+            FROM dump_jobs
+            WHERE build_id = $1
+            ORDER BY dumper ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| DumpJobRecord {
+            dumper: row.get("dumper"),
+            status: row.get("status"),
+            attempts: row.get::<i32, _>("attempts") as u32,
+            error: row.get("error"),
+            created_at: row.get("created_at"),
+            started_at: row.get("started_at"),
+            finished_at: row.get("finished_at"),
+            filename: row.get("filename"),
+        })
+        .collect())
+    }
+
+    async fn dump_job_result(
+        &self,
+        build_hash: &str,
+        dumper: &str,
+    ) -> Result<Option<(String, StoredDump)>> {
+        let Some(row) = sqlx::query(
+            "SELECT filename, content, path
+            FROM dump_jobs
+            WHERE build_id = $1 AND dumper = $2 AND status = 'succeeded'",
+        )
+        .bind(build_hash)
+        .bind(dumper)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let stored = match (row.get("content"), row.get::<Option<String>, _>("path")) {
+            (Some(content), _) => StoredDump::Database(content),
+            (None, Some(path)) => StoredDump::File(path.into()),
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some((row.get("filename"), stored)))
+    }
+
+    async fn store_build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+        diff: &BuildDiff,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO build_diffs (old_build_id, new_build_id, summary, changes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (old_build_id, new_build_id) DO UPDATE
+            SET computed_at = strftime('%Y-%m-%d %H:%M:%f', 'now'), summary = excluded.summary,
+              changes = excluded.changes",
+        )
+        .bind(old_build_hash)
+        .bind(new_build_hash)
+        .bind(serde_json::to_string(&diff.summary)?)
+        .bind(diff.changes.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn build_diff(
+        &self,
+        old_build_hash: &str,
+        new_build_hash: &str,
+    ) -> Result<Option<BuildDiffRecord>> {
+        let Some(row) = sqlx::query(
+            "SELECT computed_at, summary, changes
+            FROM build_diffs
+            WHERE old_build_id = $1 AND new_build_id = $2",
+        )
+        .bind(old_build_hash)
+        .bind(new_build_hash)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(BuildDiffRecord {
+            computed_at: row.get("computed_at"),
+            summary: serde_json::from_str(row.get("summary"))?,
+            changes: serde_json::from_str(row.get("changes"))?,
+        }))
+    }
+
+    async fn diffs_involving(&self, build_hash: &str) -> Result<Vec<(String, String)>> {
+        Ok(sqlx::query(
+            "SELECT old_build_id, new_build_id
+            FROM build_diffs
+            WHERE old_build_id = $1 OR new_build_id = $1",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row: SqliteRow| (row.get("old_build_id"), row.get("new_build_id")))
+        .collect())
+    }
+
+    async fn release_webhook_deliveries(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+        payload: &serde_json::Value,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET payload = $3, next_attempt_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+            WHERE build_id = $1 AND branch = $2 AND status = 'pending' AND attempts = 0",
+        )
+        .bind(build_hash)
+        .bind(branch.to_string().to_lowercase())
+        .bind(payload.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unattempted_webhook_payload(
+        &self,
+        build_hash: &str,
+        branch: Branch,
+    ) -> Result<Option<serde_json::Value>> {
+        sqlx::query(
+            "SELECT payload
+            FROM webhook_deliveries
+            WHERE build_id = $1 AND branch = $2 AND status = 'pending' AND attempts = 0
+            LIMIT 1",
+        )
+        .bind(build_hash)
+        .bind(branch.to_string().to_lowercase())
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: SqliteRow| Ok(serde_json::from_str(row.get(0))?))
+        .transpose()
+    }
+
+    async fn next_due_webhook_delivery(&self) -> Result<Option<DueWebhookDelivery>> {
+        sqlx::query(
+            "SELECT delivery_id, webhook_url, payload, attempts
+            FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= strftime('%Y-%m-%d %H:%M:%f', 'now')
+            ORDER BY next_attempt_at ASC, delivery_id ASC
+            LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row: SqliteRow| {
+            Ok(DueWebhookDelivery {
+                id: row.get("delivery_id"),
+                webhook_url: row.get("webhook_url"),
+                payload: serde_json::from_str(row.get("payload"))?,
+                attempts: row.get::<i32, _>("attempts") as u32,
+            })
+        })
+        .transpose()
+    }
+
+    async fn webhook_delivery_succeeded(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET status = 'delivered', attempts = attempts + 1, last_error = NULL,
+              finished_at = strftime('%Y-%m-%d %H:%M:%f', 'now')
+            WHERE delivery_id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn webhook_delivery_failed(
+        &self,
+        id: i64,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        // Times are normalized to the format of the column defaults, so that
+        // they can be compared as text.
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET attempts = attempts + 1, last_error = $2,
+              status = CASE WHEN $3 IS NULL THEN 'dead' ELSE 'pending' END,
+              next_attempt_at = COALESCE(strftime('%Y-%m-%d %H:%M:%f', $3), next_attempt_at),
+              finished_at = CASE WHEN $3 IS NULL THEN strftime('%Y-%m-%d %H:%M:%f', 'now') END
+            WHERE delivery_id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn postpone_webhook_deliveries(
+        &self,
+        webhook_url: &str,
+        until: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries
+            SET next_attempt_at = strftime('%Y-%m-%d %H:%M:%f', $2)
+            WHERE webhook_url = $1 AND status = 'pending'
+              AND next_attempt_at < strftime('%Y-%m-%d %H:%M:%f', $2)",
+        )
+        .bind(webhook_url)
+        .bind(until)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn webhook_deliveries_of_build(
+        &self,
+        build_hash: &str,
+    ) -> Result<Vec<WebhookDeliveryRecord>> {
+        Ok(sqlx::query(
+            "SELECT delivery_id, build_id, branch, status, attempts, last_error, created_at,
+              next_attempt_at, finished_at
+            FROM webhook_deliveries
+            WHERE build_id = $1
+            ORDER BY delivery_id ASC",
+        )
+        .bind(build_hash)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(webhook_delivery_from_row)
+        .collect())
+    }
+
+    async fn webhook_deliveries(
+        &self,
+        status: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<WebhookDeliveryRecord>> {
+        Ok(sqlx::query(
+            "SELECT delivery_id, build_id, branch, status, attempts, last_error, created_at,
+              next_attempt_at, finished_at
+            FROM webhook_deliveries
+            WHERE $1 IS NULL OR status = $1
+            ORDER BY delivery_id DESC
+            LIMIT $2",
+        )
+        .bind(status)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(webhook_delivery_from_row)
+        .collect())
+    }
+}
+
+async fn insert_asset(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    c: &Cataloger,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO assets (name, surface, surface_script_type, script_chunk_id)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(c.asset.filename())
+    .bind(c.kind.is_surface())
+    .bind(c.surface_script_type())
+    .bind(c.chunk_id)
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+async fn associate_asset(
+    transaction: &mut sqlx::Transaction<'_, Sqlite>,
+    c: &Cataloger,
+    build: &FeBuild,
+) -> Result<()> {
+    tracing::debug!(?build.number, asset = c.asset.filename(), "associating asset");
+
+    sqlx::query(
+        "INSERT INTO build_assets (build_id, asset_name)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING",
+    )
+    .bind(&build.manifest.hash)
+    .bind(c.asset.filename())
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use havoc::discord::{FeAssetType, FeManifest};
+
+    use super::*;
+    use crate::{db::Db, diff::diff_and_store};
+
+    /// Opens a fresh in-memory database. A single connection is kept open for
+    /// as long as the pool lives, since the database vanishes along with it.
+    async fn memory_db() -> Db {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .unwrap();
+
+        let db = Db::new(SqliteStorage { pool });
+        assert!(db.migrate().await.unwrap() > 0);
+        assert!(db.pending_migrations().await.unwrap().is_empty());
+        db
+    }
+
+    fn asset(name: &str, typ: FeAssetType) -> FeAsset {
+        FeAsset {
+            name: name.to_owned(),
+            typ,
+        }
+    }
+
+    /// Creates a build with a single deep chunk containing some modules, and
+    /// seeds a cache with the content of its scripts.
+    fn build(hash: &str, number: u32, chunk_hash: &str, modules: &str) -> (FeBuild, AssetCache) {
+        let chunk_loader = asset(&format!("loader{}", number), FeAssetType::Js);
+        let surface_scripts = [
+            chunk_loader.clone(),
+            asset("classes", FeAssetType::Js),
+            asset("vendor", FeAssetType::Js),
+            asset("entrypoint", FeAssetType::Js),
+        ];
+
+        let mut cache = AssetCache::new();
+        cache.insert_raw_content(
+            &chunk_loader,
+            format!(
+                r#"r.u = (e) => e + "." + {{ 1: "{}" }}[e] + ".js";"#,
+                chunk_hash
+            )
+            .into(),
+        );
+        for script in &surface_scripts[1..] {
+            cache.insert_raw_content(script, b"\"use strict\";".to_vec());
+        }
+        cache.insert_raw_content(
+            &asset(&format!("1.{}", chunk_hash), FeAssetType::Js),
+            format!(
+                "(self.webpackChunk = self.webpackChunk || []).push([[1], {{ {} }}]);",
+                modules
+            )
+            .into(),
+        );
+
+        let mut assets = vec![asset("style", FeAssetType::Css)];
+        assets.extend(surface_scripts);
+
+        let build = FeBuild {
+            manifest: FeManifest {
+                branch: Branch::Canary,
+                hash: hash.to_owned(),
+                assets,
+            },
+            number,
+        };
+        (build, cache)
+    }
+
+    const OLD_MODULES: &str =
+        r#"10: (e) => { e.exports = "going away"; }, 11: (e) => { e.exports = "staying"; }"#;
+    const NEW_MODULES: &str =
+        r#"11: (e) => { e.exports = "staying"; }, 12: (e) => { e.exports = "new"; }"#;
+
+    /// Detects a build on Canary following another build, without queueing
+    /// any webhook deliveries, and catalogues it.
+    async fn catalogue(
+        db: &Db,
+        (build, mut cache): (FeBuild, AssetCache),
+        previous: Option<&KnownBuild>,
+    ) -> KnownBuild {
+        let payload = serde_json::json!({});
+        let no_deliveries = NewWebhookDeliveries {
+            webhook_urls: &[],
+            payload: &payload,
+            due_at: Utc::now(),
+        };
+
+        db.detected_build_change_on_branch(
+            &build,
+            Branch::Canary,
+            &BuildChange::New,
+            previous,
+            no_deliveries,
+        )
+        .await
+        .unwrap();
+        db.catalog_and_extract_assets(&build, &mut cache)
+            .await
+            .unwrap();
+
+        KnownBuild {
+            hash: build.manifest.hash,
+            number: build.number,
+        }
+    }
+
+    /// Catalogues an old build and the new build that followed it.
+    async fn catalogue_old_and_new(db: &Db) -> (KnownBuild, KnownBuild) {
+        let old = catalogue(db, build("old", 1, "aaaa", OLD_MODULES), None).await;
+        let new = catalogue(db, build("new", 2, "bbbb", NEW_MODULES), Some(&old)).await;
+        (old, new)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn catalogues_builds() {
+        let db = memory_db().await;
+
+        assert!(!db.build_hash_is_catalogued("old").await.unwrap());
+        let old = catalogue(&db, build("old", 1, "aaaa", OLD_MODULES), None).await;
+
+        assert!(db.build_hash_is_catalogued("old").await.unwrap());
+        assert_eq!(
+            db.last_known_build_on_branch(Branch::Canary).await.unwrap(),
+            Some(old.clone())
+        );
+        assert_eq!(
+            db.branches_with_build("old").await.unwrap(),
+            vec![Branch::Canary]
+        );
+
+        let assets = db.assets_of_build("old").await.unwrap();
+        assert_eq!(assets.len(), 6);
+        let deep = db.asset("1.aaaa.js").await.unwrap().unwrap();
+        assert!(!deep.surface);
+        assert_eq!(deep.script_chunk_id, Some(1));
+        assert_eq!(
+            db.surface_asset_names("old").await.unwrap(),
+            vec![
+                "loader1.js",
+                "classes.js",
+                "vendor.js",
+                "entrypoint.js",
+                "style.css"
+            ]
+        );
+
+        let modules = db.modules_of_build("old").await.unwrap();
+        assert_eq!(
+            modules
+                .iter()
+                .map(|module| module.module_id)
+                .collect::<Vec<_>>(),
+            vec![10, 11]
+        );
+        let source = db
+            .module_source(modules[0].content_hash.as_ref().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(source.contains("going away"));
+
+        catalogue(&db, build("new", 2, "bbbb", NEW_MODULES), Some(&old)).await;
+        assert_eq!(
+            db.module_content_hashes_by_build(11)
+                .await
+                .unwrap()
+                .iter()
+                .map(|(number, _)| *number)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(db.builds_with_asset("style.css").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn records_detections() {
+        let db = memory_db().await;
+        let (_, new) = catalogue_old_and_new(&db).await;
+
+        // Promote the new build to another branch.
+        let (build, _) = build("new", 2, "bbbb", NEW_MODULES);
+        let payload = serde_json::json!({});
+        db.detected_build_change_on_branch(
+            &build,
+            Branch::Ptb,
+            &BuildChange::Promotion {
+                from: vec![Branch::Canary],
+            },
+            None,
+            NewWebhookDeliveries {
+                webhook_urls: &[],
+                payload: &payload,
+                due_at: Utc::now(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let detections = db.detections_of_build("new").await.unwrap();
+        assert_eq!(detections.len(), 2);
+        assert_eq!(detections[0].branch, Branch::Canary);
+        assert_eq!(detections[0].change_kind.as_deref(), Some("new"));
+        assert_eq!(detections[0].previous_hash.as_deref(), Some("old"));
+        assert_eq!(detections[1].branch, Branch::Ptb);
+        assert_eq!(detections[1].change_kind.as_deref(), Some("promotion"));
+        assert_eq!(detections[1].previous_hash, None);
+
+        assert_eq!(db.branches_with_build("new").await.unwrap().len(), 2);
+        assert_eq!(
+            db.last_known_build_on_branch(Branch::Ptb).await.unwrap(),
+            Some(new)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stores_diffs() {
+        let db = memory_db().await;
+        catalogue_old_and_new(&db).await;
+
+        let diff = diff_and_store(&db, "old", "new").await.unwrap();
+        let modules = diff.summary.modules.unwrap();
+        assert_eq!((modules.added, modules.removed, modules.changed), (1, 1, 0));
+        assert_eq!(diff.changes["strings"]["added"], serde_json::json!(["new"]));
+        assert_eq!(
+            diff.changes["strings"]["removed"],
+            serde_json::json!(["going away"])
+        );
+
+        let stored = db.build_diff("old", "new").await.unwrap().unwrap();
+        assert_eq!(stored.summary, diff.summary);
+        assert!(db.build_diff("new", "old").await.unwrap().is_none());
+        assert_eq!(
+            db.diffs_involving("new").await.unwrap(),
+            vec![("old".to_owned(), "new".to_owned())]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_dump_jobs() {
+        let db = memory_db().await;
+        catalogue(&db, build("new", 2, "bbbb", NEW_MODULES), None).await;
+
+        let dumpers = vec!["classes".to_owned(), "i18n".to_owned()];
+        db.enqueue_dump_jobs("new", &dumpers).await.unwrap();
+        db.enqueue_dump_jobs("new", &dumpers).await.unwrap();
+        assert_eq!(
+            db.next_pending_dump_jobs().await.unwrap(),
+            Some(("new".to_owned(), dumpers.clone()))
+        );
+
+        db.dump_job_started("new", "classes").await.unwrap();
+        db.dump_job_started("new", "i18n").await.unwrap();
+        assert_eq!(db.requeue_interrupted_dump_jobs().await.unwrap(), 2);
+
+        db.dump_job_started("new", "classes").await.unwrap();
+        db.dump_job_succeeded(
+            "new",
+            "classes",
+            "classes.json",
+            &StoredDump::Database("{}".to_owned()),
+        )
+        .await
+        .unwrap();
+        db.dump_job_started("new", "i18n").await.unwrap();
+        db.dump_job_failed("new", "i18n", "no tables")
+            .await
+            .unwrap();
+        assert_eq!(db.next_pending_dump_jobs().await.unwrap(), None);
+
+        let jobs = db.dump_jobs_of_build("new").await.unwrap();
+        assert_eq!(
+            jobs.iter()
+                .map(|job| (job.dumper.as_str(), job.status.as_str(), job.attempts))
+                .collect::<Vec<_>>(),
+            vec![("classes", "succeeded", 2), ("i18n", "failed", 2)]
+        );
+        assert_eq!(jobs[1].error.as_deref(), Some("no tables"));
+
+        let (filename, stored) = db.dump_job_result("new", "classes").await.unwrap().unwrap();
+        assert_eq!(filename, "classes.json");
+        assert_eq!(stored.read().await.unwrap(), "{}");
+        assert!(db.dump_job_result("new", "i18n").await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn holds_webhook_deliveries_until_released() {
+        let db = memory_db().await;
+        let old = catalogue(&db, build("old", 1, "aaaa", OLD_MODULES), None).await;
+
+        let (new, mut cache) = build("new", 2, "bbbb", NEW_MODULES);
+        let webhook_urls = vec!["https://discord.invalid/api/webhooks/1".to_owned()];
+        let detected_payload = serde_json::json!({ "content": "detected" });
+        db.detected_build_change_on_branch(
+            &new,
+            Branch::Canary,
+            &BuildChange::New,
+            Some(&old),
+            NewWebhookDeliveries {
+                webhook_urls: &webhook_urls,
+                payload: &detected_payload,
+                due_at: Utc::now() + chrono::Duration::hours(1),
+            },
+        )
+        .await
+        .unwrap();
+        db.catalog_and_extract_assets(&new, &mut cache)
+            .await
+            .unwrap();
+
+        let deliveries = db.webhook_deliveries_of_build("new").await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].branch, Branch::Canary);
+        assert_eq!(deliveries[0].status, "pending");
+        assert!(db.next_due_webhook_delivery().await.unwrap().is_none());
+        assert_eq!(
+            db.unattempted_webhook_payload("new", Branch::Canary)
+                .await
+                .unwrap(),
+            Some(detected_payload)
+        );
+        assert!(db
+            .unattempted_webhook_payload("new", Branch::Ptb)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Only the deliveries about the released branch become due.
+        let compared_payload = serde_json::json!({ "content": "compared" });
+        db.release_webhook_deliveries("new", Branch::Ptb, &compared_payload)
+            .await
+            .unwrap();
+        assert!(db.next_due_webhook_delivery().await.unwrap().is_none());
+        db.release_webhook_deliveries("new", Branch::Canary, &compared_payload)
+            .await
+            .unwrap();
+
+        let due = db.next_due_webhook_delivery().await.unwrap().unwrap();
+        assert_eq!(due.webhook_url, webhook_urls[0]);
+        assert_eq!(due.payload, compared_payload);
+        db.webhook_delivery_succeeded(due.id).await.unwrap();
+
+        assert!(db.next_due_webhook_delivery().await.unwrap().is_none());
+        assert!(db
+            .unattempted_webhook_payload("new", Branch::Canary)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            db.webhook_deliveries(Some("delivered"), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Delivering queued webhook posts in the background.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use tracing::Instrument;
+
+use crate::{
+    db::{Db, DueWebhookDelivery},
+    webhook::{post_to_webhook, DeliveryOutcome},
+};
+
+/// How long to wait before checking for due deliveries again, when there
+/// weren't any.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many times to attempt a delivery before giving up on it. Being rate
+/// limited doesn't count as an attempt.
+const MAX_ATTEMPTS: u32 = 8;
+
+/// How long to wait before retrying a delivery after its first failed
+/// attempt. This doubles with every attempt, up to [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Delivers queued webhook posts as they become due, one at a time.
+///
+/// Deliveries are only marked as delivered after Discord responds, so one
+/// interrupted by watchdog stopping is attempted again (and possibly posted
+/// twice) when it starts again.
+pub async fn run_webhook_deliveries_forever(db: &Db) -> Result<()> {
+    loop {
+        match db.next_due_webhook_delivery().await? {
+            Some(delivery) => {
+                let span = tracing::info_span!("webhook_delivery", id = delivery.id);
+                deliver(db, &delivery).instrument(span).await?;
+            }
+            None => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Returns how long to wait before retrying a delivery after a failed attempt
+/// (counting from one), or `None` if it shouldn't be retried anymore.
+fn retry_backoff(attempt: u32) -> Option<Duration> {
+    if attempt >= MAX_ATTEMPTS {
+        return None;
+    }
+
+    Some(
+        INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_BACKOFF),
+    )
+}
+
+/// Attempts a delivery, recording the outcome.
+async fn deliver(db: &Db, delivery: &DueWebhookDelivery) -> Result<()> {
+    let attempt = delivery.attempts + 1;
+
+    let error = match post_to_webhook(&delivery.webhook_url, &delivery.payload).await {
+        DeliveryOutcome::Delivered => {
+            tracing::info!(attempt, "delivered");
+            return db.webhook_delivery_succeeded(delivery.id).await;
+        }
+        DeliveryOutcome::RateLimited(retry_after) => {
+            // Every pending delivery to the webhook is held off, as they'd
+            // only be rate limited too.
+            tracing::warn!(?retry_after, "rate limited by discord");
+            let until = Utc::now() + chrono::Duration::from_std(retry_after)?;
+            return db
+                .postpone_webhook_deliveries(&delivery.webhook_url, until)
+                .await;
+        }
+        DeliveryOutcome::Rejected(error) => {
+            tracing::warn!(attempt, "delivery rejected, giving up: {}", error);
+            return db.webhook_delivery_failed(delivery.id, &error, None).await;
+        }
+        DeliveryOutcome::Failed(error) => error,
+    };
+
+    let Some(backoff) = retry_backoff(attempt) else {
+        tracing::warn!(attempt, "delivery failed, giving up: {}", error);
+        return db.webhook_delivery_failed(delivery.id, &error, None).await;
+    };
+    tracing::warn!(attempt, ?backoff, "delivery failed: {}", error);
+
+    let retry_at = Utc::now() + chrono::Duration::from_std(backoff)?;
+    db.webhook_delivery_failed(delivery.id, &error, Some(retry_at))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(retry_backoff(1), Some(INITIAL_BACKOFF));
+        assert_eq!(retry_backoff(2), Some(INITIAL_BACKOFF * 2));
+        assert_eq!(retry_backoff(3), Some(INITIAL_BACKOFF * 4));
+        assert_eq!(retry_backoff(7), Some(INITIAL_BACKOFF * 64));
+        assert!((1..MAX_ATTEMPTS).all(|attempt| retry_backoff(attempt).unwrap() <= MAX_BACKOFF));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        assert!(retry_backoff(MAX_ATTEMPTS - 1).is_some());
+        assert_eq!(retry_backoff(MAX_ATTEMPTS), None);
+        assert_eq!(retry_backoff(u32::MAX), None);
+    }
+}
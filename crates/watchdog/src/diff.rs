@@ -0,0 +1,633 @@
+//! Comparing catalogued builds.
+//!
+//! Assets and modules are compared using what was catalogued and indexed.
+//! Class mappings, experiments, and messages are compared using the results of
+//! the `classes`, `experiments`, and `i18n` dumpers, so they're only included
+//! once both builds have been dumped.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{Context, Result};
+use havoc::{
+    dump::{
+        classes::ClassModuleMap,
+        experiments::{diff_experiments, ExperimentChanges, ExperimentMap},
+        i18n::{diff_messages, LocaleMessagesMap, MessageChanges, DEFAULT_LOCALE},
+    },
+    parse::{parse_scripts, ModuleId},
+    search::string_literals,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::db::{AssetRecord, BuildModule, Db};
+
+/// How many things were added, removed, or changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+/// The amount of changes between two builds, by kind.
+///
+/// Kinds of changes that couldn't be compared are absent, e.g. experiments
+/// before both builds have been dumped.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    pub assets: ChangeCounts,
+    pub modules: Option<ChangeCounts>,
+    pub strings: Option<ChangeCounts>,
+    pub classes: Option<ChangeCounts>,
+    pub experiments: Option<ChangeCounts>,
+    pub messages: Option<ChangeCounts>,
+}
+
+fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+impl DiffSummary {
+    /// Describes the summary in a short line, e.g. `+34 modules, -12 modules,
+    /// 87 changed, 5 new experiments`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+
+        if let Some(modules) = self.modules {
+            if modules.added > 0 {
+                parts.push(format!("+{}", plural(modules.added, "module", "modules")));
+            }
+            if modules.removed > 0 {
+                parts.push(format!("-{}", plural(modules.removed, "module", "modules")));
+            }
+            if modules.changed > 0 {
+                parts.push(format!("{} changed", modules.changed));
+            }
+        }
+
+        if let Some(experiments) = self.experiments {
+            if experiments.added > 0 {
+                parts.push(plural(
+                    experiments.added,
+                    "new experiment",
+                    "new experiments",
+                ));
+            }
+            if experiments.removed > 0 {
+                parts.push(plural(
+                    experiments.removed,
+                    "removed experiment",
+                    "removed experiments",
+                ));
+            }
+        }
+
+        if let Some(strings) = self.strings {
+            if strings.added > 0 {
+                parts.push(plural(strings.added, "new string", "new strings"));
+            }
+        }
+
+        if parts.is_empty() {
+            if self.modules.is_some() {
+                "No module changes".to_owned()
+            } else {
+                plural(self.assets.added, "new asset", "new assets")
+            }
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// A difference between two builds.
+#[derive(Debug, Clone)]
+pub struct BuildDiff {
+    pub summary: DiffSummary,
+
+    /// The changes themselves, as JSON. Has a key for each kind of change
+    /// in the summary.
+    pub changes: serde_json::Value,
+}
+
+/// Items that were added or removed between two sets.
+#[derive(Serialize, Debug)]
+struct SetChanges<T> {
+    added: Vec<T>,
+    removed: Vec<T>,
+}
+
+impl<T: Ord + Clone> SetChanges<T> {
+    fn between(old: &BTreeSet<T>, new: &BTreeSet<T>) -> Self {
+        SetChanges {
+            added: new.difference(old).cloned().collect(),
+            removed: old.difference(new).cloned().collect(),
+        }
+    }
+
+    fn counts(&self) -> ChangeCounts {
+        ChangeCounts {
+            added: self.added.len(),
+            removed: self.removed.len(),
+            changed: 0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+struct ModuleChanges {
+    added: Vec<ModuleId>,
+    removed: Vec<ModuleId>,
+
+    /// Modules whose fingerprints differ. Modules that were only shuffled
+    /// around by minification aren't considered to have changed.
+    changed: Vec<ModuleId>,
+}
+
+/// A class mapping that was added, removed, or changed.
+#[derive(Serialize, Debug)]
+struct ClassChange<'a> {
+    module_id: ModuleId,
+    name: &'a str,
+    old: Option<&'a str>,
+    new: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+struct Changes<'a> {
+    assets: SetChanges<String>,
+    modules: Option<ModuleChanges>,
+    strings: Option<SetChanges<String>>,
+    classes: Option<Vec<ClassChange<'a>>>,
+    experiments: Option<ExperimentChanges<'a>>,
+    messages: Option<MessageChanges<'a>>,
+}
+
+/// Reduces the modules of a build to a single instance of each module.
+/// Returns `None` if the build's modules were never indexed.
+fn index_build_modules(modules: Vec<BuildModule>) -> Option<BTreeMap<ModuleId, BuildModule>> {
+    let mut indexed = BTreeMap::new();
+    for module in modules {
+        if module.content_hash.is_some() {
+            indexed.entry(module.module_id).or_insert(module);
+        }
+    }
+
+    (!indexed.is_empty()).then_some(indexed)
+}
+
+fn diff_modules(
+    old: &BTreeMap<ModuleId, BuildModule>,
+    new: &BTreeMap<ModuleId, BuildModule>,
+) -> ModuleChanges {
+    let mut changes = ModuleChanges::default();
+
+    for (id, new_module) in new {
+        match old.get(id) {
+            None => changes.added.push(*id),
+            Some(old_module) => {
+                let differs = match (&old_module.fingerprint, &new_module.fingerprint) {
+                    (Some(old), Some(new)) => old != new,
+                    _ => old_module.content_hash != new_module.content_hash,
+                };
+                if differs {
+                    changes.changed.push(*id);
+                }
+            }
+        }
+    }
+
+    changes.removed = old
+        .keys()
+        .filter(|id| !new.contains_key(id))
+        .copied()
+        .collect();
+
+    changes
+}
+
+/// Collects the string literals of modules from their stored sources.
+async fn collect_strings(db: &Db, content_hashes: &BTreeSet<&str>) -> Result<BTreeSet<String>> {
+    let content_hashes = content_hashes
+        .iter()
+        .map(|content_hash| (*content_hash).to_owned())
+        .collect::<Vec<_>>();
+
+    // Module sources are bare function expressions, which need to be wrapped
+    // in parentheses to parse as a script.
+    let sources = db
+        .module_sources(&content_hashes)
+        .await?
+        .into_iter()
+        .map(|(content_hash, source)| (content_hash, format!("({})", source)))
+        .collect::<Vec<_>>();
+
+    let labelled = sources
+        .iter()
+        .map(|(content_hash, source)| (content_hash.as_str(), source.as_str()))
+        .collect::<Vec<_>>();
+
+    tokio::task::block_in_place(|| {
+        let mut strings = BTreeSet::new();
+        for ((content_hash, _), script) in labelled.iter().zip(parse_scripts(&labelled)) {
+            let script = script
+                .with_context(|| format!("failed to parse module source {}", content_hash))?;
+            strings.extend(string_literals(&script));
+        }
+        Ok(strings)
+    })
+}
+
+/// Returns the content hashes of the modules that were added, removed, or
+/// changed between two builds, for each build.
+///
+/// Content that the other build also contains, e.g. a module that only moved
+/// to another ID, is left out, since its strings can't have changed.
+fn differing_content_hashes<'m>(
+    old: &'m BTreeMap<ModuleId, BuildModule>,
+    new: &'m BTreeMap<ModuleId, BuildModule>,
+    modules: &ModuleChanges,
+) -> (BTreeSet<&'m str>, BTreeSet<&'m str>) {
+    let content_hashes = |modules: &'m BTreeMap<ModuleId, BuildModule>| {
+        modules
+            .values()
+            .filter_map(|module| module.content_hash.as_deref())
+            .collect::<BTreeSet<_>>()
+    };
+    let (all_old, all_new) = (content_hashes(old), content_hashes(new));
+
+    let differing =
+        |ids: &[ModuleId], modules: &'m BTreeMap<ModuleId, BuildModule>, other: &BTreeSet<&str>| {
+            ids.iter()
+                .filter_map(|id| modules.get(id)?.content_hash.as_deref())
+                .filter(|content_hash| !other.contains(content_hash))
+                .collect::<BTreeSet<_>>()
+        };
+
+    let removed = [modules.removed.as_slice(), &modules.changed].concat();
+    let added = [modules.added.as_slice(), &modules.changed].concat();
+    (
+        differing(&removed, old, &all_new),
+        differing(&added, new, &all_old),
+    )
+}
+
+/// Compares the string literals of two builds.
+///
+/// Only the modules that differ between the builds are parsed; unchanged
+/// modules are compared by content hash alone. A string that merely moved
+/// between a changed module and an unchanged one is therefore reported as
+/// added or removed.
+async fn diff_strings(
+    db: &Db,
+    old: &BTreeMap<ModuleId, BuildModule>,
+    new: &BTreeMap<ModuleId, BuildModule>,
+    modules: &ModuleChanges,
+) -> Result<SetChanges<String>> {
+    let (old_content_hashes, new_content_hashes) = differing_content_hashes(old, new, modules);
+    let old_strings = collect_strings(db, &old_content_hashes).await?;
+    let new_strings = collect_strings(db, &new_content_hashes).await?;
+
+    Ok(SetChanges::between(&old_strings, &new_strings))
+}
+
+fn diff_classes<'a>(old: &'a ClassModuleMap, new: &'a ClassModuleMap) -> Vec<ClassChange<'a>> {
+    let mut changes = vec![];
+
+    for (&module_id, new_mappings) in new {
+        let old_mappings = old.get(&module_id);
+        for (name, new_class) in new_mappings {
+            let old_class = old_mappings.and_then(|mappings| mappings.get(name));
+            if old_class != Some(new_class) {
+                changes.push(ClassChange {
+                    module_id,
+                    name,
+                    old: old_class.map(String::as_str),
+                    new: Some(new_class),
+                });
+            }
+        }
+    }
+
+    for (&module_id, old_mappings) in old {
+        let new_mappings = new.get(&module_id);
+        for (name, old_class) in old_mappings {
+            if !new_mappings.is_some_and(|mappings| mappings.contains_key(name)) {
+                changes.push(ClassChange {
+                    module_id,
+                    name,
+                    old: Some(old_class),
+                    new: None,
+                });
+            }
+        }
+    }
+
+    changes.sort_by_key(|change| (change.module_id, change.name));
+    changes
+}
+
+fn count_class_changes(changes: &[ClassChange<'_>]) -> ChangeCounts {
+    let mut counts = ChangeCounts::default();
+    for change in changes {
+        match (change.old, change.new) {
+            (None, _) => counts.added += 1,
+            (_, None) => counts.removed += 1,
+            _ => counts.changed += 1,
+        }
+    }
+    counts
+}
+
+/// Loads the result of a dumper that succeeded on a build, regardless of the
+/// options it was run with.
+async fn load_dump<T: DeserializeOwned>(
+    db: &Db,
+    build_hash: &str,
+    name: &str,
+) -> Result<Option<T>> {
+    let dumper = db
+        .dump_jobs_of_build(build_hash)
+        .await?
+        .into_iter()
+        .find(|job| job.status == "succeeded" && job.dumper.split(':').next() == Some(name));
+    let Some(dumper) = dumper else {
+        return Ok(None);
+    };
+
+    let Some((_, stored)) = db.dump_job_result(build_hash, &dumper.dumper).await? else {
+        return Ok(None);
+    };
+    let content = stored.read().await?;
+
+    let parsed = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse the result of {:?}", dumper.dumper))?;
+    Ok(Some(parsed))
+}
+
+/// Loads the results of a dumper for two builds, if both have one.
+async fn load_dumps<T: DeserializeOwned>(
+    db: &Db,
+    old_hash: &str,
+    new_hash: &str,
+    name: &str,
+) -> Result<Option<(T, T)>> {
+    let old = load_dump(db, old_hash, name).await?;
+    let new = load_dump(db, new_hash, name).await?;
+    Ok(old.zip(new))
+}
+
+/// Compares two catalogued builds.
+pub async fn diff_builds(db: &Db, old_hash: &str, new_hash: &str) -> Result<BuildDiff> {
+    let asset_names = |assets: Vec<AssetRecord>| {
+        assets
+            .into_iter()
+            .map(|asset| asset.name)
+            .collect::<BTreeSet<_>>()
+    };
+    let assets = SetChanges::between(
+        &asset_names(db.assets_of_build(old_hash).await?),
+        &asset_names(db.assets_of_build(new_hash).await?),
+    );
+
+    let old_modules = index_build_modules(db.modules_of_build(old_hash).await?);
+    let new_modules = index_build_modules(db.modules_of_build(new_hash).await?);
+
+    let (modules, strings) = match old_modules.zip(new_modules) {
+        Some((old, new)) => {
+            let modules = diff_modules(&old, &new);
+            let strings = match diff_strings(db, &old, &new, &modules).await {
+                Ok(strings) => Some(strings),
+                Err(err) => {
+                    tracing::warn!("failed to compare strings: {:#}", err);
+                    None
+                }
+            };
+            (Some(modules), strings)
+        }
+        None => (None, None),
+    };
+
+    let class_maps: Option<(ClassModuleMap, ClassModuleMap)> =
+        load_dumps(db, old_hash, new_hash, "classes").await?;
+    let classes = class_maps.as_ref().map(|(old, new)| diff_classes(old, new));
+
+    let experiment_maps: Option<(ExperimentMap, ExperimentMap)> =
+        load_dumps(db, old_hash, new_hash, "experiments").await?;
+    let experiments = experiment_maps
+        .as_ref()
+        .map(|(old, new)| diff_experiments(old, new));
+
+    let locale_maps: Option<(LocaleMessagesMap, LocaleMessagesMap)> =
+        load_dumps(db, old_hash, new_hash, "i18n").await?;
+    let messages = locale_maps.as_ref().and_then(|(old, new)| {
+        Some(diff_messages(
+            &old.get(DEFAULT_LOCALE)?.messages,
+            &new.get(DEFAULT_LOCALE)?.messages,
+        ))
+    });
+
+    let summary = DiffSummary {
+        assets: assets.counts(),
+        modules: modules.as_ref().map(|modules| ChangeCounts {
+            added: modules.added.len(),
+            removed: modules.removed.len(),
+            changed: modules.changed.len(),
+        }),
+        strings: strings.as_ref().map(SetChanges::counts),
+        classes: classes.as_deref().map(count_class_changes),
+        experiments: experiments.as_ref().map(|experiments| ChangeCounts {
+            added: experiments.added.len(),
+            removed: experiments.removed.len(),
+            changed: 0,
+        }),
+        messages: messages.as_ref().map(|messages| ChangeCounts {
+            added: messages.added.len(),
+            removed: messages.removed.len(),
+            changed: messages.changed.len(),
+        }),
+    };
+
+    let changes = serde_json::to_value(Changes {
+        assets,
+        modules,
+        strings,
+        classes,
+        experiments,
+        messages,
+    })?;
+
+    Ok(BuildDiff { summary, changes })
+}
+
+/// Compares two builds and stores the result, replacing any earlier result.
+pub async fn diff_and_store(db: &Db, old_hash: &str, new_hash: &str) -> Result<BuildDiff> {
+    let diff = diff_builds(db, old_hash, new_hash).await?;
+    db.store_build_diff(old_hash, new_hash, &diff).await?;
+    Ok(diff)
+}
+
+/// Recomputes the stored diffs involving a build, e.g. once its dumps are
+/// available.
+pub async fn refresh_diffs_of_build(db: &Db, build_hash: &str) -> Result<()> {
+    for (old_hash, new_hash) in db.diffs_involving(build_hash).await? {
+        diff_and_store(db, &old_hash, &new_hash).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(module_id: ModuleId, content_hash: &str, fingerprint: Option<&str>) -> BuildModule {
+        BuildModule {
+            module_id,
+            content_hash: Some(content_hash.to_owned()),
+            fingerprint: fingerprint.map(ToOwned::to_owned),
+        }
+    }
+
+    fn modules(modules: Vec<BuildModule>) -> BTreeMap<ModuleId, BuildModule> {
+        index_build_modules(modules).unwrap()
+    }
+
+    fn class_map(mappings: &[(ModuleId, &str, &str)]) -> ClassModuleMap {
+        let mut map = ClassModuleMap::new();
+        for (module_id, name, class) in mappings {
+            map.entry(*module_id)
+                .or_default()
+                .insert((*name).to_owned(), (*class).to_owned());
+        }
+        map
+    }
+
+    #[test]
+    fn diffs_modules() {
+        let old = modules(vec![
+            module(1, "a", Some("fa")),
+            module(2, "b", Some("fb")),
+            module(3, "c", None),
+            module(4, "d", None),
+        ]);
+        let new = modules(vec![
+            // Shuffled by minification, but structurally the same.
+            module(1, "a2", Some("fa")),
+            module(2, "b2", Some("fb2")),
+            module(3, "c2", None),
+            module(5, "e", None),
+        ]);
+
+        let changes = diff_modules(&old, &new);
+        assert_eq!(changes.added, vec![5]);
+        assert_eq!(changes.removed, vec![4]);
+        assert_eq!(changes.changed, vec![2, 3]);
+    }
+
+    #[test]
+    fn ignores_unindexed_modules() {
+        let unindexed = BuildModule {
+            module_id: 1,
+            content_hash: None,
+            fingerprint: None,
+        };
+        assert!(index_build_modules(vec![unindexed.clone()]).is_none());
+
+        let indexed = index_build_modules(vec![unindexed, module(2, "b", None)]).unwrap();
+        assert_eq!(indexed.keys().copied().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn selects_differing_content_hashes() {
+        let old = modules(vec![
+            module(1, "a", Some("fa")),
+            module(2, "b", Some("fb")),
+            module(3, "c", None),
+            module(4, "moved", None),
+        ]);
+        let new = modules(vec![
+            module(1, "a2", Some("fa")),
+            module(2, "b2", Some("fb2")),
+            module(5, "e", None),
+            module(6, "moved", None),
+        ]);
+
+        let changes = diff_modules(&old, &new);
+        let (old_content_hashes, new_content_hashes) =
+            differing_content_hashes(&old, &new, &changes);
+        assert_eq!(
+            old_content_hashes.into_iter().collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+        assert_eq!(
+            new_content_hashes.into_iter().collect::<Vec<_>>(),
+            vec!["b2", "e"]
+        );
+    }
+
+    #[test]
+    fn diffs_classes() {
+        let old = class_map(&[(1, "a", "a_1"), (1, "b", "b_1"), (2, "c", "c_1")]);
+        let new = class_map(&[(1, "a", "a_1"), (1, "b", "b_2"), (3, "d", "d_1")]);
+
+        let changes = diff_classes(&old, &new);
+        let summarized = changes
+            .iter()
+            .map(|change| (change.module_id, change.name, change.old, change.new))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summarized,
+            vec![
+                (1, "b", Some("b_1"), Some("b_2")),
+                (2, "c", Some("c_1"), None),
+                (3, "d", None, Some("d_1")),
+            ]
+        );
+
+        assert_eq!(
+            count_class_changes(&changes),
+            ChangeCounts {
+                added: 1,
+                removed: 1,
+                changed: 1,
+            }
+        );
+        assert!(diff_classes(&old, &old).is_empty());
+        assert_eq!(count_class_changes(&[]), ChangeCounts::default());
+    }
+
+    #[test]
+    fn describes_summaries() {
+        let counts = |added, removed, changed| ChangeCounts {
+            added,
+            removed,
+            changed,
+        };
+
+        let summary = DiffSummary {
+            assets: counts(3, 2, 0),
+            modules: Some(counts(34, 1, 87)),
+            strings: Some(counts(1, 4, 0)),
+            experiments: Some(counts(5, 2, 0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            summary.describe(),
+            "+34 modules, -1 module, 87 changed, 5 new experiments, 2 removed experiments, 1 new string"
+        );
+
+        let summary = DiffSummary {
+            assets: counts(1, 1, 0),
+            modules: Some(ChangeCounts::default()),
+            ..Default::default()
+        };
+        assert_eq!(summary.describe(), "No module changes");
+
+        let summary = DiffSummary {
+            assets: counts(1, 0, 0),
+            ..Default::default()
+        };
+        assert_eq!(summary.describe(), "1 new asset");
+    }
+}
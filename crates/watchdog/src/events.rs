@@ -0,0 +1,230 @@
+//! A feed of things happening within watchdog, for pushing to API consumers.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use havoc::discord::Branch;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::lineage::BuildChange;
+
+/// How many recent events are kept around for replaying to consumers that
+/// reconnect.
+const HISTORY_CAPACITY: usize = 256;
+
+/// Something that happened within watchdog.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A build was detected on a branch.
+    BuildDetected {
+        branch: Branch,
+        hash: String,
+        number: u32,
+        change: BuildChange,
+    },
+
+    /// The assets of a build were catalogued for the first time.
+    BuildCatalogued { hash: String, number: u32 },
+
+    /// A dumper finished running on a build.
+    DumpFinished {
+        hash: String,
+        dumper: String,
+        succeeded: bool,
+    },
+
+    /// Scraping a branch failed.
+    ScrapeError { branch: Branch, message: String },
+}
+
+impl Event {
+    /// Returns the name of the event's type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::BuildDetected { .. } => "build_detected",
+            Event::BuildCatalogued { .. } => "build_catalogued",
+            Event::DumpFinished { .. } => "dump_finished",
+            Event::ScrapeError { .. } => "scrape_error",
+        }
+    }
+}
+
+/// An event, alongside its sequential ID and when it was published.
+#[derive(Serialize, Debug, Clone)]
+pub struct Envelope {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+struct History {
+    next_id: u64,
+    recent: VecDeque<Arc<Envelope>>,
+}
+
+/// Broadcasts events to any number of subscribers, keeping a bounded history
+/// of recent events so that subscribers can catch up on what they missed.
+///
+/// Event IDs are only unique within a single run of watchdog, as the history
+/// isn't persisted.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Arc<Envelope>>,
+    history: Arc<Mutex<History>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(HISTORY_CAPACITY);
+
+        Self {
+            sender,
+            history: Arc::new(Mutex::new(History {
+                next_id: 1,
+                recent: VecDeque::with_capacity(HISTORY_CAPACITY),
+            })),
+        }
+    }
+
+    /// Publishes an event to all current subscribers.
+    pub fn publish(&self, event: Event) {
+        let mut history = self.history.lock().unwrap();
+
+        let envelope = Arc::new(Envelope {
+            id: history.next_id,
+            at: Utc::now(),
+            event,
+        });
+        history.next_id += 1;
+
+        if history.recent.len() == HISTORY_CAPACITY {
+            history.recent.pop_front();
+        }
+        history.recent.push_back(Arc::clone(&envelope));
+
+        // Sending while holding the lock guarantees that subscribers observe
+        // events in ID order. An error only means nobody is listening.
+        let _ = self.sender.send(envelope);
+    }
+
+    /// Subscribes to future events, also returning the recent events with an
+    /// ID greater than `after`.
+    ///
+    /// Events are never both replayed and received.
+    pub fn subscribe(
+        &self,
+        after: Option<u64>,
+    ) -> (Vec<Arc<Envelope>>, broadcast::Receiver<Arc<Envelope>>) {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+
+        let replayed = match after {
+            Some(after) => history
+                .recent
+                .iter()
+                .filter(|envelope| envelope.id > after)
+                .cloned()
+                .collect(),
+            None => vec![],
+        };
+
+        (replayed, receiver)
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalogued(number: u32) -> Event {
+        Event::BuildCatalogued {
+            hash: format!("hash{}", number),
+            number,
+        }
+    }
+
+    fn ids(envelopes: &[Arc<Envelope>]) -> Vec<u64> {
+        envelopes.iter().map(|envelope| envelope.id).collect()
+    }
+
+    #[test]
+    fn replays_events_after_an_id() {
+        let bus = EventBus::new();
+        for number in 1..=5 {
+            bus.publish(catalogued(number));
+        }
+
+        let (replayed, _) = bus.subscribe(Some(3));
+        assert_eq!(ids(&replayed), vec![4, 5]);
+
+        let (replayed, _) = bus.subscribe(Some(5));
+        assert!(replayed.is_empty());
+
+        let (replayed, _) = bus.subscribe(None);
+        assert!(replayed.is_empty());
+    }
+
+    #[test]
+    fn evicts_old_events() {
+        let bus = EventBus::new();
+        let published = HISTORY_CAPACITY as u64 + 10;
+        for number in 1..=published {
+            bus.publish(catalogued(number as u32));
+        }
+
+        let (replayed, _) = bus.subscribe(Some(0));
+        assert_eq!(replayed.len(), HISTORY_CAPACITY);
+        assert_eq!(replayed.first().unwrap().id, 11);
+        assert_eq!(replayed.last().unwrap().id, published);
+    }
+
+    #[test]
+    fn never_replays_and_sends_the_same_event() {
+        let bus = EventBus::new();
+        bus.publish(catalogued(1));
+        bus.publish(catalogued(2));
+
+        let (replayed, mut receiver) = bus.subscribe(Some(0));
+        bus.publish(catalogued(3));
+
+        assert_eq!(ids(&replayed), vec![1, 2]);
+        assert_eq!(receiver.try_recv().unwrap().id, 3);
+        assert!(matches!(
+            receiver.try_recv(),
+            Err(broadcast::error::TryRecvError::Empty)
+        ));
+    }
+
+    #[test]
+    fn never_loses_events_between_threads() {
+        let bus = EventBus::new();
+        let publisher = {
+            let bus = bus.clone();
+            std::thread::spawn(move || {
+                for number in 1..=100 {
+                    bus.publish(catalogued(number));
+                }
+            })
+        };
+
+        let (replayed, mut receiver) = bus.subscribe(Some(0));
+        publisher.join().unwrap();
+
+        let mut seen = ids(&replayed);
+        while let Ok(envelope) = receiver.try_recv() {
+            seen.push(envelope.id);
+        }
+        assert_eq!(seen, (1..=100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,235 @@
+//! Running dumpers on catalogued builds in the background.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use havoc::discord::AssetCache;
+use havoc::dump::{dump_concurrently, AnalysisContext, DumpResult, DumperInvocation};
+use tracing::Instrument;
+
+use crate::{
+    config::DumpsConfig,
+    db::{Db, StoredDump},
+    diff::refresh_diffs_of_build,
+    events::{Event, EventBus},
+    webhook::describe_diff_in_payload,
+};
+
+/// How long to wait before checking for pending jobs again, when there
+/// weren't any.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Runs pending dump jobs as they are queued, one build at a time.
+pub async fn run_dump_jobs_forever(config: &DumpsConfig, db: &Db, events: &EventBus) -> Result<()> {
+    let requeued = db.requeue_interrupted_dump_jobs().await?;
+    if requeued > 0 {
+        tracing::info!("resuming {} interrupted dump job(s)", requeued);
+    }
+
+    loop {
+        match db.next_pending_dump_jobs().await? {
+            Some((build_hash, dumpers)) => {
+                let span = tracing::info_span!("dump_jobs", build = %build_hash);
+                run_dump_jobs(config, db, events, &build_hash, &dumpers)
+                    .instrument(span.clone())
+                    .await?;
+
+                // Class mappings, experiments, and messages are compared using
+                // dumps, which are only now available.
+                if let Err(err) = refresh_diffs_of_build(db, &build_hash)
+                    .instrument(span.clone())
+                    .await
+                {
+                    tracing::warn!(build = %build_hash, "failed to refresh diffs: {:#}", err);
+                }
+
+                release_webhook_deliveries(db, &build_hash)
+                    .instrument(span)
+                    .await?;
+            }
+            None => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+/// Releases the webhook deliveries about a build that were held until it was
+/// dumped, describing the refreshed difference to the build that preceded it
+/// on each branch.
+async fn release_webhook_deliveries(db: &Db, build_hash: &str) -> Result<()> {
+    for detection in db.detections_of_build(build_hash).await? {
+        let Some(mut payload) = db
+            .unattempted_webhook_payload(build_hash, detection.branch)
+            .await?
+        else {
+            continue;
+        };
+
+        if let Some(previous_hash) = &detection.previous_hash {
+            if let Some(diff) = db.build_diff(previous_hash, build_hash).await? {
+                describe_diff_in_payload(&mut payload, &diff.summary);
+            }
+        }
+
+        db.release_webhook_deliveries(build_hash, detection.branch, &payload)
+            .await?;
+        tracing::info!(branch = ?detection.branch, "released webhook deliveries");
+    }
+
+    Ok(())
+}
+
+/// Runs dumpers on a build. Errors are only returned when recording the
+/// outcome of a job fails; failing jobs are recorded as such.
+async fn run_dump_jobs(
+    config: &DumpsConfig,
+    db: &Db,
+    events: &EventBus,
+    build_hash: &str,
+    dumpers: &[String],
+) -> Result<()> {
+    let mut invocations: Vec<(&String, DumperInvocation)> = vec![];
+    for dumper in dumpers {
+        db.dump_job_started(build_hash, dumper).await?;
+
+        // The config could have changed since the job was queued.
+        match dumper.parse::<DumperInvocation>() {
+            Ok(invocation) => invocations.push((dumper, invocation)),
+            Err(err) => finish(db, events, build_hash, dumper, Err(err.into())).await?,
+        }
+    }
+
+    if invocations.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!("running {} dumper(s)", invocations.len());
+
+    let results = match dump(db, build_hash, &invocations).await {
+        Ok(results) => results,
+        Err(err) => {
+            // Nothing could be dumped, so every job shares the same fate.
+            let message = format!("{:#}", err);
+            for (dumper, _) in &invocations {
+                finish(
+                    db,
+                    events,
+                    build_hash,
+                    dumper,
+                    Err(anyhow::anyhow!("{}", message)),
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+    };
+
+    for ((dumper, _), result) in invocations.iter().zip(results) {
+        let outcome = match result {
+            Ok(result) => store(config, build_hash, &result).await,
+            Err(err) => Err(err),
+        };
+        finish(db, events, build_hash, dumper, outcome).await?;
+    }
+
+    Ok(())
+}
+
+/// Fetches and parses the assets of a build, then runs dumpers on it.
+async fn dump(
+    db: &Db,
+    build_hash: &str,
+    invocations: &[(&String, DumperInvocation)],
+) -> Result<Vec<Result<DumpResult>>> {
+    let build = db
+        .surface_build(build_hash)
+        .await?
+        .context("build isn't catalogued")?;
+
+    let requirements = invocations
+        .iter()
+        .flat_map(|(_, invocation)| invocation.descriptor.requires)
+        .copied()
+        .collect::<Vec<_>>();
+
+    let mut cache = AssetCache::new();
+    let fetched = AnalysisContext::fetch(&build, &mut cache, &requirements)
+        .await
+        .context("failed to fetch assets for dumping")?;
+
+    // Unlike when cataloguing, scripts can't be parsed in batches of
+    // `PARSING_BATCH_SIZE`, since dumpers may look at any of them at once.
+    let cx = tokio::task::block_in_place(|| fetched.parse())
+        .context("failed to parse assets for dumping")?;
+
+    let invocations = invocations
+        .iter()
+        .map(|(_, invocation)| invocation.clone())
+        .collect::<Vec<_>>();
+    let results = tokio::task::block_in_place(|| dump_concurrently(&cx, &invocations));
+
+    Ok(results
+        .into_iter()
+        .map(|result| result.map_err(anyhow::Error::from))
+        .collect())
+}
+
+/// Stores the result of a dumper, either in the artifacts directory or in the
+/// database.
+async fn store(
+    config: &DumpsConfig,
+    build_hash: &str,
+    result: &DumpResult,
+) -> Result<(String, StoredDump)> {
+    let filename = result.filename();
+
+    let stored = match &config.artifacts_directory {
+        Some(directory) => {
+            let directory = directory.join(build_hash);
+            tokio::fs::create_dir_all(&directory)
+                .await
+                .with_context(|| format!("failed to create {:?}", directory))?;
+
+            let path = directory.join(&filename);
+            let content = result.writable_content()?.into_owned();
+            tokio::fs::write(&path, content)
+                .await
+                .with_context(|| format!("failed to write {:?}", path))?;
+
+            StoredDump::File(path)
+        }
+        None => StoredDump::Database(result.writable_content()?.into_owned()),
+    };
+
+    Ok((filename, stored))
+}
+
+async fn finish(
+    db: &Db,
+    events: &EventBus,
+    build_hash: &str,
+    dumper: &str,
+    outcome: Result<(String, StoredDump)>,
+) -> Result<()> {
+    let succeeded = outcome.is_ok();
+
+    match outcome {
+        Ok((filename, stored)) => {
+            tracing::info!(dumper, "dump succeeded");
+            db.dump_job_succeeded(build_hash, dumper, &filename, &stored)
+                .await?;
+        }
+        Err(err) => {
+            let message = format!("{:#}", err);
+            tracing::warn!(dumper, "dump failed: {}", message);
+            db.dump_job_failed(build_hash, dumper, &message).await?;
+        }
+    }
+
+    events.publish(Event::DumpFinished {
+        hash: build_hash.to_owned(),
+        dumper: dumper.to_owned(),
+        succeeded,
+    });
+
+    Ok(())
+}
@@ -1,6 +1,13 @@
 pub mod api;
 pub mod config;
 pub mod db;
+pub mod deliveries;
+pub mod diff;
+pub mod events;
+pub mod jobs;
+pub mod lineage;
+pub mod migrations;
+pub mod modules;
 pub mod scraping;
 pub mod subscription;
 pub mod webhook;
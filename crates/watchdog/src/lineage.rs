@@ -0,0 +1,177 @@
+use havoc::discord::Branch;
+use serde::Serialize;
+
+/// A build that was previously deployed to a branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownBuild {
+    pub hash: String,
+    pub number: u32,
+}
+
+/// How the build deployed to a branch changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BuildChange {
+    /// A build that has never been seen before on any branch.
+    New,
+
+    /// A build that has already been seen before replacing an older build,
+    /// usually because it moved from one branch to another (e.g. from Canary
+    /// to PTB).
+    Promotion {
+        /// The branches that the build was seen on before.
+        from: Vec<Branch>,
+    },
+
+    /// A build replacing a newer build.
+    Rollback {
+        /// The number of the build that was rolled back from.
+        from_number: u32,
+    },
+}
+
+impl BuildChange {
+    /// Classifies a build being deployed to a branch, given the build that was
+    /// previously deployed there and the branches the build has already been
+    /// seen on.
+    ///
+    /// The branch being deployed to is never reported as a branch that the
+    /// build was promoted from, even if the build was seen on it before.
+    pub fn classify(
+        branch: Branch,
+        number: u32,
+        previous: Option<&KnownBuild>,
+        mut previously_seen_on: Vec<Branch>,
+        previously_catalogued: bool,
+    ) -> Self {
+        match previous {
+            Some(previous) if number < previous.number => BuildChange::Rollback {
+                from_number: previous.number,
+            },
+            _ if previously_catalogued => {
+                previously_seen_on.retain(|seen_on| *seen_on != branch);
+                BuildChange::Promotion {
+                    from: previously_seen_on,
+                }
+            }
+            _ => BuildChange::New,
+        }
+    }
+
+    /// Returns the name of the kind of change, as stored in the database.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BuildChange::New => "new",
+            BuildChange::Promotion { .. } => "promotion",
+            BuildChange::Rollback { .. } => "rollback",
+        }
+    }
+
+    /// Returns a human-readable description of the change.
+    pub fn describe(&self) -> String {
+        match self {
+            BuildChange::New => "New build".to_owned(),
+            BuildChange::Promotion { from } if from.is_empty() => "Promoted".to_owned(),
+            BuildChange::Promotion { from } => format!(
+                "Promoted from {}",
+                from.iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            BuildChange::Rollback { from_number } => format!("Rolled back from {}", from_number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn known(number: u32) -> KnownBuild {
+        KnownBuild {
+            hash: format!("hash{}", number),
+            number,
+        }
+    }
+
+    #[test]
+    fn classifies_new_builds() {
+        let change = BuildChange::classify(Branch::Canary, 2, Some(&known(1)), vec![], false);
+        assert_eq!(change, BuildChange::New);
+
+        let change = BuildChange::classify(Branch::Canary, 1, None, vec![], false);
+        assert_eq!(change, BuildChange::New);
+    }
+
+    #[test]
+    fn classifies_promotions() {
+        let change =
+            BuildChange::classify(Branch::Ptb, 2, Some(&known(1)), vec![Branch::Canary], true);
+        assert_eq!(
+            change,
+            BuildChange::Promotion {
+                from: vec![Branch::Canary]
+            }
+        );
+    }
+
+    #[test]
+    fn excludes_current_branch_from_promotions() {
+        let change = BuildChange::classify(
+            Branch::Stable,
+            2,
+            Some(&known(1)),
+            vec![Branch::Ptb, Branch::Stable],
+            true,
+        );
+        assert_eq!(
+            change,
+            BuildChange::Promotion {
+                from: vec![Branch::Ptb]
+            }
+        );
+
+        // Re-deploying a build that was only seen on this branch.
+        let change = BuildChange::classify(
+            Branch::Stable,
+            2,
+            Some(&known(1)),
+            vec![Branch::Stable],
+            true,
+        );
+        assert_eq!(change, BuildChange::Promotion { from: vec![] });
+    }
+
+    #[test]
+    fn classifies_rollbacks() {
+        let change = BuildChange::classify(
+            Branch::Stable,
+            1,
+            Some(&known(2)),
+            vec![Branch::Stable],
+            true,
+        );
+        assert_eq!(change, BuildChange::Rollback { from_number: 2 });
+    }
+
+    #[test]
+    fn describes_changes() {
+        assert_eq!(BuildChange::New.describe(), "New build");
+        assert_eq!(
+            BuildChange::Promotion { from: vec![] }.describe(),
+            "Promoted"
+        );
+        assert_eq!(
+            BuildChange::Promotion {
+                from: vec![Branch::Canary, Branch::Ptb]
+            }
+            .describe(),
+            format!("Promoted from {}, {}", Branch::Canary, Branch::Ptb)
+        );
+        assert_eq!(
+            BuildChange::Rollback { from_number: 42 }.describe(),
+            "Rolled back from 42"
+        );
+    }
+}
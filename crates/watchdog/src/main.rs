@@ -1,22 +1,48 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use watchdog::config::Config;
+use anyhow::{bail, Context, Result};
+use havoc::dump::DumperInvocation;
+use watchdog::config::{Config, DumpsConfig};
 use watchdog::db::Db;
+use watchdog::events::EventBus;
+use watchdog::scraping::{branches_to_scrape, scrape_branch_forever, ScrapeStatuses};
 
 async fn run(config: Config) -> Result<()> {
-    tracing::info!("connecting to postgres: {}", config.postgres.url);
-
-    let pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(config.postgres.max_connections)
-        .connect(&config.postgres.url)
-        .await?;
-
-    let db = Db::new(pool);
-
-    spawn_indefinite_scraper(db.clone(), config.clone());
-
-    let state = watchdog::api::AppState { db: db.clone() };
+    for dumper in &config.dumps.dumpers {
+        dumper
+            .parse::<DumperInvocation>()
+            .with_context(|| format!("invalid dumper {:?} in config", dumper))?;
+    }
+
+    tracing::info!("parsing scripts with {} thread(s)", config.parsing_threads);
+    havoc::parse::set_parsing_threads(config.parsing_threads)
+        .context("failed to set up parsing threads")?;
+
+    let db = Db::connect(&config.database).await?;
+
+    if config.database.migrate_on_startup() {
+        db.migrate().await?;
+    } else {
+        let pending = db.pending_migrations().await?;
+        if !pending.is_empty() {
+            bail!(
+                "the database is missing {} migration(s), apply them with `watchdog migrate`",
+                pending.len()
+            );
+        }
+    }
+    let events = EventBus::new();
+    let scrape_statuses = ScrapeStatuses::new();
+
+    spawn_scrapers(db.clone(), events.clone(), scrape_statuses.clone(), &config);
+    spawn_dump_worker(db.clone(), events.clone(), config.dumps.clone());
+    spawn_delivery_worker(db.clone());
+
+    let state = watchdog::api::AppState {
+        db: db.clone(),
+        events,
+        scrape_statuses,
+    };
     let router = watchdog::api::create_router().with_state(state);
 
     tracing::info!(
@@ -30,48 +56,77 @@ async fn run(config: Config) -> Result<()> {
     Ok(())
 }
 
-fn spawn_indefinite_scraper(db: Db, config: Config) {
-    let supervisor = tokio::spawn(async move {
-        let default_backoff = Duration::from_secs(1);
-        let mut restart_backoff = default_backoff;
-        let mut last_backoff: Option<Instant> = None;
+/// Applies pending migrations, then exits.
+async fn migrate(config: Config) -> Result<()> {
+    let db = Db::connect(&config.database).await?;
 
-        loop {
-            let Err(err) = watchdog::scraping::scrape_forever(&config, &db).await else {
-                panic!("indefinite scraper terminated without an error (this should never happen)");
-            };
-            tracing::error!(?restart_backoff, "indefinite scraper died: {}", err);
+    match db.migrate().await? {
+        0 => tracing::info!("the database is up to date"),
+        applied => tracing::info!("applied {} migration(s)", applied),
+    }
 
-            // If it's been five minutes or longer since the last backoff, reset
-            // it back to the default wait time.
-            let resetting_backoff =
-                last_backoff.map_or(false, |last| last.elapsed() >= Duration::new(60 * 5, 0));
-            if resetting_backoff {
-                restart_backoff = default_backoff;
-            }
+    Ok(())
+}
 
-            tokio::time::sleep(restart_backoff).await;
+fn spawn_scrapers(db: Db, events: EventBus, statuses: ScrapeStatuses, config: &Config) {
+    for (branch, subscriptions) in branches_to_scrape(config) {
+        let scraper = tokio::spawn(scrape_branch_forever(
+            branch,
+            config.scrape_schedule(branch),
+            subscriptions,
+            config.dumps.dumpers.clone(),
+            db.clone(),
+            events.clone(),
+            statuses.clone(),
+        ));
+
+        tokio::spawn(async move {
+            let err = scraper.await.expect_err(
+                "branch scraper terminated without an error (this should never happen)",
+            );
+
+            tracing::error!(
+                ?err,
+                ?branch,
+                "branch scraper panicked! something is very wrong here, aborting: {}",
+                err
+            );
 
-            if !resetting_backoff {
-                restart_backoff = restart_backoff.checked_mul(2).unwrap_or(default_backoff);
-            }
+            std::process::exit(1);
+        });
+    }
+}
+
+fn spawn_dump_worker(db: Db, events: EventBus, config: DumpsConfig) {
+    if config.dumpers.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let restart_delay = Duration::from_secs(30);
 
-            last_backoff = Some(Instant::now());
+        loop {
+            let Err(err) = watchdog::jobs::run_dump_jobs_forever(&config, &db, &events).await
+            else {
+                panic!("dump worker terminated without an error (this should never happen)");
+            };
+            tracing::error!(?restart_delay, "dump worker died: {:#}", err);
+            tokio::time::sleep(restart_delay).await;
         }
     });
+}
 
+fn spawn_delivery_worker(db: Db) {
     tokio::spawn(async move {
-        let err = supervisor.await.expect_err(
-            "indefinite scraper supervisor terminated without an error (this should never happen)",
-        );
-
-        tracing::error!(
-            ?err,
-            "indefinite scraper panicked! something is very wrong here, aborting: {}",
-            err
-        );
+        let restart_delay = Duration::from_secs(30);
 
-        std::process::exit(1);
+        loop {
+            let Err(err) = watchdog::deliveries::run_webhook_deliveries_forever(&db).await else {
+                panic!("delivery worker terminated without an error (this should never happen)");
+            };
+            tracing::error!(?restart_delay, "delivery worker died: {:#}", err);
+            tokio::time::sleep(restart_delay).await;
+        }
     });
 }
 
@@ -79,10 +134,12 @@ fn spawn_indefinite_scraper(db: Db, config: Config) {
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let config_file_path = match std::env::args().nth(1) {
-        Some(path) => path,
-        None => {
-            eprintln!("usage: watchdog <path/to/config.toml>");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (migrating, config_file_path) = match args.as_slice() {
+        [path] => (false, path),
+        [command, path] if command == "migrate" => (true, path),
+        _ => {
+            eprintln!("usage: watchdog [migrate] <path/to/config.toml>");
             std::process::exit(1);
         }
     };
@@ -91,5 +148,9 @@ async fn main() -> Result<()> {
         std::fs::read_to_string(config_file_path).context("cannot read config file")?;
     let config: Config = toml::from_str(&config_file_text).context("cannot parse config file")?;
 
-    run(config).await
+    if migrating {
+        migrate(config).await
+    } else {
+        run(config).await
+    }
 }
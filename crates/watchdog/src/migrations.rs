@@ -0,0 +1,61 @@
+//! Keeping the database schema up to date.
+//!
+//! Migrations live in `migrations/<database>` and are embedded into the
+//! binary. Every Postgres migration can be applied on top of a database that
+//! was set up by hand from the old `schema.sql`, so such databases are adopted
+//! by migrating them like any other.
+
+use anyhow::{bail, Result};
+use sqlx::migrate::{Migrate, Migration, Migrator};
+
+pub static POSTGRES: Migrator = sqlx::migrate!("./migrations/postgres");
+pub static SQLITE: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Returns the migrations that haven't been applied to a database yet,
+/// without changing anything.
+///
+/// Errors if the applied migrations don't line up with the embedded ones,
+/// e.g. if a migration was changed after being applied, or if the database
+/// was migrated by a newer version of watchdog.
+pub async fn pending_migrations(
+    migrator: &'static Migrator,
+    conn: &mut impl Migrate,
+    has_migrations_table: bool,
+) -> Result<Vec<&'static Migration>> {
+    if !has_migrations_table {
+        return Ok(migrator.iter().collect());
+    }
+
+    if let Some(version) = conn.dirty_version().await? {
+        bail!("migration {} was only partially applied", version);
+    }
+
+    let applied = conn.list_applied_migrations().await?;
+    for applied in &applied {
+        match migrator
+            .iter()
+            .find(|migration| migration.version == applied.version)
+        {
+            Some(migration) if migration.checksum != applied.checksum => {
+                bail!(
+                    "migration {} was changed after being applied",
+                    applied.version
+                )
+            }
+            Some(_) => {}
+            None => bail!(
+                "migration {} was applied, but is unknown to this version of watchdog",
+                applied.version
+            ),
+        }
+    }
+
+    Ok(migrator
+        .iter()
+        .filter(|migration| {
+            !applied
+                .iter()
+                .any(|applied| applied.version == migration.version)
+        })
+        .collect())
+}
@@ -0,0 +1,92 @@
+//! Walking the Webpack modules out of script assets.
+
+use anyhow::Result;
+use havoc::{
+    discord::{AssetCache, FeAsset},
+    dump::ParsedScript,
+    parse::{
+        fingerprint::fingerprint_module, parse_scripts, walk_module_interface, ModuleId, ParseError,
+    },
+};
+use sha2::{Digest, Sha256};
+
+/// How many scripts are fetched and parsed at once. Parsed scripts are
+/// memory hungry, so this keeps only a handful of them around at a time.
+pub const PARSING_BATCH_SIZE: usize = 16;
+
+/// A Webpack module walked out of a script.
+#[derive(Debug, Clone)]
+pub struct WalkedModule {
+    pub id: ModuleId,
+    pub source: String,
+
+    /// The hexadecimal SHA-256 hash of the source.
+    pub content_hash: String,
+
+    /// See [`fingerprint_module`].
+    pub fingerprint: String,
+
+    /// The names that the module exports, sorted.
+    pub exports: Vec<String>,
+}
+
+/// Parses scripts as Webpack chunks, returning the modules within each sorted
+/// by ID, in the same order as the scripts.
+///
+/// This blocks the current thread until the parsing thread pool is done.
+pub fn walk_modules(scripts: Vec<(FeAsset, String)>) -> Vec<Result<Vec<WalkedModule>, ParseError>> {
+    let labelled = scripts
+        .iter()
+        .map(|(asset, source)| (asset.filename(), source.as_str()))
+        .collect::<Vec<_>>();
+    let parsed = parse_scripts(&labelled);
+
+    scripts
+        .into_iter()
+        .zip(parsed)
+        .map(|((asset, source), script)| {
+            let parsed = ParsedScript {
+                asset,
+                source,
+                script: script?,
+            };
+            let chunk = parsed.webpack_chunk()?;
+
+            let mut modules: Vec<WalkedModule> = chunk
+                .modules
+                .values()
+                .map(|module| {
+                    let source = parsed.module_source(module).to_owned();
+                    let mut exports: Vec<String> =
+                        walk_module_interface(module).exports.into_keys().collect();
+                    exports.sort_unstable();
+
+                    WalkedModule {
+                        id: module.id,
+                        content_hash: hex::encode(Sha256::digest(&source)),
+                        fingerprint: fingerprint_module(module),
+                        source,
+                        exports,
+                    }
+                })
+                .collect();
+            modules.sort_unstable_by_key(|module| module.id);
+
+            Ok(modules)
+        })
+        .collect()
+}
+
+/// Fetches scripts and walks the modules within each, in the same order.
+pub async fn fetch_and_walk_modules(
+    scripts: &[FeAsset],
+    cache: &mut AssetCache,
+) -> Result<Vec<Result<Vec<WalkedModule>, ParseError>>> {
+    let mut sources: Vec<(FeAsset, String)> = Vec::with_capacity(scripts.len());
+    for script in scripts {
+        let content = cache.raw_content(script).await?;
+        sources.push((script.clone(), std::str::from_utf8(content)?.to_owned()));
+    }
+
+    Ok(tokio::task::block_in_place(|| walk_modules(sources)))
+}
@@ -1,75 +1,407 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use havoc::discord::{AssetCache, Branch};
+use rand::Rng;
+use serde::Serialize;
 use tracing::Instrument;
 
-use crate::{config::Config, db::Db, subscription::Subscription};
+use crate::{
+    config::{Config, ScrapeSchedule},
+    db::{Db, NewWebhookDeliveries},
+    diff::diff_and_store,
+    events::{Event, EventBus},
+    lineage::BuildChange,
+    subscription::Subscription,
+    webhook::build_webhook_payload,
+};
 use havoc::scrape;
 
+/// How long webhook deliveries about a detected build are held back for while
+/// the build is catalogued, compared, and dumped, at most.
+const DELIVERY_HOLD: Duration = Duration::from_secs(15 * 60);
+
+/// Locks on the build hashes that are being classified and catalogued.
+static BUILD_LOCKS: Mutex<BTreeMap<String, Arc<tokio::sync::Mutex<()>>>> =
+    Mutex::new(BTreeMap::new());
+
+/// Waits to be the only scraper handling a build hash. Branches often deploy
+/// the same build at once, which would otherwise both classify it as new and
+/// catalogue it.
+async fn lock_build(hash: &str) -> tokio::sync::OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = BUILD_LOCKS.lock().unwrap();
+        // Forget the locks that nobody holds or is waiting on.
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        locks.entry(hash.to_owned()).or_default().clone()
+    };
+
+    lock.lock_owned().await
+}
+
 pub async fn detect_changes_on_branch(
     db: &Db,
+    events: &EventBus,
     branch: Branch,
     subscriptions: &[&Subscription],
+    dumpers: &[String],
 ) -> Result<()> {
     let manifest = scrape::scrape_fe_manifest(branch).await?;
     let mut cache = AssetCache::new();
 
-    if db.last_known_build_hash_on_branch(branch).await? == Some(manifest.hash.clone()) {
+    let previous = db.last_known_build_on_branch(branch).await?;
+    if previous.as_ref().map(|previous| &previous.hash) == Some(&manifest.hash) {
         tracing::trace!("{} is stale", branch);
         return Ok(());
     }
 
     let build = scrape::scrape_fe_build(manifest, &mut cache).await?;
 
+    let build_lock = lock_build(&build.manifest.hash).await;
+    let build_was_previously_catalogued = db.build_hash_is_catalogued(&build.manifest.hash).await?;
+    let previously_seen_on = if build_was_previously_catalogued {
+        db.branches_with_build(&build.manifest.hash).await?
+    } else {
+        vec![]
+    };
+    let change = BuildChange::classify(
+        branch,
+        build.number,
+        previous.as_ref(),
+        previously_seen_on,
+        build_was_previously_catalogued,
+    );
+
     tracing::info!(
-        "detected new build (branch: {}, number: {})",
+        "detected new build (branch: {}, number: {}, change: {})",
         branch,
         build.number,
+        change.kind(),
     );
 
-    let build_was_previously_catalogued = db.build_hash_is_catalogued(&build.manifest.hash).await?;
-    db.detected_build_change_on_branch(&build, branch).await?;
+    let webhook_urls = subscriptions
+        .iter()
+        .map(|subscription| subscription.discord_webhook_url.clone())
+        .collect::<Vec<_>>();
+    let deliveries = NewWebhookDeliveries {
+        webhook_urls: &webhook_urls,
+        payload: &build_webhook_payload(&build, &change, None),
+        due_at: Utc::now() + chrono::Duration::from_std(DELIVERY_HOLD)?,
+    };
+    db.detected_build_change_on_branch(&build, branch, &change, previous.as_ref(), deliveries)
+        .await?;
+    if !webhook_urls.is_empty() {
+        tracing::info!("queued {} webhook delivery(s)", webhook_urls.len());
+    }
+    events.publish(Event::BuildDetected {
+        branch,
+        hash: build.manifest.hash.clone(),
+        number: build.number,
+        change: change.clone(),
+    });
 
     if !build_was_previously_catalogued {
         db.catalog_and_extract_assets(&build, &mut cache).await?;
+        events.publish(Event::BuildCatalogued {
+            hash: build.manifest.hash.clone(),
+            number: build.number,
+        });
+        db.enqueue_dump_jobs(&build.manifest.hash, dumpers).await?;
     } else {
         tracing::info!(?branch, ?build.number, ?build.manifest.hash, "avoiding build asset scrape, already in database");
     }
+    drop(build_lock);
 
-    for subscription in subscriptions {
-        crate::webhook::post_build_to_webhook(&build, subscription)
-            .await
-            .context("failed to publish")?;
+    let diff = match &previous {
+        Some(previous) => match diff_and_store(db, &previous.hash, &build.manifest.hash).await {
+            Ok(diff) => {
+                tracing::info!(
+                    "compared to build {}: {}",
+                    previous.number,
+                    diff.summary.describe()
+                );
+                Some(diff)
+            }
+            Err(err) => {
+                tracing::warn!("failed to compare to build {}: {:#}", previous.number, err);
+                None
+            }
+        },
+        None => None,
+    };
+    let summary = diff.as_ref().map(|diff| &diff.summary);
+
+    // Newly queued dump jobs release the deliveries once they've finished, so
+    // that the difference they describe includes experiments and the like.
+    let awaiting_dumps = !build_was_previously_catalogued && !dumpers.is_empty();
+
+    if !webhook_urls.is_empty() && !awaiting_dumps {
+        let payload = build_webhook_payload(&build, &change, summary);
+        db.release_webhook_deliveries(&build.manifest.hash, branch, &payload)
+            .await?;
     }
 
     Ok(())
 }
 
-pub async fn scrape_forever(config: &Config, db: &Db) -> Result<()> {
-    // Go from [subscription] to {branch: [subscription]}.
-    let mut branches: HashMap<Branch, Vec<&Subscription>> = HashMap::new();
+/// How long to back off for at most after consecutive failures.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// The state of scraping a branch.
+#[derive(Serialize, Debug, Clone)]
+pub struct ScrapeStatus {
+    pub branch: Branch,
+    pub interval_milliseconds: u64,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<ScrapeFailure>,
+    pub consecutive_failures: u32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ScrapeFailure {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// Tracks the scrape status of every branch being scraped. Statuses only
+/// live in memory, and start out empty when watchdog starts.
+#[derive(Clone, Default)]
+pub struct ScrapeStatuses {
+    statuses: Arc<Mutex<HashMap<Branch, ScrapeStatus>>>,
+}
+
+impl ScrapeStatuses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, branch: Branch, schedule: ScrapeSchedule) {
+        self.statuses.lock().unwrap().insert(
+            branch,
+            ScrapeStatus {
+                branch,
+                interval_milliseconds: schedule.interval.as_millis() as u64,
+                last_attempt_at: None,
+                last_success_at: None,
+                last_error: None,
+                consecutive_failures: 0,
+                next_attempt_at: None,
+            },
+        );
+    }
+
+    fn update(&self, branch: Branch, f: impl FnOnce(&mut ScrapeStatus)) {
+        if let Some(status) = self.statuses.lock().unwrap().get_mut(&branch) {
+            f(status);
+        }
+    }
+
+    /// Returns the status of a branch, if it's being scraped.
+    pub fn get(&self, branch: Branch) -> Option<ScrapeStatus> {
+        self.statuses.lock().unwrap().get(&branch).cloned()
+    }
+
+    /// Returns the statuses of all branches being scraped, ordered by branch.
+    pub fn all(&self) -> Vec<ScrapeStatus> {
+        let mut statuses = self
+            .statuses
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        statuses.sort_by_key(|status| status.branch.to_string());
+        statuses
+    }
+}
+
+/// Returns the branches to scrape, alongside the subscriptions that want
+/// to hear about them.
+pub fn branches_to_scrape(config: &Config) -> HashMap<Branch, Vec<Subscription>> {
+    let mut branches: HashMap<Branch, Vec<Subscription>> = config
+        .branches
+        .keys()
+        .map(|&branch| (branch, vec![]))
+        .collect();
+
     for subscription in &config.subscriptions {
         for branch in &subscription.branches {
             branches
                 .entry(*branch)
-                .or_insert_with(Vec::new)
-                .push(subscription);
+                .or_default()
+                .push(subscription.clone());
         }
     }
 
-    tracing::info!(?branches, "scraping continuously");
+    branches
+}
+
+/// Returns how long to wait before scraping again. Consecutive failures
+/// exponentially lengthen the wait, up to [`MAX_BACKOFF`] (or the interval,
+/// if that's even longer).
+fn delay(schedule: ScrapeSchedule, consecutive_failures: u32) -> Duration {
+    let backoff = schedule
+        .interval
+        .saturating_mul(2u32.saturating_pow(consecutive_failures))
+        .min(MAX_BACKOFF)
+        .max(schedule.interval);
+
+    let jitter = if schedule.jitter.is_zero() {
+        Duration::ZERO
+    } else {
+        rand::thread_rng().gen_range(Duration::ZERO..=schedule.jitter)
+    };
+
+    backoff + jitter
+}
+
+/// Scrapes a branch on its schedule, forever. Failures are recorded and
+/// backed off from, but never stop the scraping.
+pub async fn scrape_branch_forever(
+    branch: Branch,
+    schedule: ScrapeSchedule,
+    subscriptions: Vec<Subscription>,
+    dumpers: Vec<String>,
+    db: Db,
+    events: EventBus,
+    statuses: ScrapeStatuses,
+) {
+    let subscriptions = subscriptions.iter().collect::<Vec<_>>();
+    statuses.register(branch, schedule);
+
+    tracing::info!(
+        ?branch,
+        ?schedule,
+        subscriptions = subscriptions.len(),
+        "scraping continuously"
+    );
 
     loop {
-        for (&branch, subscriptions) in &branches {
-            let scrape_span = tracing::info_span!("scrape", ?branch);
-            detect_changes_on_branch(db, branch, subscriptions)
-                .instrument(scrape_span)
-                .await?;
+        let attempted_at = Utc::now();
+        let scrape_span = tracing::info_span!("scrape", ?branch);
+        let result = detect_changes_on_branch(&db, &events, branch, &subscriptions, &dumpers)
+            .instrument(scrape_span)
+            .await;
+
+        let mut consecutive_failures = 0;
+        statuses.update(branch, |status| {
+            status.last_attempt_at = Some(attempted_at);
+
+            match &result {
+                Ok(()) => {
+                    status.last_success_at = Some(Utc::now());
+                    status.consecutive_failures = 0;
+                }
+                Err(err) => {
+                    status.last_error = Some(ScrapeFailure {
+                        at: Utc::now(),
+                        message: format!("{:#}", err),
+                    });
+                    status.consecutive_failures += 1;
+                }
+            }
+
+            consecutive_failures = status.consecutive_failures;
+        });
+
+        let delay = delay(schedule, consecutive_failures);
+
+        if let Err(err) = result {
+            tracing::error!(
+                ?branch,
+                consecutive_failures,
+                ?delay,
+                "failed to scrape: {:#}",
+                err
+            );
+            events.publish(Event::ScrapeError {
+                branch,
+                message: format!("{:#}", err),
+            });
+        }
+
+        statuses.update(branch, |status| {
+            status.next_attempt_at = chrono::Duration::from_std(delay)
+                .ok()
+                .map(|delay| Utc::now() + delay);
+        });
+
+        tracing::trace!(?branch, "sleeping for {:?}", delay);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn locks_builds_by_hash() {
+        let held = lock_build("a").await;
+
+        // Other builds aren't held up.
+        drop(lock_build("b").await);
+
+        let waiting = tokio::spawn(lock_build("a"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        drop(held);
+        let reacquired = tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .expect("lock wasn't released")
+            .unwrap();
+        drop(reacquired);
+
+        // Released locks are forgotten.
+        drop(lock_build("c").await);
+        assert!(!BUILD_LOCKS.lock().unwrap().contains_key("a"));
+    }
+
+    fn schedule(interval: Duration) -> ScrapeSchedule {
+        ScrapeSchedule {
+            interval,
+            jitter: Duration::ZERO,
         }
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let minute = Duration::from_secs(60);
+        let schedule = schedule(minute);
+
+        assert_eq!(delay(schedule, 0), minute);
+        assert_eq!(delay(schedule, 1), minute * 2);
+        assert_eq!(delay(schedule, 2), minute * 4);
+        assert_eq!(delay(schedule, 5), minute * 32);
+        assert_eq!(delay(schedule, 6), MAX_BACKOFF);
+        assert_eq!(delay(schedule, u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn never_waits_less_than_the_interval() {
+        let interval = MAX_BACKOFF * 2;
 
-        tracing::trace!("sleeping for {}ms", config.interval_milliseconds);
-        let duration = std::time::Duration::from_millis(config.interval_milliseconds);
-        tokio::time::sleep(duration).await;
+        assert_eq!(delay(schedule(interval), 0), interval);
+        assert_eq!(delay(schedule(interval), 3), interval);
+    }
+
+    #[test]
+    fn adds_bounded_jitter() {
+        let schedule = ScrapeSchedule {
+            interval: Duration::from_secs(60),
+            jitter: Duration::from_secs(5),
+        };
+
+        for _ in 0..100 {
+            let delay = delay(schedule, 0);
+            assert!(delay >= schedule.interval);
+            assert!(delay <= schedule.interval + schedule.jitter);
+        }
     }
 }
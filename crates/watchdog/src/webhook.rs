@@ -1,15 +1,48 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use chrono::Utc;
 use havoc::discord::{self, AssetsExt, FeAsset, FeAssetType};
+use isahc::config::Configurable;
 use isahc::{AsyncReadResponseExt, Request, RequestExt};
+use serde::Deserialize;
+
+use crate::{diff::DiffSummary, lineage::BuildChange};
+
+/// How long to wait after being rate limited, if Discord doesn't say.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// How long to wait for Discord to respond to a post. Deliveries are sent one
+/// at a time, so one that hangs would hold up every other.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
-use crate::subscription::Subscription;
+/// What happened when posting to a webhook.
+#[derive(Debug, Clone)]
+pub enum DeliveryOutcome {
+    Delivered,
+
+    /// Discord asked to wait before posting to the webhook again.
+    RateLimited(Duration),
+
+    /// Posting failed, but might succeed if tried again.
+    Failed(String),
+
+    /// Discord refused the post, and would refuse it again (e.g. because the
+    /// webhook was deleted).
+    Rejected(String),
+}
 
-#[tracing::instrument(skip_all, fields(%build.manifest.branch, %build.number, ?subscription))]
-pub async fn post_build_to_webhook(
+#[derive(Deserialize)]
+struct RateLimitResponse {
+    retry_after: f64,
+}
+
+/// Builds the payload to post to webhooks about a detected build.
+pub fn build_webhook_payload(
     build: &discord::FeBuild,
-    subscription: &Subscription,
-) -> Result<()> {
+    change: &BuildChange,
+    diff: Option<&DiffSummary>,
+) -> serde_json::Value {
     use serde_json::json;
 
     let assets = &build.manifest.assets;
@@ -46,36 +79,154 @@ pub async fn post_build_to_webhook(
         .with_timezone(&chrono_tz::America::Los_Angeles)
         .format("%b %-d, %-H:%M (%a)");
 
+    let mut fields = vec![
+        json!({"name": "Scripts", "value": scripts_listing, "inline": false}),
+        json!({"name": "Styles", "value": styles_listing, "inline": false}),
+    ];
+    if let Some(diff) = diff {
+        fields.push(json!({"name": "Changes", "value": diff.describe(), "inline": false}));
+    }
+
     let embed = json!({
         "title": format!("{} {}", build.manifest.branch, build.number),
         "color": build.manifest.branch.color(),
-        "description": format!("Hash: `{}`", build.manifest.hash),
-        "fields": [
-            {"name": "Scripts", "value": scripts_listing, "inline": false},
-            {"name": "Styles", "value": styles_listing, "inline": false},
-        ],
+        "description": format!("{}\nHash: `{}`", change.describe(), build.manifest.hash),
+        "fields": fields,
         "footer": {"text": format!("Pacific: {}", pacific_time)},
         "timestamp": utc_timestamp
     });
 
-    let payload = json!({ "username": "watchdog", "embeds": [embed] });
+    json!({ "username": "watchdog", "embeds": [embed] })
+}
 
+/// Describes a difference in a payload built by [`build_webhook_payload`],
+/// replacing any difference it already describes.
+pub fn describe_diff_in_payload(payload: &mut serde_json::Value, diff: &DiffSummary) {
+    let Some(fields) = payload["embeds"][0]["fields"].as_array_mut() else {
+        return;
+    };
+
+    fields.retain(|field| field["name"] != "Changes");
+    fields.push(serde_json::json!({"name": "Changes", "value": diff.describe(), "inline": false}));
+}
+
+/// Posts a payload to a webhook.
+#[tracing::instrument(skip_all)]
+pub async fn post_to_webhook(webhook_url: &str, payload: &serde_json::Value) -> DeliveryOutcome {
+    match send(webhook_url, payload).await {
+        Ok(outcome) => outcome,
+        // This includes timing out, after which the post might succeed if
+        // tried again.
+        Err(err) => DeliveryOutcome::Failed(format!("{:#}", err)),
+    }
+}
+
+/// Determines how long Discord asked to wait for after being rate limited.
+///
+/// Discord specifies it in the body, in seconds, and also in the `Retry-After`
+/// header.
+fn retry_after(body: &str, header: Option<&str>) -> Duration {
+    serde_json::from_str::<RateLimitResponse>(body)
+        .ok()
+        .map(|response| response.retry_after)
+        .or_else(|| header?.trim().parse().ok())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+}
+
+async fn send(webhook_url: &str, payload: &serde_json::Value) -> Result<DeliveryOutcome> {
     tracing::debug!(?payload, "webhook payload");
 
-    let mut response = Request::post(&subscription.discord_webhook_url)
+    let mut response = Request::post(webhook_url)
+        .timeout(REQUEST_TIMEOUT)
         .header("content-type", "application/json")
         .header(
             "user-agent",
             "watchdog/0.0 (https://github.com/slice/havoc)",
         )
-        .body(serde_json::to_vec(&payload)?)?
+        .body(serde_json::to_vec(payload)?)?
         .send_async()
         .await?;
 
-    tracing::info!("received {} from discord", response.status());
+    let status = response.status();
+    tracing::info!("received {} from discord", status);
 
     let body_string = response.text().await?;
-    tracing::info!("discord response body: {}", body_string);
+    tracing::debug!("discord response body: {}", body_string);
+
+    if status.is_success() {
+        return Ok(DeliveryOutcome::Delivered);
+    }
+
+    if status.as_u16() == 429 {
+        let header = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok());
+        return Ok(DeliveryOutcome::RateLimited(retry_after(
+            &body_string,
+            header,
+        )));
+    }
+
+    let message = format!("discord responded with {}: {}", status, body_string);
+    Ok(if status.is_client_error() {
+        DeliveryOutcome::Rejected(message)
+    } else {
+        DeliveryOutcome::Failed(message)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ChangeCounts;
+
+    #[test]
+    fn describes_diffs_in_payloads() {
+        let mut payload = serde_json::json!({
+            "embeds": [{
+                "fields": [
+                    {"name": "Scripts", "value": "…", "inline": false},
+                    {"name": "Changes", "value": "1 new asset", "inline": false},
+                ],
+            }],
+        });
+
+        let diff = DiffSummary {
+            experiments: Some(ChangeCounts {
+                added: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        describe_diff_in_payload(&mut payload, &diff);
+
+        assert_eq!(
+            payload["embeds"][0]["fields"],
+            serde_json::json!([
+                {"name": "Scripts", "value": "…", "inline": false},
+                {"name": "Changes", "value": "2 new experiments", "inline": false},
+            ])
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(
+            retry_after(r#"{"retry_after": 1.5, "global": false}"#, Some("3")),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(retry_after("", Some("3")), Duration::from_secs(3));
+        assert_eq!(
+            retry_after("<html>", Some(" 0.25 ")),
+            Duration::from_millis(250)
+        );
+        assert_eq!(retry_after("", Some("soon")), DEFAULT_RETRY_AFTER);
+        assert_eq!(retry_after("", None), DEFAULT_RETRY_AFTER);
+        assert_eq!(
+            retry_after(r#"{"retry_after": -1}"#, None),
+            DEFAULT_RETRY_AFTER
+        );
+    }
 }